@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Completion } from "./Completion";
+
+export type AutocompleteResults = { query: string, 
+/**
+ * Recent queries first, followed by titles & then index terms
+ */
+completions: Array<Completion>, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TextSpan } from "./TextSpan";
+
+/**
+ * Document segment used to answer a question
+ */
+export type ChatCitation = { 
+/**
+ * Number the segment is cited by in the answer, e.g. [1]
+ */
+index: number, doc_id: string, title: string, open_url: string, 
+/**
+ * Location of the segment in the document content
+ */
+segment: TextSpan, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatCitation } from "./ChatCitation";
+import type { ChatMessage } from "./ChatMessage";
+
+export type ChatResponse = { chat_id: string, message: ChatMessage, 
+/**
+ * Segments cited in the answer, or every segment added to the prompt if the
+ * answer doesn't cite any.
+ */
+citations: Array<ChatCitation>, 
+/**
+ * Generation was stopped early by `cancel_chat`
+ */
+cancelled: boolean, };
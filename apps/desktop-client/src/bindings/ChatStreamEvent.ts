@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatStream } from "./ChatStream";
+
+/**
+ * Streamed event for a single chat
+ */
+export type ChatStreamEvent = { chat_id: string, event: ChatStream, };
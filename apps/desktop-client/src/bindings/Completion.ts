@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CompletionSource } from "./CompletionSource";
+
+export type Completion = { text: string, source: CompletionSource, };
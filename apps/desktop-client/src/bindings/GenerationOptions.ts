@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Generation settings that can be changed per request, unset values fall back
+ * to the LLM settings.
+ */
+export type GenerationOptions = { temperature: number | null, top_p: number | null, max_tokens: number | null, stop_sequences: Array<string> | null, };
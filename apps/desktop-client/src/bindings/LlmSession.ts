@@ -1,4 +1,21 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { ChatMessage } from "./ChatMessage";
+import type { GenerationOptions } from "./GenerationOptions";
 
-export type LlmSession = { messages: Array<ChatMessage>, };
+export type LlmSession = { messages: Array<ChatMessage>, 
+/**
+ * Only use documents in these lenses to answer
+ */
+lenses: Array<string>, 
+/**
+ * Only use these documents to answer, e.g. when asking about a specific doc
+ */
+doc_ids: Array<string>, 
+/**
+ * Overrides the generation settings for this request
+ */
+options: GenerationOptions, 
+/**
+ * Tags the streamed events & is used to cancel the chat, generated if unset
+ */
+chat_id: string | null, };
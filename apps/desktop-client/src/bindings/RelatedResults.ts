@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchResult } from "./SearchResult";
+
+export type RelatedResults = { doc_id: string, 
+/**
+ * Most related documents first
+ */
+results: Array<SearchResult>, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FacetCount } from "./FacetCount";
+
+export type SearchFacets = { lenses: Array<FacetCount>, sources: Array<FacetCount>, file_types: Array<FacetCount>, mime_types: Array<FacetCount>, domains: Array<FacetCount>, };
@@ -1,3 +1,16 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type SearchMeta = { query: string, num_docs: number, wall_time_ms: number, };
+export type SearchMeta = { query: string, num_docs: number, wall_time_ms: number, 
+/**
+ * Results include typo tolerant matches since too few docs matched exactly
+ */
+is_fuzzy: boolean, 
+/**
+ * Number of documents matching the query
+ */
+total_hits: number, 
+/**
+ * Pass this w/ the same query to get the next page of results, empty when
+ * there are no more results.
+ */
+next_cursor: string | null, };
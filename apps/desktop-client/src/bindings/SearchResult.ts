@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TextSpan } from "./TextSpan";
 
 export type SearchResult = { 
 /**
@@ -8,4 +9,18 @@ doc_id: string,
 /**
  * URI used to crawl this result
  */
-crawl_uri: string, domain: string, title: string, description: string, url: string, tags: Array<[string, string]>, score: number, };
+crawl_uri: string, domain: string, title: string, description: string, url: string, tags: Array<[string, string]>, score: number, 
+/**
+ * URLs of near-duplicates (e.g. mirrors) collapsed into this result
+ */
+also_at: Array<string>, 
+/**
+ * Set when the description is the passage that best matched the query
+ * semantically, rather than a highlight of the query terms.
+ */
+semantic_match: boolean, 
+/**
+ * Location of the passage that best matched the query semantically, if the
+ * document was found through similarity search.
+ */
+passage: TextSpan | null, };
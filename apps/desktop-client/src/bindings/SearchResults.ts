@@ -1,5 +1,10 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchFacets } from "./SearchFacets";
 import type { SearchMeta } from "./SearchMeta";
 import type { SearchResult } from "./SearchResult";
 
-export type SearchResults = { results: Array<SearchResult>, meta: SearchMeta, };
+export type SearchResults = { results: Array<SearchResult>, meta: SearchMeta, facets: SearchFacets, 
+/**
+ * Spelling correction for the query, e.g. for a "Did you mean" prompt
+ */
+suggestion: string | null, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Character offsets into the content of a document
+ */
+export type TextSpan = { 
+/**
+ * Offset of the first character
+ */
+start: number, 
+/**
+ * Offset after the last character
+ */
+end: number, };
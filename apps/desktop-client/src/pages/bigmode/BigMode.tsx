@@ -20,6 +20,7 @@ import {
 import { Btn } from "../../components/Btn";
 import { BtnType } from "../../components/_constants";
 import { ChatStream } from "../../bindings/ChatStream";
+import { ChatStreamEvent } from "../../bindings/ChatStreamEvent";
 import { marked } from "marked";
 
 enum Tab {
@@ -182,7 +183,7 @@ export function BigMode() {
           ) : null}
         </div>
       ) : null}
-      {activeTab == Tab.Chat ? <AskClippy /> : null}
+      {activeTab == Tab.Chat ? <AskClippy lenses={selectedLenses} /> : null}
     </div>
   );
 }
@@ -247,7 +248,11 @@ function ChatLog({ history }: ChatLogProps) {
   );
 }
 
-function AskClippy() {
+interface AskClippyProps {
+  lenses: string[];
+}
+
+function AskClippy({ lenses }: AskClippyProps) {
   const clippyInput = useRef<HTMLTextAreaElement>(null);
 
   const [isStreaming, setIsStreaming] = useState<boolean>(false);
@@ -258,9 +263,13 @@ function AskClippy() {
     { role: "assistant", content: "My name is Clippy." },
   ]);
   const [status, setStatus] = useState<string>("");
+  // Id of the chat being streamed, events for any other chat are ignored.
+  const chatId = useRef<string | null>(null);
 
   const handleChatEvent = (event: ChatStream) => {
-    if (event.type === "LoadingPrompt") {
+    if (event.type === "DocumentContextAdded") {
+      setStatus(`Found ${event.content.length} relevant sources...`);
+    } else if (event.type === "LoadingPrompt") {
       setStatus("Generating response...");
     } else if (event.type === "Token") {
       setTokens((toks) => [...toks, event.content]);
@@ -281,7 +290,17 @@ function AskClippy() {
     ];
     setHistory(currentCtxt);
     setIsStreaming(true);
-    await invoke("ask_clippy", { session: { messages: currentCtxt } });
+    const id = crypto.randomUUID();
+    chatId.current = id;
+    await invoke("ask_clippy", {
+      session: { messages: currentCtxt, lenses, doc_ids: [], chat_id: id },
+    });
+  };
+
+  const cancelChat = async () => {
+    if (chatId.current) {
+      await invoke("cancel_clippy", { id: chatId.current });
+    }
   };
 
   const handleQuerySubmission = () => {
@@ -310,8 +329,10 @@ function AskClippy() {
 
   useEffect(() => {
     const init = async () => {
-      return await listen<ChatStream>("ChatEvent", (event) => {
-        handleChatEvent(event.payload);
+      return await listen<ChatStreamEvent>("ChatEvent", (event) => {
+        if (event.payload.chat_id === chatId.current) {
+          handleChatEvent(event.payload.event);
+        }
       });
     };
 
@@ -377,9 +398,15 @@ function AskClippy() {
                   <div>Ask</div>
                 )}
               </Btn>
-              <Btn onClick={clearHistory} className="btn-sm">
-                Clear
-              </Btn>
+              {isStreaming ? (
+                <Btn onClick={cancelChat} className="btn-sm">
+                  Stop
+                </Btn>
+              ) : (
+                <Btn onClick={clearHistory} className="btn-sm">
+                  Clear
+                </Btn>
+              )}
             </div>
           </div>
         </div>
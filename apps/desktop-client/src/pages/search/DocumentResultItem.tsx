@@ -37,7 +37,12 @@ export function DocumentResultItem({ id, onClick, result, isSelected }: Props) {
         </div>
       </div>
       <div className="grow">
-        <div className="text-xs text-cyan-500">{url.hostname}</div>
+        <div className="text-xs text-cyan-500">
+          {url.hostname}
+          {result.semantic_match ? (
+            <span className="ml-2 text-neutral-500">Similar passage</span>
+          ) : null}
+        </div>
         <h2 className="text-base truncate font-semibold w-[30rem]">
           {result.title}
         </h2>
@@ -46,6 +51,7 @@ export function DocumentResultItem({ id, onClick, result, isSelected }: Props) {
           dangerouslySetInnerHTML={{ __html: result.description }}
         />
         <DocumentMeta result={result} />
+        <AlsoAt urls={result.also_at} />
       </div>
     </a>
   );
@@ -87,6 +93,31 @@ function DocumentIcon({ result }: { result: SearchResult }) {
   );
 }
 
+// Near-duplicates of the result (e.g. mirrors) found at other URLs
+function AlsoAt({ urls }: { urls: string[] }) {
+  if (urls.length === 0) {
+    return null;
+  }
+
+  const hosts = urls.map((url) => {
+    try {
+      return new URL(url).hostname || url;
+    } catch {
+      return url;
+    }
+  });
+
+  return (
+    <div
+      className="text-xs text-neutral-500 truncate w-[30rem]"
+      title={urls.join("\n")}
+    >
+      {"Also at "}
+      {[...new Set(hosts)].join(", ")}
+    </div>
+  );
+}
+
 function DocumentMeta({ result }: { result: SearchResult }) {
   const priorityTags: ReactNode[] = [];
   const normalTags: ReactNode[] = [];
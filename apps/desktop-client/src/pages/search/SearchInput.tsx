@@ -7,6 +7,8 @@ interface Props {
   setSelectedLenses: React.Dispatch<React.SetStateAction<string[]>>;
   query: string;
   setQuery: React.Dispatch<React.SetStateAction<string>>;
+  // Completion for the current query, shown inline & accepted w/ ArrowRight.
+  completion?: string | null;
   // Event handlers.
   onEnter?: (event: KeyboardEvent) => void;
   onKeyEvent?: (event: KeyboardEvent) => void;
@@ -17,6 +19,7 @@ export function SearchInput({
   setSelectedLenses,
   query,
   setQuery,
+  completion = null,
   onEnter = () => {},
   onKeyEvent = () => {},
 }: Props) {
@@ -36,6 +39,20 @@ export function SearchInput({
     }
 
     switch (event.key) {
+      case "ArrowRight": {
+        // Accept the completion when the cursor is at the end of the query
+        const input = searchInput.current;
+        if (
+          completion &&
+          input &&
+          input.selectionStart === query.length &&
+          completion.toLowerCase().startsWith(query.toLowerCase())
+        ) {
+          event.preventDefault();
+          setQuery(query + completion.substring(query.length));
+        }
+        break;
+      }
       case "Backspace":
         // handle clearing lenses
         if (query.length === 0 && selectedLenses.length > 0) {
@@ -49,6 +66,14 @@ export function SearchInput({
     }
   };
 
+  // Rest of the completion after what's already been typed
+  const inlineCompletion =
+    completion &&
+    query.length > 0 &&
+    completion.toLowerCase().startsWith(query.toLowerCase())
+      ? completion.substring(query.length)
+      : null;
+
   useEffect(() => {
     const initialize = async () => {
       await listen("FocusWindow", () => {
@@ -62,19 +87,27 @@ export function SearchInput({
   return (
     <div className="flex flex-nowrap w-full bg-neutral-800">
       <SelectedLenses lenses={selectedLenses} />
-      <input
-        ref={searchInput}
-        id="searchbox"
-        type="text"
-        className="bg-neutral-800 text-white text-5xl py-3 overflow-hidden flex-1 border-none caret-white active:outline-none focus-visible:outline-none focus:outline-none"
-        placeholder="Search"
-        onChange={handleUpdateQuery}
-        onKeyDown={handleKeyEvent}
-        onClick={() => searchInput.current?.focus()}
-        value={query}
-        spellCheck={false}
-        tabIndex={-1}
-      />
+      <div className="relative flex-1 overflow-hidden">
+        {inlineCompletion ? (
+          <div className="absolute inset-0 text-5xl py-3 whitespace-pre pointer-events-none">
+            <span className="invisible">{query}</span>
+            <span className="text-neutral-500">{inlineCompletion}</span>
+          </div>
+        ) : null}
+        <input
+          ref={searchInput}
+          id="searchbox"
+          type="text"
+          className="relative w-full bg-transparent text-white text-5xl py-3 overflow-hidden border-none caret-white active:outline-none focus-visible:outline-none focus:outline-none"
+          placeholder="Search"
+          onChange={handleUpdateQuery}
+          onKeyDown={handleKeyEvent}
+          onClick={() => searchInput.current?.focus()}
+          value={query}
+          spellCheck={false}
+          tabIndex={-1}
+        />
+      </div>
     </div>
   );
 }
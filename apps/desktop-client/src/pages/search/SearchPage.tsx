@@ -5,10 +5,12 @@ import { LensResult } from "../../bindings/LensResult";
 import { SearchResults } from "../../bindings/SearchResults";
 import { SearchMeta } from "../../bindings/SearchMeta";
 import { SearchResult } from "../../bindings/SearchResult";
+import { AutocompleteResults } from "../../bindings/AutocompleteResults";
 import { SearchStatus } from "./SearchStatus";
 import { UserActionSettings } from "../../bindings/UserActionSettings";
 import { ActionListButton, ActionsList } from "./ActionsList";
 import {
+  AUTOCOMPLETE_DEBOUNCE_MS,
   DEFAULT_ACTION,
   LENS_SEARCH_PREFIX,
   QUERY_DEBOUNCE_MS,
@@ -45,9 +47,10 @@ export function SearchPage() {
 
   const [selectedActionIdx, setSelectedActionIdx] = useState<number>(0);
   const [searchMeta, setSearchMeta] = useState<SearchMeta | null>(null);
-  const [offset, setOffset] = useState<number>(0);
+  const [cursor, setCursor] = useState<string | null>(null);
 
   const [query, setQuery] = useState<string>("");
+  const [completion, setCompletion] = useState<string | null>(null);
 
   const requestResize = async () => {
     if (searchWrapperRef.current) {
@@ -65,7 +68,7 @@ export function SearchPage() {
     setShowActions(false);
     setSelectedActionIdx(0);
     setSearchMeta(null);
-    setOffset(0);
+    setCursor(null);
     await requestResize();
   }, []);
 
@@ -196,22 +199,24 @@ export function SearchPage() {
 
   useEffect(() => {
     if (resultMode === ResultDisplayMode.Documents) {
-      const doc_count = docResults.length;
-      const max = doc_count - 1;
-      if (selectedIdx === max) {
-        const remainder = doc_count % 5;
-        if (remainder === 0) {
-          setOffset(doc_count);
-        }
+      const max = docResults.length - 1;
+      // Load the next page once the last result is selected
+      if (selectedIdx === max && searchMeta?.next_cursor) {
+        setCursor(searchMeta.next_cursor);
       }
     }
-  }, [selectedIdx, resultMode, docResults.length]);
+  }, [selectedIdx, resultMode, docResults.length, searchMeta]);
 
   useEffect(() => {
+    if (!cursor) {
+      return;
+    }
+
     invoke<SearchResults>("search_docs", {
       query,
       lenses: selectedLenses,
-      offset,
+      offset: 0,
+      cursor,
     }).then((resp: SearchResults) => {
       setDocResults((results: SearchResult[]) => {
         const values = [...results];
@@ -220,8 +225,31 @@ export function SearchPage() {
         }
         return values;
       });
+      setSearchMeta(resp.meta);
     });
-  }, [offset]);
+  }, [cursor]);
+
+  // Suggest a completion for the query as it's typed.
+  useEffect(() => {
+    setCompletion(null);
+    if (query.length === 0 || query.startsWith(LENS_SEARCH_PREFIX)) {
+      return;
+    }
+
+    const timer = setTimeout(async () => {
+      const resp = await invoke<AutocompleteResults>("autocomplete", {
+        query,
+        limit: 5,
+      });
+      // Only completions that extend what's typed can be shown inline
+      const lowered = query.toLowerCase();
+      const match = resp.completions.find((completion) =>
+        completion.text.toLowerCase().startsWith(lowered),
+      );
+      setCompletion(match ? match.text : null);
+    }, AUTOCOMPLETE_DEBOUNCE_MS);
+    return () => clearTimeout(timer);
+  }, [query]);
 
   // when the query changes shoot it over to the server.
   useEffect(() => {
@@ -253,6 +281,7 @@ export function SearchPage() {
         });
         setResultMode(ResultDisplayMode.Documents);
         setDocResults(resp.results);
+        setCursor(null);
         setSearchMeta(resp.meta);
         setIsThinking(false);
       }
@@ -315,6 +344,7 @@ export function SearchPage() {
         setSelectedLenses={setSelectedLenses}
         query={query}
         setQuery={setQuery}
+        completion={completion}
         onEnter={handleEnter}
         onKeyEvent={handleKeyEvent}
       />
@@ -29,7 +29,9 @@ export function SearchStatus({ meta, isThinking }: Props) {
     return (
       <div className="grow text-neutral-500 text-sm pl-3 flex flex-row items-center justify-between">
         <div>
-          {"Searched "}
+          {"Found "}
+          <span className="text-cyan-600">{meta.total_hits}</span>
+          {" matches in "}
           <span className="text-cyan-600">{meta.num_docs}</span>
           {" documents in "}
           <span className="text-cyan-600">
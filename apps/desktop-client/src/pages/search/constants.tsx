@@ -1,7 +1,9 @@
 import { UserActionDefinition } from "../../bindings/UserActionDefinition";
 
 export const LENS_SEARCH_PREFIX: string = "/";
-export const QUERY_DEBOUNCE_MS: number = 256;
+// Completions are cheap, full searches wait for a pause in typing.
+export const AUTOCOMPLETE_DEBOUNCE_MS: number = 64;
+export const QUERY_DEBOUNCE_MS: number = 400;
 export const SEARCH_MIN_CHARS: number = 2;
 
 export const DEFAULT_ACTION: UserActionDefinition = {
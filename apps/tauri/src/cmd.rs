@@ -142,12 +142,15 @@ pub async fn search_docs<'r>(
     lenses: Vec<String>,
     query: &str,
     offset: u32,
+    cursor: Option<String>,
 ) -> Result<SearchResults, String> {
     if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
         let data = request::SearchParam {
             lenses,
             query: query.to_string(),
             offset: Some(offset),
+            cursor,
+            ..Default::default()
         };
 
         let rpc = rpc.lock().await;
@@ -163,6 +166,58 @@ pub async fn search_docs<'r>(
     }
 }
 
+#[tauri::command]
+pub async fn autocomplete<'r>(
+    win: tauri::Window,
+    query: &str,
+    limit: Option<u32>,
+) -> Result<response::AutocompleteResults, String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let data = request::AutocompleteParam {
+            query: query.to_string(),
+            limit,
+        };
+
+        let rpc = rpc.lock().await;
+        match rpc.client.autocomplete(data).await {
+            Ok(resp) => Ok(resp),
+            Err(err) => {
+                log::error!("autocomplete err: {}", err);
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn related_documents<'r>(
+    win: tauri::Window,
+    doc_id: &str,
+    lenses: Vec<String>,
+    limit: Option<u32>,
+) -> Result<response::RelatedResults, String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let data = request::RelatedParam {
+            doc_id: doc_id.to_string(),
+            lenses,
+            limit,
+        };
+
+        let rpc = rpc.lock().await;
+        match rpc.client.related_documents(data).await {
+            Ok(resp) => Ok(resp),
+            Err(err) => {
+                log::error!("related_documents err: {}", err);
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
 #[tauri::command]
 pub async fn search_lenses<'r>(
     win: tauri::Window,
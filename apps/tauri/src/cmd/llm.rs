@@ -8,9 +8,23 @@ use crate::rpc;
 pub async fn ask_clippy(win: tauri::Window, session: LlmSession) -> Result<(), String> {
     tokio::spawn(async move {
         if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
-            let rpc = rpc.lock().await;
-            let _ = rpc.client.chat_completion(session).await;
+            // Don't hold the lock while the answer is generated, the chat can be
+            // canceled in the meantime.
+            let client = rpc.lock().await.client.clone();
+            let _ = client.chat_completion(session).await;
         }
     });
     Ok(())
 }
+
+#[tauri::command]
+pub async fn cancel_clippy(win: tauri::Window, id: String) -> Result<(), String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let client = rpc.lock().await.client.clone();
+        client
+            .cancel_chat(id)
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
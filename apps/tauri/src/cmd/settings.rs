@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use shared::config::EmbeddingSettings;
 use shared::config::FileSystemSettings;
+use shared::config::LlmSettings;
+use shared::config::SearchSettings;
 use shared::config::UserActionSettings;
 use tauri::Manager;
 use tauri::State;
@@ -68,6 +71,9 @@ pub async fn save_user_settings(
                                     current_settings.port = serde_json::from_str(value)
                                         .unwrap_or_else(|_| UserSettings::default_port());
                                 }
+                                "remote_index_url" => {
+                                    current_settings.remote_index_url = value.trim().to_string();
+                                }
                                 "filesystem_settings.watched_paths" => {
                                     current_settings.filesystem_settings.watched_paths =
                                         serde_json::from_str(value).unwrap_or_else(|_| {
@@ -94,6 +100,152 @@ pub async fn save_user_settings(
                                     current_settings.embedding_settings.enable_embeddings =
                                         serde_json::from_str(value).unwrap_or_default()
                                 }
+                                "embedding_settings.model" => {
+                                    current_settings.embedding_settings.model =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "embedding_settings.backend" => {
+                                    current_settings.embedding_settings.backend =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "embedding_settings.remote_url" => {
+                                    current_settings.embedding_settings.remote_url =
+                                        value.trim().to_string()
+                                }
+                                "embedding_settings.remote_model" => {
+                                    current_settings.embedding_settings.remote_model =
+                                        value.trim().to_string()
+                                }
+                                "embedding_settings.remote_batch_size" => {
+                                    let size: u32 =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            EmbeddingSettings::default_remote_batch_size()
+                                        });
+                                    current_settings.embedding_settings.remote_batch_size =
+                                        size.max(1);
+                                }
+                                "embedding_settings.local_batch_size" => {
+                                    let size: u32 =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            EmbeddingSettings::default_local_batch_size()
+                                        });
+                                    current_settings.embedding_settings.local_batch_size =
+                                        size.max(1);
+                                }
+                                "embedding_settings.document_batch_size" => {
+                                    let size: u32 =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            EmbeddingSettings::default_document_batch_size()
+                                        });
+                                    current_settings.embedding_settings.document_batch_size =
+                                        size.max(1);
+                                }
+                                "embedding_settings.chunk_size" => {
+                                    let size: u32 =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            EmbeddingSettings::default_chunk_size()
+                                        });
+                                    current_settings.embedding_settings.chunk_size = size.max(1);
+                                }
+                                "embedding_settings.chunk_overlap" => {
+                                    current_settings.embedding_settings.chunk_overlap =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            EmbeddingSettings::default_chunk_overlap()
+                                        })
+                                }
+                                "embedding_settings.quantization" => {
+                                    current_settings.embedding_settings.quantization =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "search_settings.fusion_strategy" => {
+                                    current_settings.search_settings.fusion_strategy =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "search_settings.rrf_k" => {
+                                    current_settings.search_settings.rrf_k =
+                                        serde_json::from_str(value)
+                                            .unwrap_or_else(|_| SearchSettings::default_rrf_k())
+                                }
+                                "search_settings.semantic_weight" => {
+                                    let weight: u32 =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            SearchSettings::default_semantic_weight()
+                                        });
+                                    current_settings.search_settings.semantic_weight =
+                                        weight.min(100);
+                                }
+                                "search_settings.num_candidates" => {
+                                    current_settings.search_settings.num_candidates =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            SearchSettings::default_candidates()
+                                        })
+                                }
+                                "search_settings.enable_reranking" => {
+                                    current_settings.search_settings.enable_reranking =
+                                        serde_json::from_str(value).unwrap_or_default()
+                                }
+                                "search_settings.rerank_model" => {
+                                    current_settings.search_settings.rerank_model =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "search_settings.rerank_depth" => {
+                                    current_settings.search_settings.rerank_depth =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            SearchSettings::default_rerank_depth()
+                                        })
+                                }
+                                "search_settings.rerank_budget_ms" => {
+                                    current_settings.search_settings.rerank_budget_ms =
+                                        serde_json::from_str(value).unwrap_or_else(|_| {
+                                            SearchSettings::default_rerank_budget_ms()
+                                        })
+                                }
+                                "search_settings.enable_sparse_retrieval" => {
+                                    current_settings.search_settings.enable_sparse_retrieval =
+                                        serde_json::from_str(value).unwrap_or_default()
+                                }
+                                "search_settings.sparse_model" => {
+                                    current_settings.search_settings.sparse_model =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "llm_settings.model_path" => {
+                                    let path = value.trim();
+                                    current_settings.llm_settings.model_path =
+                                        (!path.is_empty()).then(|| PathBuf::from(path))
+                                }
+                                "llm_settings.chat_template" => {
+                                    current_settings.llm_settings.chat_template =
+                                        value.trim().parse().unwrap_or_default()
+                                }
+                                "llm_settings.temperature" => {
+                                    let temperature: f32 = value
+                                        .trim()
+                                        .parse()
+                                        .unwrap_or_else(|_| LlmSettings::default_temperature());
+                                    current_settings.llm_settings.temperature =
+                                        temperature.clamp(0.0, 2.0);
+                                }
+                                "llm_settings.top_p" => {
+                                    let top_p: f32 = value
+                                        .trim()
+                                        .parse()
+                                        .unwrap_or_else(|_| LlmSettings::default_top_p());
+                                    current_settings.llm_settings.top_p = top_p.clamp(0.0, 1.0);
+                                }
+                                "llm_settings.max_tokens" => {
+                                    let max_tokens: u32 = serde_json::from_str(value)
+                                        .unwrap_or_else(|_| LlmSettings::default_max_tokens());
+                                    current_settings.llm_settings.max_tokens = max_tokens.max(1);
+                                }
+                                "llm_settings.stop_sequences" => {
+                                    current_settings.llm_settings.stop_sequences =
+                                        serde_json::from_str(value).unwrap_or_default()
+                                }
+                                "llm_settings.context_length" => {
+                                    let length: u32 = serde_json::from_str(value)
+                                        .unwrap_or_else(|_| LlmSettings::default_context_length());
+                                    current_settings.llm_settings.context_length = length.max(1);
+                                }
                                 _ => {}
                             }
                         }
@@ -136,6 +136,7 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             cmd::authorize_connection,
+            cmd::autocomplete,
             cmd::choose_folder,
             cmd::copy_to_clipboard,
             cmd::default_indices,
@@ -151,6 +152,7 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
             cmd::open_result,
             cmd::open_settings_folder,
             cmd::recrawl_domain,
+            cmd::related_documents,
             cmd::resync_connection,
             cmd::revoke_connection,
             cmd::save_user_settings,
@@ -158,6 +160,7 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
             cmd::search_lenses,
             cmd::update_and_restart,
             cmd::llm::ask_clippy,
+            cmd::llm::cancel_clippy,
             cmd::window::escape,
             cmd::window::navigate,
             cmd::window::open_big_mode,
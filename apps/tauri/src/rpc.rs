@@ -19,7 +19,8 @@ use crate::AppEvent;
 pub type RpcMutex = Arc<Mutex<SpyglassServerClient>>;
 
 pub struct SpyglassServerClient {
-    pub client: WsClient,
+    /// Shared so long running requests, e.g. chats, don't need to hold the lock
+    pub client: Arc<WsClient>,
     pub endpoint: String,
     pub sidecar_handle: Option<JoinHandle<()>>,
     pub restarts: AtomicU8,
@@ -110,7 +111,7 @@ impl SpyglassServerClient {
         let sidecar_handle = None;
 
         SpyglassServerClient {
-            client: client.expect("Unable to create search client"),
+            client: Arc::new(client.expect("Unable to create search client")),
             endpoint: endpoint.clone(),
             sidecar_handle,
             restarts: AtomicU8::new(0),
@@ -133,7 +134,7 @@ impl SpyglassServerClient {
         log::info!("reconnecting to {}", self.endpoint);
         match try_connect(&self.endpoint).await {
             Ok(client) => {
-                self.client = client;
+                self.client = Arc::new(client);
             }
             Err(err) => {
                 // Let users know something has gone dreadfully wrong.
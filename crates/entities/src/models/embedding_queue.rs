@@ -121,18 +121,23 @@ pub struct Job {
     pub id: i64,
 }
 
-pub async fn check_for_embedding_jobs(db: &DatabaseConnection) -> Result<Option<Job>, DbErr> {
+/// Marks up to `limit` queued jobs as processing & returns them, oldest first.
+/// Jobs still being processed count towards the limit.
+pub async fn check_for_embedding_jobs(
+    db: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<Job>, DbErr> {
     let count = Entity::find()
         .filter(Column::Status.eq(QueueStatus::Processing))
         .count(db)
         .await?;
 
-    if count >= 3 {
+    if count >= limit {
         log::debug!("Waiting for previous embedding tasks to finish");
-        return Ok(None);
+        return Ok(Vec::new());
     }
 
-    let query = Statement::from_string(
+    let query = Statement::from_sql_and_values(
         db.get_database_backend(),
         r#"
            UPDATE embedding_queue AS eq
@@ -145,13 +150,24 @@ pub async fn check_for_embedding_jobs(db: &DatabaseConnection) -> Result<Option<
             FROM embedding_queue
             WHERE status = 'Queued'
             ORDER By created_at
-            LIMIT 1
+            LIMIT $1
         )
-        RETURNING id"#
-            .to_string(),
+        RETURNING id"#,
+        vec![(limit - count).into()],
     );
 
-    Job::find_by_statement(query).one(db).await
+    Job::find_by_statement(query).all(db).await
+}
+
+/// Number of jobs waiting to be or being embedded
+pub async fn count_pending<C>(db: &C) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find()
+        .filter(Column::Status.is_in([QueueStatus::Queued, QueueStatus::Processing]))
+        .count(db)
+        .await
 }
 
 pub async fn mark_done(db: &DatabaseConnection, id: i64) {
@@ -0,0 +1,208 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, Set, Statement};
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Eq)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+pub enum RebuildStatus {
+    /// Documents are queued & being embedded w/ the new model
+    #[sea_orm(string_value = "Running")]
+    Running,
+    #[sea_orm(string_value = "Completed")]
+    Completed,
+    #[sea_orm(string_value = "Failed")]
+    Failed,
+}
+
+/// Tracks regenerating the embeddings for every document after switching to a
+/// different embedding model.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "embedding_rebuild")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Name of the model the embeddings are generated w/
+    pub model: String,
+    /// Dimensions of the embeddings, the vector table is recreated at this size
+    pub dimensions: i64,
+    pub status: RebuildStatus,
+    /// Number of documents queued to be embedded w/ the new model
+    pub num_docs: i64,
+    pub errors: Option<String>,
+    /// When the rebuild was started.
+    pub created_at: DateTimeUtc,
+    /// When the rebuild was last updated.
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            status: Set(RebuildStatus::Running),
+            num_docs: Set(0),
+            created_at: Set(chrono::Utc::now()),
+            updated_at: Set(chrono::Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Records a rebuild w/ `model`. Any rebuild still running is replaced by this
+/// one & marked as failed.
+pub async fn start<C>(db: &C, model: &str, dimensions: usize, num_docs: u64) -> Result<Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::update_many()
+        .col_expr(
+            Column::Status,
+            sea_query::Expr::value(RebuildStatus::Failed),
+        )
+        .col_expr(
+            Column::Errors,
+            sea_query::Expr::value(format!("Replaced by rebuild w/ {model}")),
+        )
+        .col_expr(
+            Column::UpdatedAt,
+            sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::Status.eq(RebuildStatus::Running))
+        .exec(db)
+        .await?;
+
+    let model = ActiveModel {
+        model: Set(model.to_string()),
+        dimensions: Set(dimensions as i64),
+        num_docs: Set(num_docs as i64),
+        ..ActiveModel::new()
+    };
+    model.insert(db).await
+}
+
+/// Model the stored embeddings were (or are being) generated w/. None if the
+/// embeddings have never been rebuilt, i.e. they're from the default model.
+pub async fn current_model<C>(db: &C) -> Result<Option<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(Column::Status.ne(RebuildStatus::Failed))
+        .order_by_desc(Column::Id)
+        .one(db)
+        .await?
+        .map(|rebuild| rebuild.model))
+}
+
+/// Rebuild that is still running, if any
+pub async fn in_progress<C>(db: &C) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find()
+        .filter(Column::Status.eq(RebuildStatus::Running))
+        .order_by_desc(Column::Id)
+        .one(db)
+        .await
+}
+
+/// Marks the running rebuild as completed once nothing is left in the embedding
+/// queue. Returns whether a rebuild was completed.
+pub async fn complete_if_done<C>(db: &C) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let result = db
+        .execute(Statement::from_string(
+            db.get_database_backend(),
+            r#"
+            UPDATE embedding_rebuild
+            SET
+                status = 'Completed',
+                updated_at = DATETIME('now')
+            WHERE status = 'Running'
+            AND NOT EXISTS (
+                SELECT 1 FROM embedding_queue
+                WHERE status IN ('Queued', 'Processing')
+            )"#,
+        ))
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod test {
+    use sea_orm::{ActiveModelBehavior, ActiveModelTrait, EntityTrait, Set};
+
+    use crate::models::{embedding_queue, embedding_rebuild};
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_rebuild_lifecycle() {
+        let db = setup_test_db().await;
+        assert_eq!(embedding_rebuild::current_model(&db).await.unwrap(), None);
+
+        let first = embedding_rebuild::start(&db, "bge-small-en-v1.5", 384, 1)
+            .await
+            .unwrap();
+        let second = embedding_rebuild::start(&db, "all-MiniLM-L6-v2", 384, 1)
+            .await
+            .unwrap();
+
+        // Starting a new rebuild replaces the one still running
+        let first = embedding_rebuild::Entity::find_by_id(first.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.status, embedding_rebuild::RebuildStatus::Failed);
+        assert_eq!(
+            embedding_rebuild::current_model(&db).await.unwrap(),
+            Some("all-MiniLM-L6-v2".to_string())
+        );
+
+        // Not done while documents are still queued
+        let job = embedding_queue::ActiveModel {
+            document_id: Set("doc".into()),
+            indexed_document_id: Set(1),
+            ..embedding_queue::ActiveModel::new()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        assert!(!embedding_rebuild::complete_if_done(&db).await.unwrap());
+
+        embedding_queue::mark_done(&db, job.id).await;
+        assert!(embedding_rebuild::complete_if_done(&db).await.unwrap());
+        assert!(embedding_rebuild::in_progress(&db).await.unwrap().is_none());
+
+        let second = embedding_rebuild::Entity::find_by_id(second.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.status, embedding_rebuild::RebuildStatus::Completed);
+    }
+}
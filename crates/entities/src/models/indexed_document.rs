@@ -26,6 +26,9 @@ pub struct Model {
     pub open_url: Option<String>,
     /// Reference to the document in the index
     pub doc_id: String,
+    /// SimHash of the document content, used to find near-duplicates such as
+    /// mirrors of the same page.
+    pub fingerprint: Option<i64>,
     /// When this was indexed
     pub created_at: DateTimeUtc,
     /// When this was last updated
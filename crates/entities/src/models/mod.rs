@@ -7,6 +7,7 @@ pub mod crawl_queue;
 pub mod crawl_tag;
 pub mod document_tag;
 pub mod embedding_queue;
+pub mod embedding_rebuild;
 pub mod fetch_history;
 pub mod indexed_document;
 pub mod lens;
@@ -14,6 +15,8 @@ pub mod link;
 pub mod processed_files;
 pub mod resource_rule;
 pub mod schema;
+pub mod search_history;
+pub mod sparse_backfill;
 pub mod tag;
 pub mod vec_documents;
 pub mod vec_to_indexed;
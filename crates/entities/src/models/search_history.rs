@@ -0,0 +1,172 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::LikeExpr;
+use sea_orm::{QueryOrder, QuerySelect, Set};
+use serde::Serialize;
+
+/// Queries extending a query searched within this many seconds replace it, so
+/// search-as-you-type doesn't record every keystroke.
+const REFINE_WINDOW_SECS: i64 = 30;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "search_history")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Query as typed by the user
+    #[sea_orm(unique)]
+    pub query: String,
+    /// Number of times this query was searched
+    pub num_searches: i64,
+    /// When this query was first searched.
+    pub created_at: DateTimeUtc,
+    /// When this query was last searched.
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            num_searches: Set(1),
+            created_at: Set(chrono::Utc::now()),
+            updated_at: Set(chrono::Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Records a search for `query`. Recently searched queries that `query` extends
+/// are removed, since they were most likely typed on the way to this one.
+pub async fn record(db: &DatabaseConnection, query: &str) -> Result<Model, DbErr> {
+    let query = query.trim();
+    let window_start = chrono::Utc::now() - chrono::Duration::seconds(REFINE_WINDOW_SECS);
+    let partials = Entity::find()
+        .filter(Column::UpdatedAt.gte(window_start))
+        .filter(Column::NumSearches.eq(1))
+        .filter(Column::Query.ne(query))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|model| query.starts_with(&model.query))
+        .map(|model| model.id)
+        .collect::<Vec<_>>();
+
+    if !partials.is_empty() {
+        Entity::delete_many()
+            .filter(Column::Id.is_in(partials))
+            .exec(db)
+            .await?;
+    }
+
+    let existing = Entity::find()
+        .filter(Column::Query.eq(query))
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(existing) => {
+            let num_searches = existing.num_searches + 1;
+            let mut model: ActiveModel = existing.into();
+            model.num_searches = Set(num_searches);
+            model.update(db).await
+        }
+        None => {
+            let model = ActiveModel {
+                query: Set(query.to_string()),
+                ..ActiveModel::new()
+            };
+            model.insert(db).await
+        }
+    }
+}
+
+/// Most recently searched queries starting w/ `prefix` (case insensitive)
+pub async fn recent_with_prefix(
+    db: &DatabaseConnection,
+    prefix: &str,
+    limit: u64,
+) -> Result<Vec<Model>, DbErr> {
+    // `%` & `_` in the prefix are matched literally
+    let pattern = format!("{}%", escape_like(prefix.trim_start()));
+    Entity::find()
+        .filter(Column::Query.like(LikeExpr::new(pattern).escape('\\')))
+        .order_by_desc(Column::UpdatedAt)
+        .limit(limit)
+        .all(db)
+        .await
+}
+
+/// Escapes the LIKE wildcards in `text`, w/ a backslash as the escape character
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::search_history;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_record() {
+        let db = setup_test_db().await;
+
+        // Typing out a query only keeps the final version around
+        for query in ["sal", "salin", "salinas river"] {
+            search_history::record(&db, query).await.unwrap();
+        }
+        search_history::record(&db, "frankenstein").await.unwrap();
+        search_history::record(&db, "salinas river").await.unwrap();
+
+        let recent = search_history::recent_with_prefix(&db, "sa", 5)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].query, "salinas river");
+        assert_eq!(recent[0].num_searches, 2);
+
+        let recent = search_history::recent_with_prefix(&db, "", 5)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+
+        // Wildcards in the prefix are matched literally
+        search_history::record(&db, "100% cotton").await.unwrap();
+        let recent = search_history::recent_with_prefix(&db, "1_0%", 5)
+            .await
+            .unwrap();
+        assert!(recent.is_empty());
+        let recent = search_history::recent_with_prefix(&db, "100%", 5)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+    }
+}
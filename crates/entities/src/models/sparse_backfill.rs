@@ -0,0 +1,244 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect, Set};
+use serde::Serialize;
+
+use super::indexed_document;
+
+#[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Eq)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+pub enum BackfillStatus {
+    /// Documents are being expanded w/ the model, in order of their id
+    #[sea_orm(string_value = "Running")]
+    Running,
+    #[sea_orm(string_value = "Completed")]
+    Completed,
+    #[sea_orm(string_value = "Failed")]
+    Failed,
+}
+
+/// Tracks adding SPLADE expansion terms to every document already in the index,
+/// after sparse retrieval is turned on or switched to a different model.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "sparse_backfill")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Name of the model documents are expanded w/
+    pub model: String,
+    pub status: BackfillStatus,
+    /// Number of documents in the index when the backfill started
+    pub num_docs: i64,
+    /// Number of documents expanded so far
+    pub num_done: i64,
+    /// Id of the last `indexed_document` expanded, the backfill picks up after it
+    pub last_document_id: i64,
+    pub errors: Option<String>,
+    /// When the backfill was started.
+    pub created_at: DateTimeUtc,
+    /// When the backfill was last updated.
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            status: Set(BackfillStatus::Running),
+            num_docs: Set(0),
+            num_done: Set(0),
+            last_document_id: Set(0),
+            created_at: Set(chrono::Utc::now()),
+            updated_at: Set(chrono::Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Records a backfill w/ `model` over every indexed document. Any backfill still
+/// running is replaced by this one & marked as failed.
+pub async fn start<C>(db: &C, model: &str) -> Result<Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::update_many()
+        .col_expr(
+            Column::Status,
+            sea_query::Expr::value(BackfillStatus::Failed),
+        )
+        .col_expr(
+            Column::Errors,
+            sea_query::Expr::value(format!("Replaced by backfill w/ {model}")),
+        )
+        .col_expr(
+            Column::UpdatedAt,
+            sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::Status.eq(BackfillStatus::Running))
+        .exec(db)
+        .await?;
+
+    let num_docs = indexed_document::Entity::find().count(db).await?;
+    let model = ActiveModel {
+        model: Set(model.to_string()),
+        num_docs: Set(num_docs as i64),
+        ..ActiveModel::new()
+    };
+    model.insert(db).await
+}
+
+/// Model the index was (or is being) backfilled w/. None if no backfill was ever
+/// started.
+pub async fn current_model<C>(db: &C) -> Result<Option<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(Column::Status.ne(BackfillStatus::Failed))
+        .order_by_desc(Column::Id)
+        .one(db)
+        .await?
+        .map(|backfill| backfill.model))
+}
+
+/// Backfill that is still running, if any
+pub async fn in_progress<C>(db: &C) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find()
+        .filter(Column::Status.eq(BackfillStatus::Running))
+        .order_by_desc(Column::Id)
+        .one(db)
+        .await
+}
+
+/// The next `limit` documents to expand, in order of their id
+pub async fn next_documents<C>(
+    db: &C,
+    backfill: &Model,
+    limit: u64,
+) -> Result<Vec<indexed_document::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    indexed_document::Entity::find()
+        .filter(indexed_document::Column::Id.gt(backfill.last_document_id))
+        .order_by_asc(indexed_document::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await
+}
+
+/// Records that the documents up to & including `last_document_id` were expanded
+pub async fn record_progress<C>(
+    db: &C,
+    backfill: Model,
+    last_document_id: i64,
+    num_expanded: usize,
+) -> Result<Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let num_done = backfill.num_done + num_expanded as i64;
+    let mut update: ActiveModel = backfill.into();
+    update.last_document_id = Set(last_document_id);
+    update.num_done = Set(num_done);
+    update.update(db).await
+}
+
+/// Marks the backfill as completed, once no documents are left after its cursor
+pub async fn complete<C>(db: &C, backfill: Model) -> Result<Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut update: ActiveModel = backfill.into();
+    update.status = Set(BackfillStatus::Completed);
+    update.update(db).await
+}
+
+#[cfg(test)]
+mod test {
+    use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+
+    use crate::models::{indexed_document, sparse_backfill};
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_backfill_lifecycle() {
+        let db = setup_test_db().await;
+        assert_eq!(sparse_backfill::current_model(&db).await.unwrap(), None);
+
+        for idx in 0..3 {
+            indexed_document::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(format!("https://example.com/{idx}")),
+                doc_id: Set(format!("doc-{idx}")),
+                ..Default::default()
+            }
+            .save(&db)
+            .await
+            .unwrap();
+        }
+
+        let first = sparse_backfill::start(&db, "splade-cocondenser-ensembledistil")
+            .await
+            .unwrap();
+        let second = sparse_backfill::start(&db, "splade-cocondenser-selfdistil")
+            .await
+            .unwrap();
+        assert_eq!(second.num_docs, 3);
+
+        // Starting a new backfill replaces the one still running
+        let first = sparse_backfill::Entity::find_by_id(first.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.status, sparse_backfill::BackfillStatus::Failed);
+        assert_eq!(
+            sparse_backfill::current_model(&db).await.unwrap(),
+            Some("splade-cocondenser-selfdistil".to_string())
+        );
+
+        // Picks up after the last expanded document
+        let docs = sparse_backfill::next_documents(&db, &second, 2)
+            .await
+            .unwrap();
+        assert_eq!(docs.len(), 2);
+        let last_id = docs.last().unwrap().id;
+        let second = sparse_backfill::record_progress(&db, second, last_id, docs.len())
+            .await
+            .unwrap();
+        assert_eq!(second.num_done, 2);
+
+        let docs = sparse_backfill::next_documents(&db, &second, 2)
+            .await
+            .unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].doc_id, "doc-2");
+
+        sparse_backfill::complete(&db, second).await.unwrap();
+        assert!(sparse_backfill::in_progress(&db).await.unwrap().is_none());
+    }
+}
@@ -54,6 +54,9 @@ pub enum TagType {
     /// Pull from the lens categorization
     #[strum(serialize = "category")]
     Category,
+    /// Language the document is written in, detected at index time.
+    #[strum(serialize = "lang")]
+    Language,
     /// Other custom generated TagTypes.
     #[strum(serialize = "Other(String)")]
     Other(String),
@@ -80,6 +83,7 @@ fn string_to_tag_type(v: &str) -> TagType {
         "repository" => TagType::Repository,
         "fileext" => TagType::FileExt,
         "category" => TagType::Category,
+        "lang" => TagType::Language,
         other => TagType::Other(String::from(other)),
     }
 }
@@ -99,6 +103,7 @@ impl Display for TagType {
             Self::Repository => "repository",
             Self::FileExt => "fileext",
             Self::Category => "category",
+            Self::Language => "lang",
             Self::Other(label) => label.as_str(),
         };
 
@@ -1,68 +1,252 @@
-use sea_orm::{
-    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, ExecResult, FromQueryResult, QueryFilter,
-    Statement,
-};
+use std::collections::HashSet;
 
-use super::vec_to_indexed;
+use sea_orm::{ConnectionTrait, DbErr, ExecResult, FromQueryResult, Statement};
+use shared::config::VectorQuantization;
 
-pub async fn insert_embedding<C>(db: &C, id: i64, embedding: &[f32]) -> Result<ExecResult, DbErr>
+/// Embedding converted to the format stored in the vector table
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedEmbedding {
+    pub quantization: VectorQuantization,
+    /// Raw vector, passed to sqlite-vec as a blob
+    pub bytes: Vec<u8>,
+    /// Multiplied w/ the quantized values to approximate the original embedding.
+    /// None for full precision embeddings.
+    pub scale: Option<f32>,
+}
+
+/// Format of the vector table, read from its definition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VectorFormat {
+    pub quantization: VectorQuantization,
+    pub dimensions: usize,
+}
+
+/// Converts `embedding` to the format used to store it w/ `quantization`.
+///
+/// int8 embeddings are scaled so the largest value maps to 127. Binary embeddings
+/// keep the sign of each value, packed 8 to a byte, & are scaled by the mean
+/// absolute value, the closest a vector of +/- scale can get to the original.
+pub fn quantize(embedding: &[f32], quantization: VectorQuantization) -> QuantizedEmbedding {
+    let (bytes, scale) = match quantization {
+        VectorQuantization::Float32 => (
+            embedding
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect(),
+            None,
+        ),
+        VectorQuantization::Int8 => {
+            let max = embedding
+                .iter()
+                .fold(0.0f32, |max, value| max.max(value.abs()));
+            let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+            let bytes = embedding
+                .iter()
+                .map(|value| (value / scale).round().clamp(-127.0, 127.0) as i8 as u8)
+                .collect();
+            (bytes, Some(scale))
+        }
+        VectorQuantization::Binary => {
+            let mut bytes = vec![0u8; embedding.len().div_ceil(8)];
+            for (idx, value) in embedding.iter().enumerate() {
+                if *value > 0.0 {
+                    bytes[idx / 8] |= 1 << (idx % 8);
+                }
+            }
+
+            let scale = embedding.iter().map(|value| value.abs()).sum::<f32>()
+                / embedding.len().max(1) as f32;
+            (bytes, Some(scale))
+        }
+    };
+
+    QuantizedEmbedding {
+        quantization,
+        bytes,
+        scale,
+    }
+}
+
+/// Approximates the original embedding from a stored vector
+pub fn dequantize(bytes: &[u8], scale: Option<f32>, quantization: VectorQuantization) -> Vec<f32> {
+    let scale = scale.unwrap_or(1.0);
+    match quantization {
+        VectorQuantization::Float32 => bytes
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+            .collect(),
+        VectorQuantization::Int8 => bytes
+            .iter()
+            .map(|value| *value as i8 as f32 * scale)
+            .collect(),
+        VectorQuantization::Binary => (0..bytes.len() * 8)
+            .map(|idx| {
+                if bytes[idx / 8] & (1 << (idx % 8)) != 0 {
+                    scale
+                } else {
+                    -scale
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Euclidean distance, the same metric the vector table uses for full precision
+/// embeddings.
+pub fn l2_distance(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let diff = (a - b) as f64;
+            diff * diff
+        })
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Parses the format of the vector table from its `create virtual table` statement
+pub fn parse_format(sql: &str) -> Option<VectorFormat> {
+    let sql = sql.to_lowercase();
+    let (prefix, rest) = sql.split_once('[')?;
+    let dimensions = rest.split_once(']')?.0.trim().parse().ok()?;
+    let quantization = if prefix.ends_with("int8") {
+        VectorQuantization::Int8
+    } else if prefix.ends_with("bit") {
+        VectorQuantization::Binary
+    } else {
+        VectorQuantization::Float32
+    };
+
+    Some(VectorFormat {
+        quantization,
+        dimensions,
+    })
+}
+
+#[derive(Debug, FromQueryResult)]
+struct TableDefinition {
+    sql: String,
+}
+
+/// Format embeddings are currently stored in. None if the vector table is missing.
+pub async fn stored_format<C>(db: &C) -> Result<Option<VectorFormat>, DbErr>
 where
     C: ConnectionTrait,
 {
-    let embedding_string = serde_json::to_string(embedding)
-        .map_err(|err| {
-            log::error!("Error {:?}", err);
-            err
-        })
-        .unwrap();
-    insert_or_update_embedding_str(db, id, &embedding_string, false).await
+    let definition = TableDefinition::find_by_statement(Statement::from_string(
+        db.get_database_backend(),
+        "SELECT sql FROM sqlite_master WHERE name = 'vec_documents'",
+    ))
+    .one(db)
+    .await?;
+
+    Ok(definition.and_then(|definition| parse_format(&definition.sql)))
+}
+
+// sqlite-vec function used to read a vector blob in the given format
+fn vector_fn(quantization: VectorQuantization) -> &'static str {
+    match quantization {
+        VectorQuantization::Float32 => "vec_f32",
+        VectorQuantization::Int8 => "vec_int8",
+        VectorQuantization::Binary => "vec_bit",
+    }
 }
 
-pub async fn update_embedding<C>(db: &C, id: i64, embedding: &[f32]) -> Result<ExecResult, DbErr>
+pub async fn insert_embedding<C>(
+    db: &C,
+    id: i64,
+    embedding: &QuantizedEmbedding,
+) -> Result<ExecResult, DbErr>
 where
     C: ConnectionTrait,
 {
-    let embedding_string = serde_json::to_string(embedding)
-        .map_err(|err| {
-            log::error!("Error {:?}", err);
-            err
-        })
-        .unwrap();
-    insert_or_update_embedding_str(db, id, &embedding_string, true).await
+    insert_or_update_embedding(db, id, embedding, false).await
+}
+
+pub async fn update_embedding<C>(
+    db: &C,
+    id: i64,
+    embedding: &QuantizedEmbedding,
+) -> Result<ExecResult, DbErr>
+where
+    C: ConnectionTrait,
+{
+    insert_or_update_embedding(db, id, embedding, true).await
 }
 
-pub async fn insert_or_update_embedding_str<C>(
+/// Stores the embedding, which needs to be in the format of the vector table.
+/// The scale of quantized embeddings is stored separately, in `vec_to_indexed`.
+pub async fn insert_or_update_embedding<C>(
     db: &C,
     id: i64,
-    embedding: &str,
+    embedding: &QuantizedEmbedding,
     is_update: bool,
 ) -> Result<ExecResult, DbErr>
 where
     C: ConnectionTrait,
 {
+    let vector = vector_fn(embedding.quantization);
     let statement = if is_update {
         Statement::from_sql_and_values(
             db.get_database_backend(),
-            r#"
-            update vec_documents set embedding = $2
+            format!(
+                r#"
+            update vec_documents set embedding = {vector}($2)
                 where rowid = $1
-            "#,
-            vec![id.into(), embedding.into()],
+            "#
+            ),
+            vec![id.into(), embedding.bytes.clone().into()],
         )
     } else {
         Statement::from_sql_and_values(
             db.get_database_backend(),
-            r#"
+            format!(
+                r#"
             insert into vec_documents(rowid, embedding)
-                VALUES($1, $2)
-            "#,
-            vec![id.into(), embedding.into()],
+                VALUES($1, {vector}($2))
+            "#
+            ),
+            vec![id.into(), embedding.bytes.clone().into()],
         )
     };
 
     db.execute(statement).await
 }
 
+/// Drops every stored embedding & recreates the table for embeddings w/ the
+/// given number of dimensions, e.g. after switching embedding models.
+pub async fn recreate_table<C>(
+    db: &C,
+    dimensions: usize,
+    quantization: VectorQuantization,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    db.execute(Statement::from_string(
+        db.get_database_backend(),
+        "drop table if exists vec_documents;",
+    ))
+    .await?;
+
+    let column = match quantization {
+        VectorQuantization::Float32 => "float",
+        VectorQuantization::Int8 => "int8",
+        VectorQuantization::Binary => "bit",
+    };
+    db.execute(Statement::from_string(
+        db.get_database_backend(),
+        format!(
+            "create virtual table vec_documents using vec0(
+                embedding {column}[{dimensions}]
+            );"
+        ),
+    ))
+    .await?;
+
+    Ok(())
+}
+
 pub async fn delete_embedding_by_id<C>(db: &C, id: i64) -> Result<ExecResult, DbErr>
 where
     C: ConnectionTrait,
@@ -134,154 +318,84 @@ pub struct DocDistance {
     pub segment_end: i64,
 }
 
-// Returns the topX documents based on the distance. (The smaller the number the
-// better the result). A single document that has multiple segments in the
-// topX will only be returned once and the segment provided will only be the
-// top result. get_top_context can be used to get the highest ranked context
-pub async fn get_document_distance<C>(
+/// Stored segment along w/ its raw vector, used to compute the full precision
+/// distance to a query.
+#[derive(Debug, FromQueryResult)]
+struct StoredSegment {
+    id: i64,
+    doc_id: String,
+    url: String,
+    segment_start: i64,
+    segment_end: i64,
+    embedding: Vec<u8>,
+    embedding_scale: Option<f32>,
+}
+
+impl StoredSegment {
+    fn into_distance(self, query: &[f32], quantization: VectorQuantization) -> DocDistance {
+        let stored = dequantize(&self.embedding, self.embedding_scale, quantization);
+        DocDistance {
+            id: self.id,
+            distance: l2_distance(query, &stored),
+            doc_id: self.doc_id,
+            url: self.url,
+            segment_start: self.segment_start,
+            segment_end: self.segment_end,
+        }
+    }
+}
+
+// Segments closest to `embedding`, nearest first. The quantized index is searched
+// for `limit` times the oversample factor of the table format & the candidates are
+// rescored by the distance between the full precision query & their dequantized
+// vectors, which is far closer to the float32 distance than comparing two
+// quantized vectors, so the distance thresholds hold for every format.
+async fn nearest_segments<C>(
     db: &C,
     lens_ids: &[u64],
     embedding: &[f32],
-    top_x: u32,
+    limit: u32,
 ) -> Result<Vec<DocDistance>, DbErr>
 where
     C: ConnectionTrait,
 {
-    let embedding_string = serde_json::to_string(embedding)
-        .map_err(|err| {
-            log::error!("Error {:?}", err);
-            err
-        })
-        .unwrap();
+    let Some(format) = stored_format(db).await? else {
+        return Ok(Vec::new());
+    };
 
-    let k_size = 3 * top_x;
-    let statement = if !lens_ids.is_empty() {
-        let query = format!(
-            r#"
-                WITH RankedScores AS (
-                    SELECT
-                        indexed_document.id AS score_id,
-                        vd.distance,
-                        indexed_document.doc_id,
-                        indexed_document.url,
-                        vti.segment_start,
-                        vti.segment_end,
-                        ROW_NUMBER() OVER (PARTITION BY indexed_document.doc_id ORDER BY vd.distance ASC) AS rank
-                    FROM
-                        vec_documents vd
-                    left JOIN
-                        vec_to_indexed vti
-                        ON vd.rowid = vti.id
-                    left JOIN indexed_document
-                        ON vti.indexed_id = indexed_document.id
-                    left JOIN document_tag on document_tag.indexed_document_id = indexed_document.id
-                    WHERE document_tag.id in ({}) AND vd.embedding MATCH $1 AND k = $2 ORDER BY vd.distance ASC
-                )
-                SELECT score_id as id, distance, doc_id, url, segment_start, segment_end FROM RankedScores WHERE rank = 1 ORDER BY distance ASC limit $3;
-            "#,
-            lens_ids
-                .iter()
-                .map(|val| val.to_string())
-                .collect::<Vec<String>>()
-                .join(",")
-        );
+    let query = quantize(embedding, format.quantization);
+    let vector = vector_fn(format.quantization);
+    let k_size = limit.saturating_mul(format.quantization.oversample());
 
-        Statement::from_sql_and_values(
-            db.get_database_backend(),
-            query,
-            vec![embedding_string.into(), k_size.into(), top_x.into()],
-        )
+    let (lens_join, lens_condition) = if lens_ids.is_empty() {
+        (String::new(), String::new())
     } else {
-        Statement::from_sql_and_values(
-            db.get_database_backend(),
-            r#"
-                WITH RankedScores AS (
-                    SELECT
-                        indexed_document.id AS score_id,
-                        vd.distance,
-                        indexed_document.doc_id,
-                        indexed_document.url,
-                        vti.segment_start,
-                        vti.segment_end,
-                        ROW_NUMBER() OVER (PARTITION BY indexed_document.doc_id ORDER BY vd.distance ASC) AS rank
-                    FROM
-                        vec_documents vd
-                    left JOIN
-                        vec_to_indexed vti
-                        ON vd.rowid = vti.id
-                    left JOIN indexed_document
-                        ON vti.indexed_id = indexed_document.id
-                    WHERE vd.embedding MATCH $1 AND k = $2 ORDER BY vd.distance ASC
-                )
-                SELECT score_id as id, distance, doc_id, url, segment_start, segment_end FROM RankedScores WHERE rank = 1 ORDER BY distance ASC limit $3;
-            "#,
-            vec![embedding_string.into(), k_size.into(), top_x.into()],
+        (
+            "left JOIN document_tag on document_tag.indexed_document_id = indexed_document.id"
+                .to_string(),
+            format!(
+                "document_tag.id in ({}) AND",
+                lens_ids
+                    .iter()
+                    .map(|val| val.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
         )
     };
 
-    DocDistance::find_by_statement(statement)
-        .all(db)
-        .await
-        .map_err(|err| {
-            log::error!("Error is {:?}", err);
-            err
-        })
-}
-
-pub async fn get_top_context<C>(
-    db: &C,
-    lens_ids: &[u64],
-    embedding: &[f32],
-    top_x: u32,
-) -> Result<Vec<DocDistance>, DbErr>
-where
-    C: ConnectionTrait,
-{
-    let embedding_string = serde_json::to_string(embedding)
-        .map_err(|err| {
-            log::error!("Error {:?}", err);
-            err
-        })
-        .unwrap();
-    let statement = if !lens_ids.is_empty() {
-        Statement::from_sql_and_values(
-            db.get_database_backend(),
-            r#"
-                SELECT
-                    indexed_document.id AS id,
-                    vd.distance,
-                    indexed_document.doc_id,
-                    vti.segment_start,
-                    vti.segment_end,
-                    indexed_document.url
-                FROM
-                    vec_documents vd
-                left JOIN
-                    vec_to_indexed vti
-                    ON vd.rowid = vti.id
-                left JOIN indexed_document
-                    ON vti.indexed_id = indexed_document.id
-                left join document_tag on document_tag.indexed_document_id = indexed_document.id
-                WHERE document_tag.id in $1 AND vd.embedding MATCH $2 AND k = $3 ORDER BY vd.distance ASC
-            "#,
-            vec![
-                lens_ids.to_owned().into(),
-                embedding_string.into(),
-                top_x.into(),
-            ],
-        )
-    } else {
-        Statement::from_sql_and_values(
-            db.get_database_backend(),
+    let statement = Statement::from_sql_and_values(
+        db.get_database_backend(),
+        format!(
             r#"
                 SELECT
                     indexed_document.id AS id,
-                    vd.distance,
                     indexed_document.doc_id,
                     indexed_document.url,
                     vti.segment_start,
                     vti.segment_end,
-                    ROW_NUMBER() OVER (PARTITION BY indexed_document.doc_id ORDER BY vd.distance ASC) AS rank
+                    vti.embedding_scale,
+                    vd.embedding
                 FROM
                     vec_documents vd
                 left JOIN
@@ -289,87 +403,240 @@ where
                     ON vd.rowid = vti.id
                 left JOIN indexed_document
                     ON vti.indexed_id = indexed_document.id
-                WHERE vd.embedding MATCH $1 AND k = $2 ORDER BY vd.distance ASC
-            "#,
-            vec![embedding_string.into(), top_x.into()],
-        )
-    };
+                {lens_join}
+                WHERE {lens_condition} vd.embedding MATCH {vector}($1) AND k = $2 ORDER BY vd.distance ASC
+            "#
+        ),
+        vec![query.bytes.into(), k_size.into()],
+    );
 
-    DocDistance::find_by_statement(statement)
+    let mut segments = StoredSegment::find_by_statement(statement)
         .all(db)
         .await
         .map_err(|err| {
             log::error!("Error is {:?}", err);
             err
-        })
+        })?
+        .into_iter()
+        .map(|segment| segment.into_distance(embedding, format.quantization))
+        .collect::<Vec<_>>();
+
+    segments.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    Ok(segments)
 }
 
-pub async fn get_context_for_doc<C>(
+// Returns the topX documents based on the distance. (The smaller the number the
+// better the result). A single document that has multiple segments in the
+// topX will only be returned once and the segment provided will only be the
+// top result. get_top_context can be used to get the highest ranked context
+pub async fn get_document_distance<C>(
     db: &C,
-    document_id: i64,
+    lens_ids: &[u64],
     embedding: &[f32],
+    top_x: u32,
 ) -> Result<Vec<DocDistance>, DbErr>
 where
     C: ConnectionTrait,
 {
-    let embedding_string = serde_json::to_string(embedding)
-        .map_err(|err| {
-            log::error!("Error {:?}", err);
-            err
-        })
-        .unwrap();
-
-    let indexed = vec_to_indexed::Entity::find()
-        .filter(vec_to_indexed::Column::IndexedId.eq(document_id))
-        .all(db)
-        .await;
+    let segments = nearest_segments(db, lens_ids, embedding, 3 * top_x).await?;
 
-    let indexed_vectors = indexed
-        .map(|indexed| {
-            indexed
-                .iter()
-                .map(|val| val.id.to_string())
-                .collect::<Vec<String>>()
-                .join(",")
-        })
-        .unwrap_or("".to_string());
+    let mut seen = HashSet::new();
+    Ok(segments
+        .into_iter()
+        .filter(|segment| seen.insert(segment.doc_id.clone()))
+        .take(top_x as usize)
+        .collect())
+}
 
-    let query = format!(
-        r#"
-                SELECT
-                    indexed_document.id AS id,
-                    vec_distance_L2(vd.embedding, $1) as distance,
-                    indexed_document.doc_id,
-                    vti.segment_start,
-                    vti.segment_end,
-                    indexed_document.url
-                FROM
-                    vec_documents vd
-                left JOIN
-                    vec_to_indexed vti
-                    ON vd.rowid = vti.id
-                left JOIN indexed_document
-                    ON vti.indexed_id = indexed_document.id
-                WHERE vd.rowid in ({}) ORDER BY vd.distance ASC
-            "#,
-        indexed_vectors
-    );
+/// The topX segments closest to the embedding, a document can show up more than
+/// once.
+pub async fn get_top_context<C>(
+    db: &C,
+    lens_ids: &[u64],
+    embedding: &[f32],
+    top_x: u32,
+) -> Result<Vec<DocDistance>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut segments = nearest_segments(db, lens_ids, embedding, top_x).await?;
+    segments.truncate(top_x as usize);
+    Ok(segments)
+}
 
+// Every stored segment of a document, in the order they appear in the document
+async fn segments_for_doc<C>(
+    db: &C,
+    document_id: i64,
+    limit: Option<u32>,
+) -> Result<Vec<StoredSegment>, DbErr>
+where
+    C: ConnectionTrait,
+{
     let statement = Statement::from_sql_and_values(
         db.get_database_backend(),
-        query,
-        vec![embedding_string.into()],
+        r#"
+            SELECT
+                indexed_document.id AS id,
+                indexed_document.doc_id,
+                indexed_document.url,
+                vti.segment_start,
+                vti.segment_end,
+                vti.embedding_scale,
+                vd.embedding
+            FROM vec_to_indexed vti
+            JOIN vec_documents vd ON vd.rowid = vti.id
+            JOIN indexed_document ON vti.indexed_id = indexed_document.id
+            WHERE vti.indexed_id = $1
+            ORDER BY vti.segment_start ASC
+            LIMIT $2
+        "#,
+        vec![document_id.into(), limit.map_or(-1, i64::from).into()],
     );
 
-    DocDistance::find_by_statement(statement)
-        .all(db)
+    StoredSegment::find_by_statement(statement).all(db).await
+}
+
+/// Embeddings stored for the first `limit` segments of a document, in the order
+/// the segments appear in the document. Quantized embeddings are approximated.
+pub async fn get_embeddings_for_doc<C>(
+    db: &C,
+    document_id: i64,
+    limit: u32,
+) -> Result<Vec<Vec<f32>>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let Some(format) = stored_format(db).await? else {
+        return Ok(Vec::new());
+    };
+
+    Ok(segments_for_doc(db, document_id, Some(limit))
+        .await?
+        .into_iter()
+        .map(|segment| {
+            dequantize(
+                &segment.embedding,
+                segment.embedding_scale,
+                format.quantization,
+            )
+        })
+        .collect())
+}
+
+/// Every segment of a document, closest to the embedding first
+pub async fn get_context_for_doc<C>(
+    db: &C,
+    document_id: i64,
+    embedding: &[f32],
+) -> Result<Vec<DocDistance>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let Some(format) = stored_format(db).await? else {
+        return Ok(Vec::new());
+    };
+
+    let mut segments = segments_for_doc(db, document_id, None)
         .await
         .map_err(|err| {
             log::error!("Error is {:?}", err);
             err
-        })
-        .map(|mut segments| {
-            segments.sort_by(|a, b| a.distance.total_cmp(&b.distance));
-            segments
-        })
+        })?
+        .into_iter()
+        .map(|segment| segment.into_distance(embedding, format.quantization))
+        .collect::<Vec<_>>();
+
+    segments.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod test {
+    use shared::config::VectorQuantization;
+
+    use super::{dequantize, l2_distance, parse_format, quantize, StoredSegment, VectorFormat};
+
+    #[test]
+    fn test_quantize_int8() {
+        let embedding = [0.5, -1.0, 0.25, 0.0];
+        let quantized = quantize(&embedding, VectorQuantization::Int8);
+        assert_eq!(quantized.bytes, vec![64, (-127i8) as u8, 32, 0]);
+
+        let restored = dequantize(&quantized.bytes, quantized.scale, VectorQuantization::Int8);
+        assert!(l2_distance(&embedding, &restored) < 0.01);
+    }
+
+    #[test]
+    fn test_quantize_binary() {
+        let embedding = [0.5, -1.0, 1.5, 0.0, 1.0, 1.0, -1.0, 1.0, 2.0];
+        let quantized = quantize(&embedding, VectorQuantization::Binary);
+        // Sign bits packed w/ the first dimension in the lowest bit
+        assert_eq!(quantized.bytes, vec![0b1011_0101, 0b0000_0001]);
+        assert_eq!(quantized.scale, Some(1.0));
+
+        let restored = dequantize(
+            &quantized.bytes,
+            quantized.scale,
+            VectorQuantization::Binary,
+        );
+        assert_eq!(&restored[..4], &[1.0, -1.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_quantize_float32() {
+        let embedding = [0.5, -1.0, 0.123];
+        let quantized = quantize(&embedding, VectorQuantization::Float32);
+        assert_eq!(quantized.bytes.len(), 12);
+        assert_eq!(quantized.scale, None);
+        assert_eq!(
+            dequantize(&quantized.bytes, None, VectorQuantization::Float32),
+            embedding.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_rescore_asymmetric() {
+        let query = [0.1, -0.2, 0.3, 0.05];
+        let stored = [0.12, -0.18, 0.31, 0.0];
+        let quantized = quantize(&stored, VectorQuantization::Int8);
+        let segment = StoredSegment {
+            id: 1,
+            doc_id: "doc".into(),
+            url: "https://example.com".into(),
+            segment_start: 0,
+            segment_end: 10,
+            embedding: quantized.bytes,
+            embedding_scale: quantized.scale,
+        };
+
+        // The full precision query is compared to the dequantized vector
+        let rescored = segment.into_distance(&query, VectorQuantization::Int8);
+        assert!((rescored.distance - l2_distance(&query, &stored)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let format = |sql: &str| parse_format(sql).unwrap();
+        assert_eq!(
+            format("CREATE VIRTUAL TABLE vec_documents USING vec0(embedding float[768])"),
+            VectorFormat {
+                quantization: VectorQuantization::Float32,
+                dimensions: 768
+            }
+        );
+        assert_eq!(
+            format("create virtual table vec_documents using vec0(\n embedding int8[384]\n)"),
+            VectorFormat {
+                quantization: VectorQuantization::Int8,
+                dimensions: 384
+            }
+        );
+        assert_eq!(
+            format("create virtual table vec_documents using vec0(embedding bit[ 768 ])")
+                .quantization,
+            VectorQuantization::Binary
+        );
+        assert!(parse_format("create table vec_documents(embedding blob)").is_none());
+    }
 }
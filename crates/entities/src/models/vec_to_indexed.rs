@@ -3,7 +3,7 @@ use serde::Serialize;
 
 use super::{indexed_document, vec_documents};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "vec_to_indexed")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -13,8 +13,13 @@ pub struct Model {
     pub created_at: DateTimeUtc,
     /// When this task was last updated.
     pub updated_at: DateTimeUtc,
+    /// Byte offset of the start of the embedded segment in the document content
     pub segment_start: i64,
+    /// Byte offset right after the end of the embedded segment
     pub segment_end: i64,
+    /// Multiplied w/ the quantized values in `vec_documents` to approximate the
+    /// original embedding. Not set for full precision embeddings.
+    pub embedding_scale: Option<f32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
@@ -57,11 +62,13 @@ pub async fn insert_embedding_mapping(
     indexed_id: i64,
     start: usize,
     end: usize,
+    embedding_scale: Option<f32>,
 ) -> Result<InsertResult<ActiveModel>, DbErr> {
     let mut active_model = ActiveModel::new();
     active_model.indexed_id = Set(indexed_id);
     active_model.segment_start = Set(start as i64);
     active_model.segment_end = Set(end as i64);
+    active_model.embedding_scale = Set(embedding_scale);
 
     Entity::insert(active_model).exec(db).await
 }
@@ -3,7 +3,8 @@ use shared::config::Config;
 
 use crate::models::{
     bootstrap_queue, connection, crawl_queue, crawl_tag, create_connection, document_tag,
-    fetch_history, indexed_document, lens, link, resource_rule, tag,
+    embedding_queue, embedding_rebuild, fetch_history, indexed_document, lens, link, resource_rule,
+    search_history, sparse_backfill, tag,
 };
 
 #[allow(dead_code)]
@@ -110,6 +111,42 @@ async fn setup_schema(db: &DatabaseConnection) -> anyhow::Result<(), sea_orm::Db
     )
     .await?;
 
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(search_history::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(embedding_queue::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(embedding_rebuild::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(sparse_backfill::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
     db.execute(
         builder.build(
             &Index::create()
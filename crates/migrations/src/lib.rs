@@ -34,6 +34,15 @@ mod m20241029_000001_add_vector;
 mod m20241105_000001_add_embeddings_table;
 mod m20241115_000001_embedding_to_indexed_document;
 mod m20241119_000001_segment_columns;
+mod m20241125_000001_add_search_history_table;
+mod m20241202_000001_add_document_fingerprint;
+mod m20241210_000001_add_symbols_field;
+mod m20241212_000001_segment_byte_offsets;
+mod m20241216_000001_add_embedding_rebuild_table;
+mod m20241218_000001_quantize_vectors;
+mod m20241220_000001_add_sparse_field;
+mod m20241221_000001_add_sparse_backfill_table;
+mod m20241222_000001_backfill_fingerprints;
 mod utils;
 
 pub struct Migrator;
@@ -73,6 +82,15 @@ impl MigratorTrait for Migrator {
             Box::new(m20241105_000001_add_embeddings_table::Migration),
             Box::new(m20241115_000001_embedding_to_indexed_document::Migration),
             Box::new(m20241119_000001_segment_columns::Migration),
+            Box::new(m20241125_000001_add_search_history_table::Migration),
+            Box::new(m20241202_000001_add_document_fingerprint::Migration),
+            Box::new(m20241210_000001_add_symbols_field::Migration),
+            Box::new(m20241212_000001_segment_byte_offsets::Migration),
+            Box::new(m20241216_000001_add_embedding_rebuild_table::Migration),
+            Box::new(m20241218_000001_quantize_vectors::Migration),
+            Box::new(m20241220_000001_add_sparse_field::Migration),
+            Box::new(m20241221_000001_add_sparse_backfill_table::Migration),
+            Box::new(m20241222_000001_backfill_fingerprints::Migration),
         ]
     }
 }
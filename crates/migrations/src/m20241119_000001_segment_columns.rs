@@ -1,6 +1,7 @@
 use sea_orm::Statement;
 use sea_orm_migration::prelude::*;
 use shared::config::Config;
+use spyglass_searcher::schema::value_text;
 use spyglass_searcher::schema::DocFields;
 use spyglass_searcher::schema::SearchDocument;
 use tantivy::collector::TopDocs;
@@ -74,14 +75,14 @@ impl MigrationTrait for Migration {
             if segment_start.is_err() {
                 if let (Ok(id), Ok(indexed_id), Ok(doc_id)) = (id, indexed_id, doc_id) {
                     let (_writer, reader) = open_index();
-                    let schema = DocFields::as_schema();
+                    let schema = reader.searcher().schema().clone();
                     let id_field = schema.get_field("id").unwrap();
                     let content_field = schema.get_field("content").unwrap();
 
                     if let Some(doc) = get_by_id(id_field, &reader, &doc_id) {
                         let content = doc.get_first(content_field);
                         if let Some(content) = content {
-                            if let Some(content) = content.as_text() {
+                            if let Some(content) = value_text(content) {
                                 calc_update_length(id, indexed_id, content, manager, &tokenizer)
                                     .await?;
                             }
@@ -180,8 +181,14 @@ fn open_index() -> (IndexWriter, IndexReader) {
     let schema = DocFields::as_schema();
 
     let dir = MmapDirectory::open(config.index_dir()).expect("Unable to create MmapDirectory");
-    let index =
-        tantivy::Index::open_or_create(dir, schema).expect("Unable to open / create directory");
+    // Existing indices are opened w/ the schema they were created w/, it may be
+    // changed by a later migration.
+    let index = if tantivy::Index::exists(&dir).unwrap_or(false) {
+        tantivy::Index::open(dir)
+    } else {
+        tantivy::Index::open_or_create(dir, schema)
+    }
+    .expect("Unable to open / create directory");
 
     let writer = index
         .writer(50_000_000)
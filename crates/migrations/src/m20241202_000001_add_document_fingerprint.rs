@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum IndexedDocument {
+    #[iden = "indexed_document"]
+    Table,
+    Fingerprint,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing documents are fingerprinted from their stored content once the
+        // index is on its latest schema, see m20241222_000001_backfill_fingerprints.
+        if let Ok(false) = manager.has_column("indexed_document", "fingerprint").await {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(IndexedDocument::Table)
+                        .add_column(ColumnDef::new(IndexedDocument::Fingerprint).big_integer())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-indexed_document-fingerprint")
+                    .table(IndexedDocument::Table)
+                    .col(IndexedDocument::Fingerprint)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
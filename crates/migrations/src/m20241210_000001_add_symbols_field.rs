@@ -0,0 +1,20 @@
+use sea_orm_migration::prelude::*;
+
+use crate::utils::index_utils;
+
+/// Rebuilds the search index w/ the `symbols` field & the code analyzer for code
+/// files. Documents are re-added from the stored fields of the old index, so
+/// nothing needs to be recrawled.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        index_utils::rebuild_index_with_field("symbols")
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use shared::config::Config;
+use spyglass_searcher::schema::value_text;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::TermQuery;
+use tantivy::schema::IndexRecordOption;
+use tantivy::{Index, Term};
+
+/// Embedded segments used to be stored as char offsets into the trimmed document
+/// content w/ an inclusive end. They're now byte offsets into the content w/ an
+/// exclusive end, which can be used to slice the content directly.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let results = manager
+            .get_connection()
+            .query_all(Statement::from_string(
+                manager.get_database_backend(),
+                r#"SELECT vec_to_indexed.id, indexed_document.doc_id, vec_to_indexed.segment_start, vec_to_indexed.segment_end
+                FROM vec_to_indexed
+                JOIN indexed_document on indexed_document.id = vec_to_indexed.indexed_id"#
+                    .to_owned(),
+            ))
+            .await?;
+
+        let mut segments: HashMap<String, Vec<(i64, i64, i64)>> = HashMap::new();
+        for result in results {
+            let id: Result<i64, DbErr> = result.try_get("", "id");
+            let doc_id: Result<String, DbErr> = result.try_get("", "doc_id");
+            let start: Result<i64, DbErr> = result.try_get("", "segment_start");
+            let end: Result<i64, DbErr> = result.try_get("", "segment_end");
+            if let (Ok(id), Ok(doc_id), Ok(start), Ok(end)) = (id, doc_id, start, end) {
+                segments.entry(doc_id).or_default().push((id, start, end));
+            }
+        }
+
+        if segments.is_empty() {
+            return Ok(());
+        }
+
+        let config = Config::new();
+        let dir = MmapDirectory::open(config.index_dir())
+            .map_err(|e| DbErr::Custom(format!("Unable to open index: {e}")))?;
+        if !Index::exists(&dir).unwrap_or(false) {
+            return Ok(());
+        }
+
+        let index =
+            Index::open(dir).map_err(|e| DbErr::Custom(format!("Unable to open index: {e}")))?;
+        let reader = index
+            .reader()
+            .map_err(|e| DbErr::Custom(format!("Unable to read index: {e}")))?;
+        let searcher = reader.searcher();
+        let schema = index.schema();
+        let (Some(id_field), Some(content_field)) =
+            (schema.get_field("id"), schema.get_field("content"))
+        else {
+            return Err(DbErr::Custom("Unexpected index schema".into()));
+        };
+
+        for (doc_id, segments) in segments {
+            let query = TermQuery::new(
+                Term::from_field_text(id_field, &doc_id),
+                IndexRecordOption::Basic,
+            );
+            let content = searcher
+                .search(&query, &TopDocs::with_limit(1))
+                .ok()
+                .and_then(|docs| docs.first().map(|(_, addr)| *addr))
+                .and_then(|addr| searcher.doc(addr).ok())
+                .and_then(|doc| {
+                    doc.get_first(content_field)
+                        .and_then(value_text)
+                        .map(|text| text.to_string())
+                });
+
+            // Removed from the index, the embeddings get cleaned up w/ the document
+            let Some(content) = content else {
+                continue;
+            };
+
+            for (id, start, end) in segments {
+                let (start, end) = to_byte_offsets(&content, start, end);
+                manager
+                    .get_connection()
+                    .execute(Statement::from_sql_and_values(
+                        manager.get_database_backend(),
+                        "UPDATE vec_to_indexed SET segment_start = $1, segment_end = $2 WHERE id = $3",
+                        vec![start.into(), end.into(), id.into()],
+                    ))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
+
+fn to_byte_offsets(content: &str, start: i64, end: i64) -> (i64, i64) {
+    let leading = content.len() - content.trim_start().len();
+    let trimmed = content.trim();
+    let byte_offset = |chars: i64| {
+        trimmed
+            .char_indices()
+            .nth(chars.max(0) as usize)
+            .map_or(trimmed.len(), |(idx, _)| idx)
+    };
+
+    let start = byte_offset(start);
+    let end = byte_offset(end.saturating_add(1)).max(start);
+    ((leading + start) as i64, (leading + end) as i64)
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum EmbeddingRebuild {
+    #[iden = "embedding_rebuild"]
+    Table,
+    Id,
+    Model,
+    Dimensions,
+    Status,
+    NumDocs,
+    Errors,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmbeddingRebuild::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EmbeddingRebuild::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EmbeddingRebuild::Model).string().not_null())
+                    .col(
+                        ColumnDef::new(EmbeddingRebuild::Dimensions)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EmbeddingRebuild::Status).string().not_null())
+                    .col(
+                        ColumnDef::new(EmbeddingRebuild::NumDocs)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(EmbeddingRebuild::Errors).string())
+                    .col(
+                        ColumnDef::new(EmbeddingRebuild::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmbeddingRebuild::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use entities::models::vec_documents;
+use entities::sea_orm::{ConnectionTrait, Statement, TransactionTrait};
+use sea_orm_migration::prelude::*;
+use shared::config::VectorQuantization;
+
+/// Number of embeddings converted at a time
+const BATCH_SIZE: u64 = 500;
+
+/// Embeddings used to be stored as float32 vectors. They're now quantized by
+/// default, w/ the scale of each embedding stored next to its segment offsets.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum VecToIndexed {
+    #[iden = "vec_to_indexed"]
+    Table,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if let Ok(false) = manager
+            .has_column("vec_to_indexed", "embedding_scale")
+            .await
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(VecToIndexed::Table)
+                        .add_column(ColumnDef::new(Alias::new("embedding_scale")).float())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        let db = manager.get_connection();
+        let format = match vec_documents::stored_format(db).await? {
+            Some(format) if format.quantization == VectorQuantization::Float32 => format,
+            _ => return Ok(()),
+        };
+
+        let quantization = VectorQuantization::default();
+        let backend = manager.get_database_backend();
+        let txn = db.begin().await?;
+        txn.execute(Statement::from_string(
+            backend,
+            r#"CREATE TABLE vec_documents_float AS
+                SELECT rowid AS id, embedding FROM vec_documents"#,
+        ))
+        .await?;
+        vec_documents::recreate_table(&txn, format.dimensions, quantization).await?;
+
+        let mut last_id = i64::MIN;
+        loop {
+            let rows = txn
+                .query_all(Statement::from_sql_and_values(
+                    backend,
+                    "SELECT id, embedding FROM vec_documents_float WHERE id > $1 ORDER BY id LIMIT $2",
+                    vec![last_id.into(), BATCH_SIZE.into()],
+                ))
+                .await?;
+            if rows.is_empty() {
+                break;
+            }
+
+            for row in rows {
+                let id: i64 = row.try_get("", "id")?;
+                let embedding: Vec<u8> = row.try_get("", "embedding")?;
+                last_id = id;
+
+                let embedding =
+                    vec_documents::dequantize(&embedding, None, VectorQuantization::Float32);
+                let quantized = vec_documents::quantize(&embedding, quantization);
+                vec_documents::insert_embedding(&txn, id, &quantized).await?;
+                txn.execute(Statement::from_sql_and_values(
+                    backend,
+                    "UPDATE vec_to_indexed SET embedding_scale = $1 WHERE id = $2",
+                    vec![quantized.scale.into(), id.into()],
+                ))
+                .await?;
+            }
+        }
+
+        txn.execute(Statement::from_string(
+            backend,
+            "DROP TABLE vec_documents_float",
+        ))
+        .await?;
+        txn.commit().await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
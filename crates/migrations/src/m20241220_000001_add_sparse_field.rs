@@ -0,0 +1,21 @@
+use sea_orm_migration::prelude::*;
+
+use crate::utils::index_utils;
+
+/// Rebuilds the search index w/ the `sparse` field holding the SPLADE expansion
+/// terms of documents. Existing documents are re-added from the stored fields of
+/// the old index w/o any expansion terms, those are added by the sparse backfill
+/// once sparse retrieval is turned on.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        index_utils::rebuild_index_with_field("sparse")
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum SparseBackfill {
+    #[iden = "sparse_backfill"]
+    Table,
+    Id,
+    Model,
+    Status,
+    NumDocs,
+    NumDone,
+    LastDocumentId,
+    Errors,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SparseBackfill::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SparseBackfill::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SparseBackfill::Model).string().not_null())
+                    .col(ColumnDef::new(SparseBackfill::Status).string().not_null())
+                    .col(
+                        ColumnDef::new(SparseBackfill::NumDocs)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SparseBackfill::NumDone)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SparseBackfill::LastDocumentId)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(SparseBackfill::Errors).string())
+                    .col(
+                        ColumnDef::new(SparseBackfill::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SparseBackfill::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use sea_orm::{Statement, TransactionTrait};
+use sea_orm_migration::prelude::*;
+use shared::config::Config;
+use spyglass_searcher::dedup;
+use spyglass_searcher::schema::value_text;
+use tantivy::directory::MmapDirectory;
+use tantivy::{DocAddress, Index};
+
+/// Fingerprints the documents indexed before fingerprints were added, from the
+/// content stored in the index, so their near-duplicates are collapsed w/o having
+/// to recrawl them.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let config = Config::new();
+        let index_path = config.index_dir();
+        // Fresh install, nothing to fingerprint.
+        if !index_path.exists() {
+            return Ok(());
+        }
+
+        let dir = MmapDirectory::open(&index_path)
+            .map_err(|e| DbErr::Custom(format!("Unable to open index: {e}")))?;
+        if !Index::exists(&dir).unwrap_or(false) {
+            return Ok(());
+        }
+
+        let index =
+            Index::open(dir).map_err(|e| DbErr::Custom(format!("Unable to open index: {e}")))?;
+        let reader = index
+            .reader()
+            .map_err(|e| DbErr::Custom(format!("Unable to read index: {e}")))?;
+        let searcher = reader.searcher();
+        let schema = index.schema();
+        let (Some(id_field), Some(content_field)) =
+            (schema.get_field("id"), schema.get_field("content"))
+        else {
+            return Err(DbErr::Custom("Unexpected index schema".into()));
+        };
+
+        let backend = manager.get_database_backend();
+        let txn = manager.get_connection().begin().await?;
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let doc = match searcher.doc(DocAddress::new(segment_ord as u32, doc_id)) {
+                    Ok(doc) => doc,
+                    Err(e) => {
+                        log::error!("Unable to read doc {:?}", e);
+                        continue;
+                    }
+                };
+
+                let id = doc.get_first(id_field).and_then(value_text);
+                let fingerprint = doc
+                    .get_first(content_field)
+                    .and_then(value_text)
+                    .and_then(dedup::fingerprint);
+                let (Some(id), Some(fingerprint)) = (id, fingerprint) else {
+                    continue;
+                };
+
+                txn.execute(Statement::from_sql_and_values(
+                    backend,
+                    "UPDATE indexed_document SET fingerprint = $1 WHERE doc_id = $2 AND fingerprint IS NULL",
+                    vec![(fingerprint as i64).into(), id.into()],
+                ))
+                .await?;
+            }
+        }
+
+        txn.commit().await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Instant;
+
+use sea_orm_migration::prelude::*;
+use shared::config::Config;
+use spyglass_searcher::document_to_struct;
+use spyglass_searcher::schema::{self, DocFields, DocumentUpdate, SearchDocument, ToDocument};
+use tantivy::directory::MmapDirectory;
+use tantivy::{DocAddress, Document, Index};
+
+use super::migration_utils;
+
+// Rebuilds the search index w/ the current schema if it doesn't have the field
+// `field_name` yet. Documents are re-added from the stored fields of the old
+// index, so nothing needs to be recrawled. Fields that aren't stored are derived
+// from the content again when the document is rebuilt. The old index is backed up
+// before the new one is moved into place.
+pub fn rebuild_index_with_field(field_name: &str) -> Result<(), DbErr> {
+    let config = Config::new();
+    let old_index_path = config.index_dir();
+    let new_index_path = old_index_path
+        .parent()
+        .expect("Expected parent path")
+        .join("migrated_index");
+
+    // Fresh install, the index will be created w/ the new schema.
+    if !old_index_path.exists() {
+        return Ok(());
+    }
+
+    let dir = MmapDirectory::open(&old_index_path)
+        .map_err(|e| DbErr::Custom(format!("Unable to open index: {e}")))?;
+    if !Index::exists(&dir).unwrap_or(false) {
+        return Ok(());
+    }
+
+    let now = Instant::now();
+    // Scoped so the old index is closed before it's replaced
+    {
+        let old_index =
+            Index::open(dir).map_err(|e| DbErr::Custom(format!("Unable to open index: {e}")))?;
+        let old_schema = old_index.schema();
+        if old_schema.get_field(field_name).is_some() {
+            // Already migrated
+            return Ok(());
+        }
+
+        if new_index_path.exists() {
+            // Leftover from an earlier failed attempt
+            let _ = std::fs::remove_dir_all(&new_index_path);
+        }
+
+        if let Err(e) = std::fs::create_dir(&new_index_path) {
+            return Err(DbErr::Custom(format!("Can't create new index: {e}")));
+        }
+
+        println!("Migrating index @ {old_index_path:?} to {new_index_path:?}");
+        let new_schema = DocFields::as_schema();
+        let new_index = schema::initialize_index(new_schema.clone(), &new_index_path)
+            .map_err(|e| DbErr::Custom(format!("Unable to create new index: {e}")))?;
+        let mut new_writer = new_index
+            .writer(50_000_000)
+            .map_err(|e| DbErr::Custom(format!("Unable to create writer: {e}")))?;
+
+        let reader = old_index
+            .reader()
+            .map_err(|e| DbErr::Custom(format!("Unable to read index: {e}")))?;
+        let searcher = reader.searcher();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let old_doc = match searcher.doc(DocAddress::new(segment_ord as u32, doc_id)) {
+                    Ok(doc) => doc,
+                    Err(e) => {
+                        log::error!("Unable to read doc {:?}", e);
+                        continue;
+                    }
+                };
+
+                // Same fields, but the field ids shifted w/ the new field
+                let mut doc = Document::default();
+                for value in old_doc.field_values() {
+                    let name = old_schema.get_field_name(value.field());
+                    if let Some(field) = new_schema.get_field(name) {
+                        doc.add_field_value(field, value.value().clone());
+                    }
+                }
+
+                let Some(doc) = document_to_struct(&doc) else {
+                    continue;
+                };
+
+                let tags = doc.tags.iter().map(|tag| *tag as i64).collect::<Vec<_>>();
+                let update = DocumentUpdate {
+                    doc_id: Some(doc.doc_id.clone()),
+                    title: &doc.title,
+                    domain: &doc.domain,
+                    url: &doc.url,
+                    content: &doc.content,
+                    tags: &tags,
+                    published_at: doc.published,
+                    last_modified: doc.last_modified,
+                };
+
+                if let Err(e) = new_writer.add_document(update.to_document()) {
+                    log::error!("Error migrating doc {:?}", e);
+                }
+            }
+        }
+
+        // Save change to new index
+        if let Err(e) = new_writer.commit() {
+            return Err(DbErr::Custom(format!("Unable to commit changes: {e}")));
+        }
+    }
+
+    if let Err(e) = migration_utils::backup_dir(&old_index_path) {
+        return Err(DbErr::Custom(format!("Unable to backup old index: {e}")));
+    }
+
+    // Move new index into place.
+    if let Err(e) = migration_utils::replace_dir(&new_index_path, &old_index_path) {
+        return Err(DbErr::Custom(format!(
+            "Unable to move new index into place: {e}"
+        )));
+    }
+
+    println!("Migration took {} seconds.", now.elapsed().as_secs());
+    Ok(())
+}
@@ -1 +1,2 @@
+pub mod index_utils;
 pub mod migration_utils;
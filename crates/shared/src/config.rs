@@ -1,7 +1,6 @@
 use crate::form::{FormType, SettingOpts};
 use diff::Diff;
 use directories::ProjectDirs;
-use embeddings::{embedding_setting_opts, EmbeddingSettings};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -17,9 +16,14 @@ pub use spyglass_lens::{
 mod audio;
 mod embeddings;
 mod filesystem;
+mod llm;
+mod search;
 mod user_actions;
 pub use audio::*;
+pub use embeddings::*;
 pub use filesystem::*;
+pub use llm::*;
+pub use search::*;
 pub use user_actions::*;
 
 pub const MAX_TOTAL_INFLIGHT: u32 = 100;
@@ -116,6 +120,14 @@ pub struct UserSettings {
     pub audio_settings: AudioSettings,
     #[serde(default)]
     pub embedding_settings: EmbeddingSettings,
+    #[serde(default)]
+    pub search_settings: SearchSettings,
+    #[serde(default)]
+    pub llm_settings: LlmSettings,
+    /// Elasticsearch compatible index server shared w/ other daemons, e.g.
+    /// `http://localhost:9200/spyglass`. The local index is used if empty.
+    #[serde(default)]
+    pub remote_index_url: String,
     // /// Hide the app icon from the dock/taskbar while running. Will still show up
     // /// in the menubar/systemtray.
     // #[serde(default)]
@@ -200,6 +212,13 @@ impl From<UserSettings> for Vec<(String, SettingOpts)> {
                 restart_required: true,
                 help_text: Some("Port number used by the Spyglass background services. Only change this if you already have another server running on this port. This will require a restart.".into())
             }),
+            ("_.remote_index_url".into(), SettingOpts {
+                label: "Remote Index URL".into(),
+                value: settings.remote_index_url.clone(),
+                form_type: FormType::Text,
+                restart_required: true,
+                help_text: Some("Elasticsearch compatible index shared w/ other Spyglass installs, e.g. http://localhost:9200/spyglass. Leave empty to use the local index. Autocomplete, related documents & sparse retrieval need a local index. This will require a restart.".into())
+            }),
         ];
 
         if let Limit::Finite(val) = settings.inflight_crawl_limit {
@@ -235,6 +254,8 @@ impl From<UserSettings> for Vec<(String, SettingOpts)> {
         config.extend(fs_setting_opts(&settings));
         config.extend(audio_setting_opts(&settings));
         config.extend(embedding_setting_opts(&settings));
+        config.extend(search_setting_opts(&settings));
+        config.extend(llm_setting_opts(&settings));
 
         config
     }
@@ -265,6 +286,9 @@ impl Default for UserSettings {
             user_action_settings: UserActionSettings::default(),
             audio_settings: AudioSettings::default(),
             embedding_settings: EmbeddingSettings::default(),
+            search_settings: SearchSettings::default(),
+            llm_settings: LlmSettings::default(),
+            remote_index_url: String::new(),
         }
     }
 }
@@ -359,6 +383,29 @@ impl Config {
         self.model_dir().join("embeddings")
     }
 
+    /// Where the files for an embedding model are stored
+    pub fn embedding_model_path(&self, model: EmbeddingModel) -> PathBuf {
+        model.model_dir(&self.embedding_model_dir())
+    }
+
+    pub fn rerank_model_dir(&self) -> PathBuf {
+        self.model_dir().join("rerank")
+    }
+
+    /// Where the files for a reranking model are stored
+    pub fn rerank_model_path(&self, model: RerankModel) -> PathBuf {
+        model.model_dir(&self.rerank_model_dir())
+    }
+
+    pub fn sparse_model_dir(&self) -> PathBuf {
+        self.model_dir().join("sparse")
+    }
+
+    /// Where the files for a SPLADE model are stored
+    pub fn sparse_model_path(&self, model: SparseModel) -> PathBuf {
+        model.model_dir(&self.sparse_model_dir())
+    }
+
     pub fn llm_model_dir(&self) -> PathBuf {
         self.model_dir().join("llm")
     }
@@ -1,29 +1,417 @@
+use std::path::{Path, PathBuf};
+
 use diff::Diff;
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
 
 use crate::form::{FormType, SettingOpts};
 
 use super::UserSettings;
 
-#[derive(Clone, Debug, Serialize, Deserialize, Diff, Default)]
+/// How the token embeddings of a model are combined into a single embedding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbeddingPooling {
+    /// Embedding of the first (CLS) token
+    Cls,
+    /// Average of all the token embeddings
+    Mean,
+}
+
+/// Where embeddings are generated
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Diff, Display, EnumString,
+)]
+pub enum EmbeddingBackend {
+    /// Runs the model on this machine
+    #[default]
+    #[strum(serialize = "local")]
+    Local,
+    /// Sends content to a service implementing the OpenAI `/v1/embeddings` API
+    #[strum(serialize = "remote")]
+    Remote,
+}
+
+/// How embeddings are stored in the vector table. Quantized embeddings take up a
+/// fraction of the space, at the cost of some precision.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Diff, Display, EnumString,
+)]
+pub enum VectorQuantization {
+    /// 4 bytes per dimension, exactly as generated by the model
+    #[strum(serialize = "float32")]
+    Float32,
+    /// 1 byte per dimension, w/ a single float32 scale per embedding
+    #[default]
+    #[strum(serialize = "int8")]
+    Int8,
+    /// 1 bit per dimension, only the sign of each value is kept, w/ a single
+    /// float32 scale per embedding
+    #[strum(serialize = "bit")]
+    Binary,
+}
+
+impl VectorQuantization {
+    /// How many more candidates are fetched from the quantized index than needed,
+    /// before they're rescored against the full precision query.
+    pub fn oversample(&self) -> u32 {
+        match self {
+            VectorQuantization::Float32 => 1,
+            VectorQuantization::Int8 => 4,
+            VectorQuantization::Binary => 10,
+        }
+    }
+}
+
+/// Models that can be used to generate embeddings for similarity search
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    Diff,
+    Display,
+    EnumIter,
+    EnumString,
+)]
+pub enum EmbeddingModel {
+    #[default]
+    #[strum(serialize = "nomic-embed-text-v1")]
+    NomicEmbedText,
+    #[strum(serialize = "bge-small-en-v1.5")]
+    BgeSmall,
+    #[strum(serialize = "all-MiniLM-L6-v2")]
+    MiniLm,
+    #[strum(serialize = "gte-base-en-v1.5")]
+    GteBase,
+    #[strum(serialize = "jina-embeddings-v2-base-code")]
+    JinaCode,
+}
+
+impl EmbeddingModel {
+    /// Hugging Face repository the model files are downloaded from
+    pub fn repo(&self) -> &'static str {
+        match self {
+            EmbeddingModel::NomicEmbedText => "nomic-ai/nomic-embed-text-v1",
+            EmbeddingModel::BgeSmall => "BAAI/bge-small-en-v1.5",
+            EmbeddingModel::MiniLm => "sentence-transformers/all-MiniLM-L6-v2",
+            EmbeddingModel::GteBase => "Alibaba-NLP/gte-base-en-v1.5",
+            EmbeddingModel::JinaCode => "jinaai/jina-embeddings-v2-base-code",
+        }
+    }
+
+    /// Where the model files are stored in `embeddings_dir`. The default model
+    /// lives at the root, where it was stored before models could be picked.
+    pub fn model_dir(&self, embeddings_dir: &Path) -> PathBuf {
+        match self {
+            EmbeddingModel::NomicEmbedText => embeddings_dir.to_path_buf(),
+            _ => embeddings_dir.join(self.to_string()),
+        }
+    }
+
+    /// Download link for one of the model files, e.g. `config.json`
+    pub fn file_url(&self, file: &str) -> String {
+        format!("https://huggingface.co/{}/resolve/main/{file}", self.repo())
+    }
+
+    /// Number of dimensions in the embeddings generated by the model
+    pub fn dimensions(&self) -> usize {
+        match self {
+            EmbeddingModel::BgeSmall | EmbeddingModel::MiniLm => 384,
+            EmbeddingModel::NomicEmbedText | EmbeddingModel::GteBase | EmbeddingModel::JinaCode => {
+                768
+            }
+        }
+    }
+
+    /// Max number of tokens embedded at once, chunks are kept under this
+    pub fn max_tokens(&self) -> usize {
+        match self {
+            EmbeddingModel::BgeSmall | EmbeddingModel::MiniLm => 512,
+            _ => 2048,
+        }
+    }
+
+    pub fn pooling(&self) -> EmbeddingPooling {
+        match self {
+            EmbeddingModel::BgeSmall | EmbeddingModel::GteBase => EmbeddingPooling::Cls,
+            EmbeddingModel::NomicEmbedText | EmbeddingModel::MiniLm | EmbeddingModel::JinaCode => {
+                EmbeddingPooling::Mean
+            }
+        }
+    }
+
+    /// Prepended to document content before it's embedded
+    pub fn document_prefix(&self) -> &'static str {
+        match self {
+            EmbeddingModel::NomicEmbedText => "search_document: ",
+            _ => "",
+        }
+    }
+
+    /// Prepended to search queries before they're embedded
+    pub fn query_prefix(&self) -> &'static str {
+        match self {
+            EmbeddingModel::NomicEmbedText => "search_query: ",
+            EmbeddingModel::BgeSmall => "Represent this sentence for searching relevant passages: ",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Diff)]
 pub struct EmbeddingSettings {
     pub enable_embeddings: bool,
+    /// Model used to generate embeddings. Changing it regenerates the embeddings
+    /// for every document.
+    #[serde(default)]
+    pub model: EmbeddingModel,
+    #[serde(default)]
+    pub backend: EmbeddingBackend,
+    /// Root of the remote embedding API, e.g. `http://192.168.1.10:8080/v1`
+    #[serde(default)]
+    pub remote_url: String,
+    /// Name the remote service knows the model by. Defaults to the name of `model`.
+    #[serde(default)]
+    pub remote_model: String,
+    /// Max number of chunks sent to the remote service in a single request.
+    #[serde(default = "EmbeddingSettings::default_remote_batch_size")]
+    pub remote_batch_size: u32,
+    /// Max number of chunks run through the local model in a single pass.
+    #[serde(default = "EmbeddingSettings::default_local_batch_size")]
+    pub local_batch_size: u32,
+    /// Max number of documents picked up from the embedding queue at once.
+    #[serde(default = "EmbeddingSettings::default_document_batch_size")]
+    pub document_batch_size: u32,
+    /// Max number of tokens in each chunk of a document that gets embedded.
+    #[serde(default = "EmbeddingSettings::default_chunk_size")]
+    pub chunk_size: u32,
+    /// Number of tokens a chunk can share w/ the chunk before it.
+    #[serde(default = "EmbeddingSettings::default_chunk_overlap")]
+    pub chunk_overlap: u32,
+    /// How embeddings are stored. Changing it regenerates the embeddings for every
+    /// document.
+    #[serde(default)]
+    pub quantization: VectorQuantization,
+}
+
+impl EmbeddingSettings {
+    pub fn default_chunk_size() -> u32 {
+        512
+    }
+
+    pub fn default_chunk_overlap() -> u32 {
+        64
+    }
+
+    pub fn default_remote_batch_size() -> u32 {
+        32
+    }
+
+    pub fn default_local_batch_size() -> u32 {
+        16
+    }
+
+    pub fn default_document_batch_size() -> u32 {
+        8
+    }
+
+    /// Name of the model sent to the remote embedding service
+    pub fn remote_model_name(&self) -> String {
+        if self.remote_model.trim().is_empty() {
+            self.model.to_string()
+        } else {
+            self.remote_model.trim().to_string()
+        }
+    }
+
+    /// Name of the model the embeddings are generated w/, the remote model name
+    /// when a remote service generates them.
+    pub fn effective_model_name(&self) -> String {
+        match self.backend {
+            EmbeddingBackend::Local => self.model.to_string(),
+            EmbeddingBackend::Remote => self.remote_model_name(),
+        }
+    }
+}
+
+impl Default for EmbeddingSettings {
+    fn default() -> Self {
+        EmbeddingSettings {
+            enable_embeddings: false,
+            model: EmbeddingModel::default(),
+            backend: EmbeddingBackend::default(),
+            remote_url: String::new(),
+            remote_model: String::new(),
+            remote_batch_size: EmbeddingSettings::default_remote_batch_size(),
+            local_batch_size: EmbeddingSettings::default_local_batch_size(),
+            document_batch_size: EmbeddingSettings::default_document_batch_size(),
+            chunk_size: EmbeddingSettings::default_chunk_size(),
+            chunk_overlap: EmbeddingSettings::default_chunk_overlap(),
+            quantization: VectorQuantization::default(),
+        }
+    }
 }
 
 #[allow(dead_code)]
 pub fn embedding_setting_opts(settings: &UserSettings) -> Vec<(String, SettingOpts)> {
-    vec![(
-        "_.embedding_settings.enable_embeddings".into(),
-        SettingOpts {
-            label: "Beta: Enable Similarity Search".into(),
-            value: settings.embedding_settings.enable_embeddings.to_string(),
-            form_type: FormType::Bool,
-            restart_required: false,
-            help_text: Some(
-                r#"Embeddings are generated for documents and search will check for
+    let embedding = &settings.embedding_settings;
+    vec![
+        (
+            "_.embedding_settings.enable_embeddings".into(),
+            SettingOpts {
+                label: "Beta: Enable Similarity Search".into(),
+                value: embedding.enable_embeddings.to_string(),
+                form_type: FormType::Bool,
+                restart_required: false,
+                help_text: Some(
+                    r#"Embeddings are generated for documents and search will check for
                    semantic similarity as well as standard search."#
-                    .into(),
-            ),
-        },
-    )]
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.model".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Model".into(),
+                value: embedding.model.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(format!(
+                    r#"Model used to generate embeddings, one of {}. Switching models
+                       regenerates the embeddings for every document in the background."#,
+                    EmbeddingModel::iter()
+                        .map(|model| format!("\"{model}\""))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
+        ),
+        (
+            "_.embedding_settings.backend".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Backend".into(),
+                value: embedding.backend.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"Either "local" to generate embeddings on this machine or "remote" to
+                       use an OpenAI compatible embedding service."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.remote_url".into(),
+            SettingOpts {
+                label: "Beta: Remote Embedding URL".into(),
+                value: embedding.remote_url.clone(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"Root of the remote embedding API when using the "remote" backend,
+                       e.g. http://192.168.1.10:8080/v1"#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.remote_model".into(),
+            SettingOpts {
+                label: "Beta: Remote Embedding Model".into(),
+                value: embedding.remote_model.clone(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"Name the remote service knows the model by. Leave empty to use the
+                       name of the similarity search model. Changing it regenerates the
+                       embeddings for every document."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.remote_batch_size".into(),
+            SettingOpts {
+                label: "Beta: Remote Embedding Batch Size".into(),
+                value: embedding.remote_batch_size.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    "Max number of chunks sent to the remote service in a single request.".into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.local_batch_size".into(),
+            SettingOpts {
+                label: "Beta: Local Embedding Batch Size".into(),
+                value: embedding.local_batch_size.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    r#"Max number of chunks run through the local model at once. Larger
+                       batches are faster but use more memory."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.document_batch_size".into(),
+            SettingOpts {
+                label: "Beta: Embedding Document Batch Size".into(),
+                value: embedding.document_batch_size.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some("Max number of documents embedded together.".into()),
+            },
+        ),
+        (
+            "_.embedding_settings.chunk_size".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Chunk Size".into(),
+                value: embedding.chunk_size.to_string(),
+                form_type: FormType::Number,
+                restart_required: true,
+                help_text: Some(
+                    r#"Long documents are split into chunks of up to this many tokens
+                       before generating embeddings. Only applies to newly indexed documents."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.chunk_overlap".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Chunk Overlap".into(),
+                value: embedding.chunk_overlap.to_string(),
+                form_type: FormType::Number,
+                restart_required: true,
+                help_text: Some(
+                    "Number of tokens each chunk can share w/ the chunk before it.".into(),
+                ),
+            },
+        ),
+        (
+            "_.embedding_settings.quantization".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Storage".into(),
+                value: embedding.quantization.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"How embeddings are stored, one of "float32", "int8" or "bit". "int8"
+                       takes a quarter of the space w/ little loss in quality, "bit" is the
+                       smallest but least accurate. Changing it regenerates the embeddings
+                       for every document in the background."#
+                        .into(),
+                ),
+            },
+        ),
+    ]
 }
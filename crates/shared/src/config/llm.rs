@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+
+use diff::Diff;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+use super::UserSettings;
+use crate::form::{FormType, SettingOpts};
+
+/// Prompt formats used by chat models
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    Diff,
+    Display,
+    EnumIter,
+    EnumString,
+)]
+pub enum ChatTemplate {
+    /// Llama 3 instruct models
+    #[default]
+    #[strum(serialize = "llama3")]
+    Llama3,
+    /// ChatML, used by Qwen, Hermes & a lot of other fine-tunes
+    #[strum(serialize = "chatml")]
+    ChatMl,
+    /// Mistral & Mixtral instruct models
+    #[strum(serialize = "mistral")]
+    Mistral,
+    /// Phi 3 & 3.5 instruct models
+    #[strum(serialize = "phi3")]
+    Phi3,
+}
+
+impl ChatTemplate {
+    /// Template in `assets/templates/llm` the prompt is rendered w/
+    pub fn template_file(&self) -> &'static str {
+        match self {
+            ChatTemplate::Llama3 => "llama3-instruct.txt",
+            ChatTemplate::ChatMl => "chatml.txt",
+            ChatTemplate::Mistral => "mistral-instruct.txt",
+            ChatTemplate::Phi3 => "phi3.5-instruct.txt",
+        }
+    }
+
+    /// Special tokens that end the assistant's turn
+    pub fn eos_tokens(&self) -> &'static [&'static str] {
+        match self {
+            ChatTemplate::Llama3 => &["<|eot_id|>", "<|end_of_text|>"],
+            ChatTemplate::ChatMl => &["<|im_end|>", "<|endoftext|>"],
+            ChatTemplate::Mistral => &["</s>"],
+            ChatTemplate::Phi3 => &["<|end|>", "<|endoftext|>"],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Diff)]
+pub struct LlmSettings {
+    /// GGUF model file used for chat, the bundled Llama 3.2 model is used if unset.
+    #[serde(default)]
+    pub model_path: Option<PathBuf>,
+    #[serde(default)]
+    pub chat_template: ChatTemplate,
+    /// Higher values make answers more random, 0 always picks the likeliest token.
+    #[serde(default = "LlmSettings::default_temperature")]
+    pub temperature: f32,
+    /// Only tokens making up this much of the probability mass are sampled from.
+    #[serde(default = "LlmSettings::default_top_p")]
+    pub top_p: f32,
+    /// Max number of tokens generated for an answer.
+    #[serde(default = "LlmSettings::default_max_tokens")]
+    pub max_tokens: u32,
+    /// Generation stops once the answer contains any of these.
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+    /// Max number of tokens in the prompt & answer combined. The oldest messages
+    /// are dropped when a conversation gets too long.
+    #[serde(default = "LlmSettings::default_context_length")]
+    pub context_length: u32,
+}
+
+impl LlmSettings {
+    pub fn default_temperature() -> f32 {
+        0.7
+    }
+
+    pub fn default_top_p() -> f32 {
+        0.9
+    }
+
+    pub fn default_max_tokens() -> u32 {
+        1024
+    }
+
+    pub fn default_context_length() -> u32 {
+        8192
+    }
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        LlmSettings {
+            model_path: None,
+            chat_template: ChatTemplate::default(),
+            temperature: LlmSettings::default_temperature(),
+            top_p: LlmSettings::default_top_p(),
+            max_tokens: LlmSettings::default_max_tokens(),
+            stop_sequences: Vec::new(),
+            context_length: LlmSettings::default_context_length(),
+        }
+    }
+}
+
+pub fn llm_setting_opts(settings: &UserSettings) -> Vec<(String, SettingOpts)> {
+    let llm = &settings.llm_settings;
+    vec![
+        (
+            "_.llm_settings.model_path".into(),
+            SettingOpts {
+                label: "Beta: Chat Model".into(),
+                value: llm
+                    .model_path
+                    .as_ref()
+                    .and_then(|path| path.to_str())
+                    .map_or(String::new(), |path| path.to_string()),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"Path to a GGUF model file used to answer questions. Leave empty to
+                       use the bundled Llama 3.2 3B model."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.llm_settings.chat_template".into(),
+            SettingOpts {
+                label: "Beta: Chat Template".into(),
+                value: llm.chat_template.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(format!(
+                    "Prompt format the chat model was trained w/, one of {}.",
+                    ChatTemplate::iter()
+                        .map(|template| format!("\"{template}\""))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
+        ),
+        (
+            "_.llm_settings.temperature".into(),
+            SettingOpts {
+                label: "Beta: Chat Temperature".into(),
+                value: llm.temperature.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"Between 0 & 2. Higher values make answers more varied, 0 always
+                       picks the most likely word."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.llm_settings.top_p".into(),
+            SettingOpts {
+                label: "Beta: Chat Top P".into(),
+                value: llm.top_p.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"Between 0 & 1. Only the most likely words making up this share of
+                       the probabilities are picked from."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.llm_settings.max_tokens".into(),
+            SettingOpts {
+                label: "Beta: Max Answer Length".into(),
+                value: llm.max_tokens.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some("Max number of tokens generated for an answer.".into()),
+            },
+        ),
+        (
+            "_.llm_settings.stop_sequences".into(),
+            SettingOpts {
+                label: "Beta: Stop Sequences".into(),
+                value: serde_json::to_string(&llm.stop_sequences)
+                    .expect("Unable to ser stop sequences"),
+                form_type: FormType::StringList,
+                restart_required: false,
+                help_text: Some(
+                    "Answers end as soon as they contain any of these, on top of the model's own end token."
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.llm_settings.context_length".into(),
+            SettingOpts {
+                label: "Beta: Chat Context Length".into(),
+                value: llm.context_length.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    r#"Max number of tokens in the conversation & answer combined. Older
+                       messages are dropped once a conversation gets longer."#
+                        .into(),
+                ),
+            },
+        ),
+    ]
+}
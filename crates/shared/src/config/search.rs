@@ -0,0 +1,345 @@
+use std::path::{Path, PathBuf};
+
+use diff::Diff;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+use super::UserSettings;
+use crate::form::{FormType, SettingOpts};
+
+/// How keyword & similarity search results are combined
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Diff, Display, EnumString,
+)]
+pub enum FusionStrategy {
+    /// Reciprocal rank fusion, only looks at the rank of a document in each list.
+    #[default]
+    #[strum(serialize = "rrf")]
+    ReciprocalRank,
+    /// Weighted sum of the normalized scores from each list.
+    #[strum(serialize = "weighted")]
+    Weighted,
+}
+
+/// Cross-encoders that can be used to rerank the top search results
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    Diff,
+    Display,
+    EnumIter,
+    EnumString,
+)]
+pub enum RerankModel {
+    /// Small English BERT model trained on MS MARCO, fast enough for most machines
+    #[default]
+    #[strum(serialize = "ms-marco-MiniLM-L-6-v2")]
+    MsMarcoMiniLm,
+    /// Larger multilingual XLM-RoBERTa model, more accurate but slower
+    #[strum(serialize = "bge-reranker-base")]
+    BgeRerankerBase,
+}
+
+impl RerankModel {
+    /// Hugging Face repository the model files are downloaded from
+    pub fn repo(&self) -> &'static str {
+        match self {
+            RerankModel::MsMarcoMiniLm => "cross-encoder/ms-marco-MiniLM-L-6-v2",
+            RerankModel::BgeRerankerBase => "BAAI/bge-reranker-base",
+        }
+    }
+
+    /// Where the model files are stored in `rerank_dir`
+    pub fn model_dir(&self, rerank_dir: &Path) -> PathBuf {
+        rerank_dir.join(self.to_string())
+    }
+
+    /// Download link for one of the model files, e.g. `config.json`
+    pub fn file_url(&self, file: &str) -> String {
+        format!("https://huggingface.co/{}/resolve/main/{file}", self.repo())
+    }
+}
+
+/// SPLADE models that can be used to expand documents & queries into weighted terms
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    Diff,
+    Display,
+    EnumIter,
+    EnumString,
+)]
+pub enum SparseModel {
+    /// English BERT model distilled from an ensemble of rankers
+    #[default]
+    #[strum(serialize = "splade-cocondenser-ensembledistil")]
+    SpladeEnsembleDistil,
+    /// Same base model, distilled from a single cross-encoder
+    #[strum(serialize = "splade-cocondenser-selfdistil")]
+    SpladeSelfDistil,
+}
+
+impl SparseModel {
+    /// Hugging Face repository the model files are downloaded from
+    pub fn repo(&self) -> &'static str {
+        match self {
+            SparseModel::SpladeEnsembleDistil => "naver/splade-cocondenser-ensembledistil",
+            SparseModel::SpladeSelfDistil => "naver/splade-cocondenser-selfdistil",
+        }
+    }
+
+    /// Where the model files are stored in `sparse_dir`
+    pub fn model_dir(&self, sparse_dir: &Path) -> PathBuf {
+        sparse_dir.join(self.to_string())
+    }
+
+    /// Download link for one of the model files, e.g. `config.json`
+    pub fn file_url(&self, file: &str) -> String {
+        format!("https://huggingface.co/{}/resolve/main/{file}", self.repo())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Diff)]
+pub struct SearchSettings {
+    #[serde(default)]
+    pub fusion_strategy: FusionStrategy,
+    /// Rank constant used by reciprocal rank fusion, higher values flatten the
+    /// difference between the top ranks.
+    #[serde(default = "SearchSettings::default_rrf_k")]
+    pub rrf_k: u32,
+    /// Percentage (0-100) of the score coming from similarity search when using
+    /// weighted fusion.
+    #[serde(default = "SearchSettings::default_semantic_weight")]
+    pub semantic_weight: u32,
+    /// Number of results pulled from each of keyword & similarity search before
+    /// they're combined.
+    #[serde(default = "SearchSettings::default_candidates")]
+    pub num_candidates: u32,
+    /// Similarity search results further than this from the query are ignored.
+    #[serde(default = "SearchSettings::default_max_distance")]
+    pub max_semantic_distance: u32,
+    /// Reorders the top results w/ a cross-encoder, which reads the query &
+    /// each result together.
+    #[serde(default)]
+    pub enable_reranking: bool,
+    #[serde(default)]
+    pub rerank_model: RerankModel,
+    /// Number of top results that get reranked.
+    #[serde(default = "SearchSettings::default_rerank_depth")]
+    pub rerank_depth: u32,
+    /// Time reranking can add to a search, in milliseconds. Results that haven't
+    /// been scored by then keep their place.
+    #[serde(default = "SearchSettings::default_rerank_budget_ms")]
+    pub rerank_budget_ms: u32,
+    /// Expands documents & queries into weighted terms w/ a SPLADE model & uses
+    /// those to find similar documents instead of embeddings.
+    #[serde(default)]
+    pub enable_sparse_retrieval: bool,
+    #[serde(default)]
+    pub sparse_model: SparseModel,
+}
+
+impl SearchSettings {
+    pub fn default_rrf_k() -> u32 {
+        60
+    }
+
+    pub fn default_semantic_weight() -> u32 {
+        50
+    }
+
+    pub fn default_candidates() -> u32 {
+        50
+    }
+
+    pub fn default_max_distance() -> u32 {
+        25
+    }
+
+    pub fn default_rerank_depth() -> u32 {
+        20
+    }
+
+    pub fn default_rerank_budget_ms() -> u32 {
+        250
+    }
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        SearchSettings {
+            fusion_strategy: FusionStrategy::default(),
+            rrf_k: SearchSettings::default_rrf_k(),
+            semantic_weight: SearchSettings::default_semantic_weight(),
+            num_candidates: SearchSettings::default_candidates(),
+            max_semantic_distance: SearchSettings::default_max_distance(),
+            enable_reranking: false,
+            rerank_model: RerankModel::default(),
+            rerank_depth: SearchSettings::default_rerank_depth(),
+            rerank_budget_ms: SearchSettings::default_rerank_budget_ms(),
+            enable_sparse_retrieval: false,
+            sparse_model: SparseModel::default(),
+        }
+    }
+}
+
+pub fn search_setting_opts(settings: &UserSettings) -> Vec<(String, SettingOpts)> {
+    let search = &settings.search_settings;
+    vec![
+        (
+            "_.search_settings.fusion_strategy".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Ranking".into(),
+                value: search.fusion_strategy.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(
+                    r#"How keyword & similarity search results are combined. Either "rrf"
+                       (reciprocal rank fusion) or "weighted"."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.rrf_k".into(),
+            SettingOpts {
+                label: "Beta: Rank Fusion Constant".into(),
+                value: search.rrf_k.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    r#"Used by "rrf" ranking. Higher values give lower ranked results
+                       more of a say."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.semantic_weight".into(),
+            SettingOpts {
+                label: "Beta: Similarity Search Weight".into(),
+                value: search.semantic_weight.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    r#"Used by "weighted" ranking. Percentage (0-100) of the score that
+                       comes from similarity search, the rest comes from keyword search."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.num_candidates".into(),
+            SettingOpts {
+                label: "Beta: Ranking Candidates".into(),
+                value: search.num_candidates.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    "Number of results from each of keyword & similarity search considered when ranking."
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.enable_reranking".into(),
+            SettingOpts {
+                label: "Beta: Rerank Top Results".into(),
+                value: search.enable_reranking.to_string(),
+                form_type: FormType::Bool,
+                restart_required: false,
+                help_text: Some(
+                    r#"Reorders the top results w/ a model that reads the query & each
+                       result together. Improves ranking for ambiguous queries at the cost
+                       of slower searches."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.rerank_model".into(),
+            SettingOpts {
+                label: "Beta: Reranking Model".into(),
+                value: search.rerank_model.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(format!(
+                    "Model used to rerank results, one of {}.",
+                    RerankModel::iter()
+                        .map(|model| format!("\"{model}\""))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
+        ),
+        (
+            "_.search_settings.rerank_depth".into(),
+            SettingOpts {
+                label: "Beta: Reranked Results".into(),
+                value: search.rerank_depth.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some("Number of top results that get reranked.".into()),
+            },
+        ),
+        (
+            "_.search_settings.rerank_budget_ms".into(),
+            SettingOpts {
+                label: "Beta: Reranking Time Limit".into(),
+                value: search.rerank_budget_ms.to_string(),
+                form_type: FormType::Number,
+                restart_required: false,
+                help_text: Some(
+                    r#"Max time in milliseconds reranking can add to a search. Results
+                       that haven't been reranked by then keep their place."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.enable_sparse_retrieval".into(),
+            SettingOpts {
+                label: "Beta: Sparse Similarity Search".into(),
+                value: search.enable_sparse_retrieval.to_string(),
+                form_type: FormType::Bool,
+                restart_required: false,
+                help_text: Some(
+                    r#"Finds similar documents w/ a model that expands documents & queries
+                       into related terms instead of embeddings. Runs well on machines w/o
+                       a GPU. Documents already in the index are expanded in the background
+                       once it's enabled."#
+                        .into(),
+                ),
+            },
+        ),
+        (
+            "_.search_settings.sparse_model".into(),
+            SettingOpts {
+                label: "Beta: Sparse Search Model".into(),
+                value: search.sparse_model.to_string(),
+                form_type: FormType::Text,
+                restart_required: false,
+                help_text: Some(format!(
+                    "Model used to expand documents & queries, one of {}. Changing it expands every document again in the background.",
+                    SparseModel::iter()
+                        .map(|model| format!("\"{model}\""))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
+        ),
+    ]
+}
@@ -24,12 +24,3 @@ pub const WHISPER_MODEL_CONFIG: &str =
 
 pub const WHISPER_MODEL_TOKENIZER: &str =
     "https://huggingface.co/openai/whisper-base.en/resolve/main/tokenizer.json";
-
-pub const EMBEDDING_MODEL: &str =
-    "https://huggingface.co/nomic-ai/nomic-embed-text-v1/resolve/main/model.safetensors";
-
-pub const EMBEDDING_MODEL_CONFIG: &str =
-    "https://huggingface.co/nomic-ai/nomic-embed-text-v1/resolve/main/config.json";
-
-pub const EMBEDDING_MODEL_TOKENIZER: &str =
-    "https://huggingface.co/nomic-ai/nomic-embed-text-v1/resolve/main/tokenizer.json";
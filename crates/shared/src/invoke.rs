@@ -9,8 +9,12 @@ use ts_rs::TS;
 pub enum ClientInvoke {
     #[serde(rename = "ask_clippy")]
     AskClippy,
+    #[serde(rename = "autocomplete")]
+    Autocomplete,
     #[serde(rename = "authorize_connection")]
     AuthorizeConnection,
+    #[serde(rename = "cancel_clippy")]
+    CancelClippy,
     #[serde(rename = "choose_folder")]
     ChooseFolder,
     #[serde(rename = "copy_to_clipboard")]
@@ -53,6 +57,8 @@ pub enum ClientInvoke {
     OpenSettingsFolder,
     #[serde(rename = "resize_window")]
     ResizeWindow,
+    #[serde(rename = "related_documents")]
+    RelatedDocuments,
     #[serde(rename = "resync_connection")]
     ResyncConnection,
     #[serde(rename = "revoke_connection")]
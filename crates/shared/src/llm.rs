@@ -1,11 +1,15 @@
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use crate::response::TextSpan;
+
 // Generation is roughly the order things happen.
 #[derive(Deserialize, Serialize, PartialEq, Eq, TS)]
 #[serde(tag = "type", content = "content")]
 #[ts(export)]
 pub enum ChatStream {
+    /// Document segments added to the prompt, numbered as the answer cites them
+    DocumentContextAdded(Vec<ChatCitation>),
     LoadingPrompt,
     ChatStart,
     Token(String),
@@ -30,8 +34,64 @@ pub struct ChatMessage {
     pub content: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
 #[ts(export)]
 pub struct LlmSession {
     pub messages: Vec<ChatMessage>,
+    /// Only use documents in these lenses to answer
+    #[serde(default)]
+    pub lenses: Vec<String>,
+    /// Only use these documents to answer, e.g. when asking about a specific doc
+    #[serde(default)]
+    pub doc_ids: Vec<String>,
+    /// Overrides the generation settings for this request
+    #[serde(default)]
+    pub options: GenerationOptions,
+    /// Tags the streamed events & is used to cancel the chat, generated if unset
+    #[serde(default)]
+    pub chat_id: Option<String>,
+}
+
+/// Streamed event for a single chat
+#[derive(Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct ChatStreamEvent {
+    pub chat_id: String,
+    pub event: ChatStream,
+}
+
+/// Generation settings that can be changed per request, unset values fall back
+/// to the LLM settings.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, TS)]
+#[ts(export)]
+pub struct GenerationOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+/// Document segment used to answer a question
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct ChatCitation {
+    /// Number the segment is cited by in the answer, e.g. [1]
+    pub index: u32,
+    pub doc_id: String,
+    pub title: String,
+    pub open_url: String,
+    /// Location of the segment in the document content
+    pub segment: TextSpan,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export)]
+pub struct ChatResponse {
+    pub chat_id: String,
+    pub message: ChatMessage,
+    /// Segments cited in the answer, or every segment added to the prompt if the
+    /// answer doesn't cite any.
+    pub citations: Vec<ChatCitation>,
+    /// Generation was stopped early by `cancel_chat`
+    pub cancelled: bool,
 }
@@ -2,11 +2,72 @@ use crate::response::DocMetadata;
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct SearchParam {
     pub lenses: Vec<String>,
     pub query: String,
     pub offset: Option<u32>,
+    /// Max number of results to return, defaults to 5 & is capped at 100.
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// `next_cursor` from the previous page of results. Takes precedence over
+    /// `offset` & keeps pages from shifting as documents are added or removed.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Only return documents modified after this time (unix timestamp, seconds)
+    #[serde(default)]
+    pub modified_after: Option<i64>,
+    /// Only return documents modified before this time (unix timestamp, seconds)
+    #[serde(default)]
+    pub modified_before: Option<i64>,
+    #[serde(default)]
+    pub sort: SearchSort,
+    /// Only match the query as typed, disabling the typo tolerant retry &
+    /// spelling suggestions.
+    #[serde(default)]
+    pub exact: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SearchSort {
+    /// Sort by relevance to the query
+    #[default]
+    Relevance,
+    /// Most recently modified documents first
+    Newest,
+    /// Least recently modified documents first
+    Oldest,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AutocompleteParam {
+    /// Partially typed query
+    pub query: String,
+    /// Max number of completions to return
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RelatedParam {
+    /// Document to find related documents for
+    pub doc_id: String,
+    /// Only return related documents in these lenses
+    #[serde(default)]
+    pub lenses: Vec<String>,
+    /// Max number of related documents to return
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ExplainParam {
+    pub query: String,
+    /// Document to explain the score for
+    pub doc_id: String,
+    /// Lenses the search was limited to
+    #[serde(default)]
+    pub lenses: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -8,6 +8,32 @@ use url::Url;
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AppStatus {
     pub num_docs: u64,
+    /// Progress regenerating embeddings after switching embedding models
+    #[serde(default)]
+    pub embedding_rebuild: Option<EmbeddingRebuildStatus>,
+    /// Progress adding sparse expansion terms to the documents already indexed
+    #[serde(default)]
+    pub sparse_backfill: Option<SparseBackfillStatus>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EmbeddingRebuildStatus {
+    /// Model the embeddings are being generated w/
+    pub model: String,
+    /// Number of documents queued when the rebuild started
+    pub num_docs: u64,
+    /// Number of documents still waiting to be embedded
+    pub num_remaining: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SparseBackfillStatus {
+    /// Model the documents are being expanded w/
+    pub model: String,
+    /// Number of documents in the index when the backfill started
+    pub num_docs: u64,
+    /// Number of documents expanded so far
+    pub num_done: u64,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, TS)]
@@ -136,6 +162,16 @@ pub struct SearchMeta {
     pub query: String,
     pub num_docs: u32,
     pub wall_time_ms: u32,
+    /// Results include typo tolerant matches since too few docs matched exactly
+    #[serde(default)]
+    pub is_fuzzy: bool,
+    /// Number of documents matching the query
+    #[serde(default)]
+    pub total_hits: u32,
+    /// Pass this w/ the same query to get the next page of results, empty when
+    /// there are no more results.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, TS)]
@@ -151,6 +187,27 @@ pub struct SearchResult {
     pub url: String,
     pub tags: Vec<(String, String)>,
     pub score: f32,
+    /// URLs of near-duplicates (e.g. mirrors) collapsed into this result
+    #[serde(default)]
+    pub also_at: Vec<String>,
+    /// Set when the description is the passage that best matched the query
+    /// semantically, rather than a highlight of the query terms.
+    #[serde(default)]
+    pub semantic_match: bool,
+    /// Location of the passage that best matched the query semantically, if the
+    /// document was found through similarity search.
+    #[serde(default)]
+    pub passage: Option<TextSpan>,
+}
+
+/// Character offsets into the content of a document
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct TextSpan {
+    /// Offset of the first character
+    pub start: u32,
+    /// Offset after the last character
+    pub end: u32,
 }
 
 // The search result template is used to provide extra
@@ -237,11 +294,130 @@ impl From<SearchResult> for SearchResultTemplate {
     }
 }
 
+/// Number of matched documents w/ a specific facet value
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u32,
+}
+
+/// Facet counts computed over all the documents matched by a search, sorted by count.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SearchFacets {
+    pub lenses: Vec<FacetCount>,
+    pub sources: Vec<FacetCount>,
+    pub file_types: Vec<FacetCount>,
+    pub mime_types: Vec<FacetCount>,
+    pub domains: Vec<FacetCount>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, TS)]
 #[ts(export)]
 pub struct SearchResults {
     pub results: Vec<SearchResult>,
     pub meta: SearchMeta,
+    #[serde(default)]
+    pub facets: SearchFacets,
+    /// Spelling correction for the query, e.g. for a "Did you mean" prompt
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
+#[ts(export)]
+pub struct RelatedResults {
+    pub doc_id: String,
+    /// Most related documents first
+    pub results: Vec<SearchResult>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum CompletionSource {
+    /// A query the user searched for before
+    RecentQuery,
+    /// Title of an indexed document
+    Title,
+    /// The query completed w/ a term from the index
+    Term,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct Completion {
+    pub text: String,
+    pub source: CompletionSource,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
+#[ts(export)]
+pub struct AutocompleteResults {
+    pub query: String,
+    /// Recent queries first, followed by titles & then index terms
+    pub completions: Vec<Completion>,
+}
+
+/// What part of the query a score contribution came from
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ScoreKind {
+    /// BM25 score for a single query term
+    Term,
+    /// Typo tolerant match for a query term
+    FuzzyTerm,
+    /// Query terms appearing together as a phrase
+    Phrase,
+    /// Tags mentioned in the query, e.g. "repository"
+    Tag,
+    /// Lens or tag filters
+    Lens,
+    Favorite,
+    Document,
+    Url,
+    CustomField,
+    /// Query operators such as `domain:` or `"exact phrases"`
+    Operator,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ScoreComponent {
+    pub kind: ScoreKind,
+    /// Field the term or phrase was matched in
+    pub field: Option<String>,
+    /// Term, operator or tag the score is for
+    pub label: String,
+    pub score: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SemanticScore {
+    /// Distance between the query & document embeddings, smaller is closer
+    pub distance: f32,
+    /// Position in the similarity search results, starting at 1
+    pub rank: u32,
+    /// What the similarity match adds to the final score
+    pub score: f32,
+}
+
+/// Breakdown of why a document scored the way it did for a query
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ScoreExplanation {
+    pub query: String,
+    pub doc_id: String,
+    /// Whether the document matches the query & filters at all
+    pub matches: bool,
+    /// Keyword search score, made up of the `components`
+    pub lexical_score: f32,
+    /// Position in the keyword search results, starting at 1. Empty if the document
+    /// isn't in the top results considered for ranking.
+    pub lexical_rank: Option<u32>,
+    pub components: Vec<ScoreComponent>,
+    /// Set if the document was found through similarity search
+    pub semantic: Option<SemanticScore>,
+    /// Score used to rank the document, after keyword & similarity search results
+    /// are combined.
+    pub score: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -305,20 +481,6 @@ pub struct DefaultIndices {
     pub extensions: Vec<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct SimilarityResultPayload {
-    pub title: String,
-    pub url: String,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct SimilaritySearchResult {
-    pub id: usize,
-    pub version: usize,
-    pub score: f32,
-    pub payload: SimilarityResultPayload,
-}
-
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct DocMetadata {
     pub doc_id: String,
@@ -1,5 +1,6 @@
+use shared::config::LlmSettings;
 use shared::llm::{ChatMessage, ChatRole, ChatStream, LlmSession};
-use spyglass_llm::LlmClient;
+use spyglass_llm::{LlmClient, DEFAULT_MODEL_PATH};
 /// This is mainly for testing the llm client, should remove this after it's comfortably
 /// integrated with the other code.
 ///
@@ -26,6 +27,7 @@ pub async fn main() -> Result<(), anyhow::Error> {
                 content: "What is the capital of Zimbabwe?".into(),
             },
         ],
+        ..Default::default()
     };
 
     let (tx, mut rx) = mpsc::channel(10);
@@ -33,6 +35,7 @@ pub async fn main() -> Result<(), anyhow::Error> {
     tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
             match msg {
+                ChatStream::DocumentContextAdded(_) => {}
                 ChatStream::LoadingPrompt => {
                     log::info!("loading prompt...");
                 }
@@ -51,9 +54,10 @@ pub async fn main() -> Result<(), anyhow::Error> {
         }
     });
 
-    match LlmClient::new("assets/models/llm/llama3/Llama-3.2-3B-Instruct.Q5_K_M.gguf".into()) {
+    let settings = LlmSettings::default();
+    match LlmClient::new(DEFAULT_MODEL_PATH.into(), settings.chat_template) {
         Ok(mut client) => {
-            client.chat(&prompt, Some(tx)).await?;
+            client.chat(&prompt, &settings, Some(tx), None).await?;
         }
         Err(error) => {
             log::error!("Error loading model {error}");
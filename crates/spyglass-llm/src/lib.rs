@@ -1,14 +1,20 @@
 use anyhow::Result;
 use lazy_static::lazy_static;
 use model::LLMModel;
-use shared::llm::{ChatMessage, ChatRole, ChatStream, LlmSession};
+use shared::config::{ChatTemplate, LlmSettings};
+use shared::llm::{ChatMessage, ChatRole, ChatStream, GenerationOptions, LlmSession};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tera::{Context, Tera};
 
 pub mod model;
 pub mod sampler;
 mod token_output_stream;
 
+/// Model used when no GGUF file is picked in the settings
+pub const DEFAULT_MODEL_PATH: &str = "assets/models/llm/llama3/Llama-3.2-3B-Instruct.Q5_K_M.gguf";
+
 lazy_static! {
     pub static ref TEMPLATES: Tera = {
         match Tera::new("assets/templates/llm/*.txt") {
@@ -21,27 +27,71 @@ lazy_static! {
     };
 }
 
+/// Sampling parameters for a single chat request
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationParams {
+    pub temperature: f64,
+    pub top_p: f64,
+    pub max_tokens: usize,
+    pub stop_sequences: Vec<String>,
+    pub context_length: usize,
+}
+
+impl GenerationParams {
+    /// The LLM settings w/ the request `options` applied on top
+    pub fn new(settings: &LlmSettings, options: &GenerationOptions) -> Self {
+        Self {
+            temperature: options.temperature.unwrap_or(settings.temperature).max(0.0) as f64,
+            top_p: options.top_p.unwrap_or(settings.top_p).clamp(0.0, 1.0) as f64,
+            max_tokens: options.max_tokens.unwrap_or(settings.max_tokens).max(1) as usize,
+            stop_sequences: options
+                .stop_sequences
+                .clone()
+                .unwrap_or_else(|| settings.stop_sequences.clone())
+                .into_iter()
+                .filter(|stop| !stop.is_empty())
+                .collect(),
+            context_length: settings.context_length as usize,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LlmClient {
     llm: LLMModel,
+    template: ChatTemplate,
 }
 
 impl LlmClient {
-    pub fn new(gguf_path: PathBuf) -> Result<Self> {
+    pub fn new(gguf_path: PathBuf, template: ChatTemplate) -> Result<Self> {
         Ok(Self {
-            llm: LLMModel::new(gguf_path)?,
+            llm: LLMModel::new(gguf_path, template.eos_tokens())?,
+            template,
         })
     }
 
+    /// Generates the next assistant message. Setting `cancel` stops generation
+    /// after the current token & returns the message so far.
     pub async fn chat(
         &mut self,
         session: &LlmSession,
+        settings: &LlmSettings,
         stream: Option<tokio::sync::mpsc::Sender<ChatStream>>,
+        cancel: Option<Arc<AtomicBool>>,
     ) -> Result<ChatMessage> {
+        let is_cancelled = || {
+            cancel
+                .as_ref()
+                .map(|cancel| cancel.load(Ordering::Relaxed))
+                .unwrap_or_default()
+        };
+        let params = GenerationParams::new(settings, &session.options);
+
         // Encode the prompt.
         let mut all_tokens = vec![];
         let mut content_buffer = String::new();
-        let mut sampler = self.llm.sampler();
+        let mut sampler = self.llm.sampler(&params);
+        self.llm.stream.clear();
 
         // process prompt
         let mut timer = std::time::Instant::now();
@@ -49,8 +99,18 @@ impl LlmClient {
             let _ = stream.send(ChatStream::LoadingPrompt).await;
         }
 
-        let prompt_contents =
-            TEMPLATES.render("llama3-instruct.txt", &Context::from_serialize(session)?)?;
+        if is_cancelled() {
+            if let Some(stream) = &stream {
+                let _ = stream.send(ChatStream::ChatDone).await;
+            }
+
+            return Ok(ChatMessage {
+                role: ChatRole::Assistant,
+                content: content_buffer,
+            });
+        }
+
+        let (prompt_contents, num_prompt_tokens) = self.render_prompt(session, &params)?;
         let next_token = sampler.load_prompt(&prompt_contents)?;
         log::info!("processing prompt in {:.3}s", timer.elapsed().as_secs_f32());
 
@@ -58,37 +118,65 @@ impl LlmClient {
             let _ = stream.send(ChatStream::ChatStart).await;
         }
 
-        all_tokens.push(next_token);
-        if let Some(t) = self.llm.stream.next_token(next_token)? {
-            content_buffer.push_str(&t);
-            if let Some(stream) = &stream {
-                let _ = stream.send(ChatStream::Token(t)).await;
-            }
-        }
+        // Leave the answer room in the context window
+        let num_tokens_to_sample = params
+            .max_tokens
+            .min(params.context_length.saturating_sub(num_prompt_tokens))
+            .max(1);
 
         timer = std::time::Instant::now();
         let mut sampled = 1;
-        let num_tokens_to_sample = 1024;
+        let mut stopped = false;
+        // Bytes of the answer sent to the stream. Text that could still be the
+        // start of a stop sequence is held back until later tokens rule it out.
+        let mut streamed = 0;
+        let mut next_token = Some(next_token);
+        while let Some(token) = next_token.take() {
+            if sampler.is_done() || is_cancelled() {
+                break;
+            }
 
-        for _ in 0..num_tokens_to_sample {
-            let next_token = sampler.next_token()?;
-            all_tokens.push(next_token);
-            if let Some(t) = self.llm.stream.next_token(next_token)? {
+            all_tokens.push(token);
+            if let Some(t) = self.llm.stream.next_token(token)? {
                 content_buffer.push_str(&t);
+            }
+
+            if let Some(stop) = stop_position(&content_buffer, streamed, &params.stop_sequences) {
+                content_buffer.truncate(stop);
+                stopped = true;
+                break;
+            }
+
+            let safe_end = holdback_position(&content_buffer, streamed, &params.stop_sequences);
+            if safe_end > streamed {
                 if let Some(stream) = &stream {
-                    let _ = stream.send(ChatStream::Token(t)).await;
+                    let text = content_buffer[streamed..safe_end].to_string();
+                    let _ = stream.send(ChatStream::Token(text)).await;
                 }
+                streamed = safe_end;
             }
 
-            sampled += 1;
-            if sampler.is_done() {
-                break;
-            };
+            if sampled < num_tokens_to_sample {
+                next_token = Some(sampler.next_token()?);
+                sampled += 1;
+            }
         }
 
-        if let Some(rest) = self.llm.stream.decode_rest().map_err(candle::Error::msg)? {
+        if !stopped {
+            if let Some(rest) = self.llm.stream.decode_rest().map_err(candle::Error::msg)? {
+                content_buffer.push_str(&rest);
+                if let Some(stop) = stop_position(&content_buffer, streamed, &params.stop_sequences)
+                {
+                    content_buffer.truncate(stop);
+                }
+            }
+        }
+
+        // Whatever is left can't turn into a stop sequence anymore
+        if content_buffer.len() > streamed {
             if let Some(stream) = &stream {
-                let _ = stream.send(ChatStream::Token(rest)).await;
+                let text = content_buffer[streamed..].to_string();
+                let _ = stream.send(ChatStream::Token(text)).await;
             }
         }
 
@@ -106,4 +194,116 @@ impl LlmClient {
             content: content_buffer,
         })
     }
+
+    /// Renders the prompt w/ the chat template, dropping the oldest messages until
+    /// it fits in the context window w/ room left for the answer. System messages &
+    /// the last message are always kept. Returns the prompt & its length in tokens.
+    fn render_prompt(
+        &self,
+        session: &LlmSession,
+        params: &GenerationParams,
+    ) -> Result<(String, usize)> {
+        let budget = params.context_length.saturating_sub(params.max_tokens);
+        let mut messages = session.messages.clone();
+        loop {
+            let mut context = Context::new();
+            context.insert("messages", &messages);
+            let prompt = TEMPLATES.render(self.template.template_file(), &context)?;
+            let num_tokens = self.llm.encode(&prompt)?.len();
+            if num_tokens <= budget {
+                return Ok((prompt, num_tokens));
+            }
+
+            let oldest = messages
+                .iter()
+                .position(|msg| !matches!(msg.role, ChatRole::System));
+            match oldest {
+                Some(idx) if idx + 1 < messages.len() => {
+                    messages.remove(idx);
+                }
+                _ => {
+                    log::warn!("Prompt is {num_tokens} tokens, over the {budget} token budget");
+                    return Ok((prompt, num_tokens));
+                }
+            }
+        }
+    }
+}
+
+/// Where the first stop sequence in `content` starts. Only the text from `from` on
+/// is searched, text before it was already checked & streamed.
+fn stop_position(content: &str, from: usize, stop_sequences: &[String]) -> Option<usize> {
+    let tail = &content[from..];
+    stop_sequences
+        .iter()
+        .filter_map(|stop| tail.find(stop.as_str()))
+        .min()
+        .map(|pos| from + pos)
+}
+
+/// Where the text at the end of `content` that could be the start of a stop
+/// sequence begins, or the end of `content` if there is none. Only the text from
+/// `from` on is checked.
+fn holdback_position(content: &str, from: usize, stop_sequences: &[String]) -> usize {
+    content[from..]
+        .char_indices()
+        .map(|(idx, _)| from + idx)
+        .find(|&idx| {
+            let rest = &content[idx..];
+            stop_sequences.iter().any(|stop| stop.starts_with(rest))
+        })
+        .unwrap_or(content.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{holdback_position, stop_position, GenerationParams};
+    use shared::config::LlmSettings;
+    use shared::llm::GenerationOptions;
+
+    #[test]
+    fn test_generation_params() {
+        let settings = LlmSettings {
+            stop_sequences: vec!["###".into()],
+            ..Default::default()
+        };
+
+        let params = GenerationParams::new(&settings, &GenerationOptions::default());
+        assert_eq!(params.max_tokens, 1024);
+        assert_eq!(params.stop_sequences, vec!["###".to_string()]);
+
+        let options = GenerationOptions {
+            temperature: Some(0.0),
+            top_p: Some(1.5),
+            max_tokens: Some(64),
+            stop_sequences: Some(vec!["".into(), "\n\n".into()]),
+        };
+        let params = GenerationParams::new(&settings, &options);
+        assert_eq!(params.temperature, 0.0);
+        assert_eq!(params.top_p, 1.0);
+        assert_eq!(params.max_tokens, 64);
+        assert_eq!(params.stop_sequences, vec!["\n\n".to_string()]);
+        assert_eq!(params.context_length, 8192);
+    }
+
+    #[test]
+    fn test_stop_position() {
+        let stops = vec!["User:".to_string(), "\n\n".to_string()];
+        assert_eq!(stop_position("Harare.\n\nUser: thanks", 0, &stops), Some(7));
+        assert_eq!(stop_position("Harare.", 0, &stops), None);
+        assert_eq!(stop_position("Harare.", 0, &[]), None);
+        // Only the tail is searched
+        assert_eq!(stop_position("User: Harare.\n\n", 5, &stops), Some(13));
+    }
+
+    #[test]
+    fn test_holdback_position() {
+        let stops = vec!["User:".to_string(), "\n\n".to_string()];
+        assert_eq!(holdback_position("Harare.", 0, &stops), 7);
+        assert_eq!(holdback_position("Harare.\n", 0, &stops), 7);
+        assert_eq!(holdback_position("Harare. Us", 0, &stops), 8);
+        assert_eq!(holdback_position("Harare. Use it", 0, &stops), 14);
+        assert_eq!(holdback_position("Harare. Us", 0, &[]), 10);
+        assert_eq!(holdback_position("Harare. Us", 10, &stops), 10);
+    }
 }
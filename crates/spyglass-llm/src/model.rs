@@ -1,11 +1,14 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use candle::{backend::BackendDevice, quantized::gguf_file, Device, MetalDevice};
 use candle_transformers::models::quantized_llama::ModelWeights;
 use tokenizers::Tokenizer;
 
-use crate::{sampler::Sampler, token_output_stream::TokenOutputStream};
+use crate::{sampler::Sampler, token_output_stream::TokenOutputStream, GenerationParams};
+
+/// Tokenizer used when there isn't one next to the model file
+const DEFAULT_TOKENIZER_PATH: &str = "assets/models/llm/llama3/tokenizer.json";
 
 fn format_size(size_in_bytes: usize) -> String {
     if size_in_bytes < 1_000 {
@@ -24,11 +27,12 @@ pub struct LLMModel {
     device: Device,
     pub weights: ModelWeights,
     pub stream: TokenOutputStream,
-    pub eos_token: u32,
+    /// Tokens that end generation
+    pub eos_tokens: Vec<u32>,
 }
 
 impl LLMModel {
-    pub fn new(gguf_path: PathBuf) -> Result<Self> {
+    pub fn new(gguf_path: PathBuf, eos_tokens: &[&str]) -> Result<Self> {
         let mut timer = std::time::Instant::now();
 
         let device = if cfg!(target_os = "macos") {
@@ -51,6 +55,7 @@ impl LLMModel {
             candle::Device::Cpu
         };
 
+        let tokenizer_path = tokenizer_path(&gguf_path);
         let mut file = std::fs::File::open(gguf_path)?;
         let model = gguf_file::Content::read(&mut file)?;
         dbg!(model.metadata.keys());
@@ -72,20 +77,27 @@ impl LLMModel {
         // todo: load tokenizer from gguf file itself.
         log::info!("loading tokenizer & weights");
         timer = std::time::Instant::now();
-        let tokenizer = Tokenizer::from_file("assets/models/llm/llama3/tokenizer.json")
-            .map_err(anyhow::Error::msg)?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(anyhow::Error::msg)?;
 
         let weights = ModelWeights::from_gguf(model, &mut file, &device)?;
         let tos = TokenOutputStream::new(tokenizer.clone());
         log::info!("total load took: {:.3}s", timer.elapsed().as_secs_f32());
 
-        let eos_token = "<|eot_id|>";
-        let eos_token = *tos.tokenizer().get_vocab(true).get(eos_token).unwrap();
+        let vocab = tos.tokenizer().get_vocab(true);
+        let eos_ids = eos_tokens
+            .iter()
+            .filter_map(|token| vocab.get(*token).copied())
+            .collect::<Vec<_>>();
+        if eos_ids.is_empty() {
+            return Err(anyhow::anyhow!(
+                "None of the end tokens {eos_tokens:?} are in the tokenizer, does the chat template match the model?"
+            ));
+        }
 
         Ok(Self {
             device,
             weights,
-            eos_token,
+            eos_tokens: eos_ids,
             stream: tos,
         })
     }
@@ -103,7 +115,17 @@ impl LLMModel {
         Ok(tokens.get_ids().to_vec())
     }
 
-    pub fn sampler(&self) -> Sampler {
-        Sampler::new(self)
+    pub fn sampler(&self, params: &GenerationParams) -> Sampler {
+        Sampler::new(self, params)
+    }
+}
+
+/// Uses the `tokenizer.json` next to the model file if there is one
+fn tokenizer_path(gguf_path: &Path) -> PathBuf {
+    let tokenizer_path = gguf_path.with_file_name("tokenizer.json");
+    if tokenizer_path.exists() {
+        tokenizer_path
+    } else {
+        PathBuf::from(DEFAULT_TOKENIZER_PATH)
     }
 }
@@ -3,6 +3,7 @@ use candle::Tensor;
 use candle_transformers::generation::{LogitsProcessor, Sampling};
 
 use crate::model::LLMModel;
+use crate::GenerationParams;
 
 pub struct Sampler {
     model: LLMModel,
@@ -12,10 +13,28 @@ pub struct Sampler {
 }
 
 impl Sampler {
-    pub fn new(model: &LLMModel) -> Self {
+    pub fn new(model: &LLMModel, params: &GenerationParams) -> Self {
+        let sampling = if params.temperature <= 0.0 {
+            Sampling::ArgMax
+        } else if params.top_p >= 1.0 {
+            Sampling::All {
+                temperature: params.temperature,
+            }
+        } else {
+            Sampling::TopP {
+                p: params.top_p,
+                temperature: params.temperature,
+            }
+        };
+
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+
         Self {
             model: model.clone(),
-            processor: LogitsProcessor::from_sampling(0, Sampling::ArgMax),
+            processor: LogitsProcessor::from_sampling(seed, sampling),
             num_sampled: 0,
             last_token: None,
         }
@@ -54,7 +73,7 @@ impl Sampler {
 
     pub fn is_done(&self) -> bool {
         self.last_token
-            .map(|x| x == self.model.eos_token)
+            .map(|x| self.model.eos_tokens.contains(&x))
             .unwrap_or_default()
     }
 }
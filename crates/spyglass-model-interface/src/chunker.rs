@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+/// Characters that can end a sentence
+const SENTENCE_END: &[char] = &['.', '!', '?', '。', '！', '？'];
+/// Characters allowed after the end of a sentence, e.g. `He said "hi."`
+const SENTENCE_CLOSE: &[char] = &['"', '\'', ')', ']', '”', '’'];
+
+/// How long content is split into chunks before being embedded, both in tokens
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkOptions {
+    /// Max number of tokens in a chunk
+    pub window: usize,
+    /// Max number of tokens a chunk shares w/ the chunk before it, so passages
+    /// crossing a chunk boundary can still be found.
+    pub overlap: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        ChunkOptions {
+            window: 512,
+            overlap: 64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Boundary {
+    None,
+    Sentence,
+    Paragraph,
+}
+
+/// Splits `text` into chunks of at most `opts.window` tokens. `offsets` are the
+/// byte offsets of each token in `text`, e.g. from a tokenizer `Encoding`.
+///
+/// Chunks end at a paragraph break or, failing that, the end of a sentence, as
+/// long as one is found in the second half of the window. The next chunk starts
+/// up to `opts.overlap` tokens earlier, at the start of a sentence when possible.
+/// Returns the byte range of each chunk in `text`, w/o surrounding whitespace.
+pub fn chunk(text: &str, offsets: &[(usize, usize)], opts: ChunkOptions) -> Vec<Range<usize>> {
+    // Special tokens (e.g. [CLS]) don't map to any text
+    let tokens = offsets
+        .iter()
+        .filter(|(start, end)| end > start && *end <= text.len())
+        .copied()
+        .collect::<Vec<_>>();
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    let window = opts.window.max(1);
+    // Each chunk has to move forward by at least half a window
+    let overlap = opts.overlap.min(window / 2);
+    let boundary_at = |idx: usize| boundary(text, tokens[idx].0);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let limit = (start + window).min(tokens.len());
+        let end = if limit == tokens.len() {
+            limit
+        } else {
+            // Ends before token `idx`, picking the strongest boundary & the
+            // largest chunk for that boundary.
+            (start + window / 2 + 1..=limit)
+                .map(|idx| (boundary_at(idx), idx))
+                .max_by_key(|(boundary, _)| *boundary)
+                .filter(|(boundary, _)| *boundary != Boundary::None)
+                .map_or(limit, |(_, idx)| idx)
+        };
+
+        if let Some(range) = trimmed(text, tokens[start].0..tokens[end - 1].1) {
+            chunks.push(range);
+        }
+
+        if end == tokens.len() {
+            break;
+        }
+
+        let overlap_start = end - overlap;
+        start = (overlap_start..end)
+            .find(|idx| boundary_at(*idx) != Boundary::None)
+            .unwrap_or(overlap_start);
+    }
+
+    chunks
+}
+
+// Kind of boundary right before the byte at `offset`
+fn boundary(text: &str, offset: usize) -> Boundary {
+    let (head, tail) = text.split_at(offset);
+    let before = head.trim_end();
+    let after = tail.trim_start();
+    let whitespace = &text[before.len()..text.len() - after.len()];
+    if whitespace.is_empty() {
+        return Boundary::None;
+    }
+
+    let newlines = whitespace.matches('\n').count();
+    if newlines > 1 {
+        return Boundary::Paragraph;
+    }
+
+    let ends_sentence = before
+        .trim_end_matches(SENTENCE_CLOSE)
+        .ends_with(SENTENCE_END);
+    if newlines == 1 || ends_sentence {
+        Boundary::Sentence
+    } else {
+        Boundary::None
+    }
+}
+
+fn trimmed(text: &str, range: Range<usize>) -> Option<Range<usize>> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = range.end - (slice.len() - slice.trim_end().len());
+    (start < end).then_some(start..end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chunk, ChunkOptions};
+
+    // One token per word & punctuation mark
+    fn offsets(text: &str) -> Vec<(usize, usize)> {
+        let mut offsets = Vec::new();
+        let mut word_start = None;
+        for (idx, c) in text.char_indices() {
+            if c.is_alphanumeric() {
+                word_start.get_or_insert(idx);
+                continue;
+            }
+
+            if let Some(start) = word_start.take() {
+                offsets.push((start, idx));
+            }
+            if !c.is_whitespace() {
+                offsets.push((idx, idx + c.len_utf8()));
+            }
+        }
+
+        if let Some(start) = word_start {
+            offsets.push((start, text.len()));
+        }
+        offsets
+    }
+
+    fn chunks(text: &str, window: usize, overlap: usize) -> Vec<&str> {
+        chunk(text, &offsets(text), ChunkOptions { window, overlap })
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_short_content() {
+        let text = "  A single short sentence.\n";
+        let ranges = chunk(text, &offsets(text), ChunkOptions::default());
+        assert_eq!(ranges, vec![2..26]);
+        assert!(chunk("", &[], ChunkOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_sentence_boundaries() {
+        let text = "The river runs deep. It is green and warm. Willows line the bank.";
+        // Ends at the last sentence that fits
+        assert_eq!(
+            chunks(text, 14, 0),
+            vec![
+                "The river runs deep. It is green and warm.",
+                "Willows line the bank."
+            ]
+        );
+
+        // W/o a boundary in the window, chunks are cut at the window size
+        assert_eq!(
+            chunks("one two three four five six seven", 3, 0),
+            vec!["one two three", "four five six", "seven"]
+        );
+    }
+
+    #[test]
+    fn test_paragraph_boundaries() {
+        let text = "First paragraph. Still first.\n\nSecond one. More text here.";
+        // A paragraph break wins over a later sentence break
+        assert_eq!(
+            chunks(text, 10, 0),
+            vec![
+                "First paragraph. Still first.",
+                "Second one. More text here."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlap() {
+        let text = "Alpha beta gamma. Delta epsilon. Zeta eta theta iota. Kappa.";
+        // The second chunk starts at the first sentence in the overlap
+        assert_eq!(
+            chunks(text, 10, 4),
+            vec![
+                "Alpha beta gamma. Delta epsilon.",
+                "Delta epsilon. Zeta eta theta iota. Kappa."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_offsets() {
+        let text = "Über die Brücke. Straße über Straße. Ende.";
+        let ranges = chunk(
+            text,
+            &offsets(text),
+            ChunkOptions {
+                window: 5,
+                overlap: 0,
+            },
+        );
+        assert_eq!(ranges[0], 0..18);
+        assert_eq!(&text[ranges[1].clone()], "Straße über Straße.");
+        assert_eq!(&text[ranges[2].clone()], "Ende.");
+    }
+}
@@ -1,17 +1,41 @@
-use std::{path::PathBuf, sync::Arc, time::Instant};
+use std::{ops::Range, path::PathBuf, sync::Arc, time::Instant};
 
+use async_trait::async_trait;
 use tokenizers::{Encoding, Tokenizer};
 
+use crate::chunker::{self, ChunkOptions};
 use crate::{batch, load_tokenizer, Backend, CandleBackend, Embedding, ModelType, Pool};
 
-const MAX_TOKENS: usize = 2048;
+/// Max number of segments embedded at once, unless configured otherwise
+const DEFAULT_MAX_BATCH_SIZE: usize = 16;
+/// Max number of tokens embedded at once, including padding
+const MAX_BATCH_TOKENS: usize = 16_384;
 
-#[derive(Clone)]
-pub struct EmbeddingApi {
-    backend: Arc<CandleBackend>,
-    tokenizer: Tokenizer,
+/// How a specific embedding model is used
+#[derive(Clone, Debug)]
+pub struct EmbeddingModelOptions {
+    pub pool: Pool,
+    /// Prepended to document content before it's embedded
+    pub document_prefix: String,
+    /// Prepended to search queries before they're embedded
+    pub query_prefix: String,
+    /// Max number of tokens the model can embed at once, including the prefix
+    pub max_tokens: usize,
+}
+
+impl Default for EmbeddingModelOptions {
+    /// Options for nomic-embed-text-v1
+    fn default() -> Self {
+        EmbeddingModelOptions {
+            pool: Pool::Mean,
+            document_prefix: "search_document: ".into(),
+            query_prefix: "search_query: ".into(),
+            max_tokens: 2048,
+        }
+    }
 }
 
+#[derive(Clone, Copy)]
 pub enum EmbeddingContentType {
     Document,
     Query,
@@ -20,118 +44,278 @@ pub enum EmbeddingContentType {
 #[derive(Clone, Debug)]
 pub struct SegmentEmbedding {
     pub embedding: Vec<f32>,
+    /// Byte offset of the start of the segment in the content
     pub start: usize,
+    /// Byte offset right after the end of the segment
     pub end: usize,
 }
 
+/// Generates embeddings for content, either w/ a local model or a remote service.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embeds the chunks of each of `contents`, returning the segments of each in
+    /// the same order. Segment offsets are byte offsets into the content, w/ an
+    /// exclusive end.
+    async fn embed_many(
+        &self,
+        contents: &[&str],
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<Vec<SegmentEmbedding>>>;
+
+    /// Embeds each chunk of `content`
+    async fn embed(
+        &self,
+        content: &str,
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<SegmentEmbedding>> {
+        self.embed_many(&[content], content_type)
+            .await
+            .map(|mut embedded| embedded.pop().unwrap_or_default())
+    }
+}
+
+/// A chunk of content to embed
+pub(crate) struct Segment {
+    /// Index of the content the chunk is from
+    pub content_idx: usize,
+    /// Byte range of the chunk in the content
+    pub range: Range<usize>,
+    /// Chunk w/ the model prefix prepended
+    pub text: String,
+}
+
+/// Splits content into the chunks that get embedded, using the model tokenizer to
+/// keep each chunk within the token limits.
+#[derive(Clone)]
+pub(crate) struct Segmenter {
+    pub tokenizer: Tokenizer,
+    pub model_opts: EmbeddingModelOptions,
+    pub chunk_opts: ChunkOptions,
+}
+
+impl Segmenter {
+    pub fn new(mut tokenizer: Tokenizer, model_opts: EmbeddingModelOptions) -> Self {
+        // Chunks are cut from the tokens of the whole content. If the tokenizer.json
+        // sets a max length, everything past it would never be embedded.
+        tokenizer
+            .with_truncation(None)
+            .expect("Disabling truncation can't fail");
+        Segmenter {
+            tokenizer,
+            model_opts,
+            chunk_opts: ChunkOptions::default(),
+        }
+    }
+
+    pub fn prefix(&self, content_type: EmbeddingContentType) -> &str {
+        match content_type {
+            EmbeddingContentType::Document => &self.model_opts.document_prefix,
+            EmbeddingContentType::Query => &self.model_opts.query_prefix,
+        }
+    }
+
+    pub fn segments(
+        &self,
+        contents: &[&str],
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<Segment>> {
+        let prefix = self.prefix(content_type);
+        let mut segments = Vec::new();
+        for (content_idx, content) in contents.iter().enumerate() {
+            for range in self.chunk(content, prefix)? {
+                segments.push(Segment {
+                    content_idx,
+                    text: format!("{prefix}{}", &content[range.clone()]),
+                    range,
+                });
+            }
+        }
+
+        Ok(segments)
+    }
+
+    // Byte ranges of the chunks of `content`, leaving room for the `prefix`
+    fn chunk(&self, content: &str, prefix: &str) -> anyhow::Result<Vec<Range<usize>>> {
+        let trimmed = content.trim();
+        let leading = content.len() - content.trim_start().len();
+        let tokens = self
+            .tokenizer
+            .encode(trimmed, false)
+            .map_err(|err| anyhow::format_err!("Error tokenizing {:?}", err))?;
+        let prefix_len = self
+            .tokenizer
+            .encode(prefix, false)
+            .map_err(|err| anyhow::format_err!("Error tokenizing {:?}", err))?
+            .len();
+
+        let opts = ChunkOptions {
+            window: self
+                .chunk_opts
+                .window
+                .min(self.model_opts.max_tokens.saturating_sub(prefix_len)),
+            overlap: self.chunk_opts.overlap,
+        };
+        let chunks = chunker::chunk(trimmed, tokens.get_offsets(), opts);
+        log::debug!("Split {} tokens into {} chunks", tokens.len(), chunks.len());
+
+        Ok(chunks
+            .into_iter()
+            .map(|chunk| leading + chunk.start..leading + chunk.end)
+            .collect())
+    }
+}
+
+/// Groups the embedding of each segment by the content it's from, skipping
+/// segments w/o an embedding.
+pub(crate) fn collect_segments(
+    num_contents: usize,
+    segments: Vec<Segment>,
+    embeddings: Vec<Option<Vec<f32>>>,
+) -> Vec<Vec<SegmentEmbedding>> {
+    let mut embedded: Vec<Vec<SegmentEmbedding>> = vec![Vec::new(); num_contents];
+    for (segment, embedding) in segments.into_iter().zip(embeddings) {
+        if let Some(embedding) = embedding {
+            embedded[segment.content_idx].push(SegmentEmbedding {
+                embedding,
+                start: segment.range.start,
+                end: segment.range.end,
+            });
+        }
+    }
+
+    embedded
+}
+
+/// Generates embeddings w/ a model running locally
+#[derive(Clone)]
+pub struct EmbeddingApi {
+    backend: Arc<CandleBackend>,
+    segmenter: Segmenter,
+    max_batch_size: usize,
+}
+
 impl EmbeddingApi {
-    pub fn new(model_root: PathBuf) -> anyhow::Result<Self> {
+    pub fn new(model_root: PathBuf, model_opts: EmbeddingModelOptions) -> anyhow::Result<Self> {
         let tokenizer = load_tokenizer(&model_root)?;
         let backend = CandleBackend::new(
             model_root,
             "float32".to_string(),
-            ModelType::Embedding(Pool::Mean),
+            ModelType::Embedding(model_opts.pool.clone()),
         )?;
 
         Ok(EmbeddingApi {
             backend: Arc::new(backend),
-            tokenizer,
+            segmenter: Segmenter::new(tokenizer, model_opts),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
         })
     }
 
-    pub fn embed(
+    /// Splits long content into chunks using `opts` instead of the defaults.
+    pub fn with_chunk_options(mut self, opts: ChunkOptions) -> Self {
+        self.segmenter.chunk_opts = opts;
+        self
+    }
+
+    /// Max number of segments embedded at once, capped by what the backend
+    /// supports.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Embeds each chunk of `content` on the current thread. Segment offsets are
+    /// byte offsets into `content`, w/ an exclusive end.
+    pub fn embed_blocking(
         &self,
         content: &str,
         content_type: EmbeddingContentType,
     ) -> anyhow::Result<Vec<SegmentEmbedding>> {
-        let doc_content = match content_type {
-            EmbeddingContentType::Document => {
-                format!("search_document: {}", content.trim())
-            }
-            EmbeddingContentType::Query => {
-                format!("search_query: {}", content.trim())
-            }
-        };
+        self.embed_many_blocking(&[content], content_type)
+            .map(|mut embedded| embedded.pop().unwrap_or_default())
+    }
 
-        let tokens = self
-            .tokenizer
-            .encode(doc_content, false)
-            .map_err(|err| anyhow::format_err!("Error tokenizing {:?}", err))?;
-        let token_length = tokens.len();
-        let mut content_chunks = Vec::new();
-        if token_length > MAX_TOKENS {
-            let segment_count = token_length.div_ceil(MAX_TOKENS);
-            let char_per_segment = content.len().div_ceil(segment_count);
-
-            let chunks: Vec<(String, usize, usize)> = content
-                .trim()
-                .chars()
-                .collect::<Vec<char>>()
-                .chunks(char_per_segment)
-                .enumerate()
-                .map(|(i, chunk)| {
-                    let start_index = i * char_per_segment;
-                    let end_index = start_index + chunk.len() - 1;
-
-                    (chunk.iter().collect::<String>(), start_index, end_index)
-                })
-                .collect();
-
-            log::debug!(
-                "Splitting text into chunks of {} chars long",
-                char_per_segment
-            );
-            for (chunk, start_index, end_index) in chunks {
-                let doc_content = match content_type {
-                    EmbeddingContentType::Document => {
-                        format!("search_document: {}", chunk)
-                    }
-                    EmbeddingContentType::Query => {
-                        format!("search_query: {}", chunk)
-                    }
-                };
+    /// Embeds the chunks of each of `contents`, returning the segments of each in
+    /// the same order. Chunks from all of them are embedded together in batches,
+    /// on the current thread.
+    pub fn embed_many_blocking(
+        &self,
+        contents: &[&str],
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<Vec<SegmentEmbedding>>> {
+        let segments = self.segmenter.segments(contents, content_type)?;
+        let encodings = segments
+            .iter()
+            .map(|segment| {
                 let tokens = self
+                    .segmenter
                     .tokenizer
-                    .encode(doc_content, false)
+                    .encode(segment.text.as_str(), false)
                     .map_err(|err| anyhow::format_err!("Error tokenizing {:?}", err))?;
                 log::trace!("Chunk was {} tokens long", tokens.len());
-                content_chunks.push((tokens, start_index, end_index));
-            }
-        } else {
-            content_chunks.push((tokens, 0, content.len() - 1));
-        }
+                Ok(tokens)
+            })
+            .collect::<anyhow::Result<Vec<Encoding>>>()?;
 
-        let mut embeddings = Vec::new();
-        for (chunk, start, end) in content_chunks {
-            let embedding = self.embed_tokens(chunk.to_owned())?;
-            embeddings.push(SegmentEmbedding {
-                embedding,
-                start,
-                end,
-            });
+        let max_batch_size = self
+            .backend
+            .max_batch_size()
+            .map_or(self.max_batch_size, |max| max.min(self.max_batch_size));
+        let lengths = encodings
+            .iter()
+            .map(|tokens| tokens.len())
+            .collect::<Vec<_>>();
+        let batches = plan_batches(
+            &lengths,
+            max_batch_size,
+            MAX_BATCH_TOKENS,
+            self.backend.is_padded(),
+        );
+
+        let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; segments.len()];
+        for batch in batches {
+            let batch_encodings = batch
+                .iter()
+                .map(|idx| encodings[*idx].clone())
+                .collect::<Vec<_>>();
+            for (idx, embedding) in batch.iter().zip(self.embed_encodings(batch_encodings)?) {
+                embeddings[*idx] = Some(embedding);
+            }
         }
 
-        Ok(embeddings)
+        Ok(collect_segments(contents.len(), segments, embeddings))
     }
 
     pub fn embed_tokens(&self, tokens: Encoding) -> anyhow::Result<Vec<f32>> {
-        let token_length = tokens.len();
-        let input_batch = batch(vec![tokens], [0].to_vec(), vec![]);
+        self.embed_encodings(vec![tokens])?
+            .pop()
+            .ok_or_else(|| anyhow::format_err!("Unable to process embedding"))
+    }
+
+    /// Embeds a batch of sequences in a single pass through the model
+    pub fn embed_encodings(&self, encodings: Vec<Encoding>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let count = encodings.len();
+        let token_length = encodings
+            .iter()
+            .map(|encoding| encoding.len())
+            .sum::<usize>();
+        let input_batch = batch(encodings, (0..count as u32).collect(), vec![]);
         let start = Instant::now();
 
         match self.backend.embed(input_batch) {
-            Ok(embed) => {
+            Ok(mut embed) => {
                 log::debug!(
-                    "Embedding {} tokens took {}",
+                    "Embedding {} tokens in {} sequences took {}",
                     token_length,
+                    count,
                     start.elapsed().as_millis()
                 );
 
-                if let Some(Embedding::Pooled(embedding)) = embed.get(&0) {
-                    Ok(embedding.to_owned())
-                } else {
-                    Err(anyhow::format_err!("Unable to process embedding"))
-                }
+                (0..count)
+                    .map(|idx| match embed.remove(&idx) {
+                        Some(Embedding::Pooled(embedding)) => Ok(embedding),
+                        _ => Err(anyhow::format_err!("Unable to process embedding")),
+                    })
+                    .collect()
             }
             Err(error) => {
                 log::error!(
@@ -146,3 +330,134 @@ impl EmbeddingApi {
         }
     }
 }
+
+#[async_trait]
+impl EmbeddingProvider for EmbeddingApi {
+    async fn embed_many(
+        &self,
+        contents: &[&str],
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<Vec<SegmentEmbedding>>> {
+        // Inference is CPU bound, keep it off the async workers
+        let api = self.clone();
+        let contents = contents
+            .iter()
+            .map(|content| content.to_string())
+            .collect::<Vec<_>>();
+        tokio::task::spawn_blocking(move || {
+            let contents = contents.iter().map(String::as_str).collect::<Vec<_>>();
+            api.embed_many_blocking(&contents, content_type)
+        })
+        .await?
+    }
+}
+
+/// Groups sequences w/ the given token `lengths` into batches of at most
+/// `max_batch_size` sequences & `max_batch_tokens` tokens, returning the indices
+/// of the sequences in each batch. Sequences of similar length are batched
+/// together, so padded batches waste as little as possible on padding.
+fn plan_batches(
+    lengths: &[usize],
+    max_batch_size: usize,
+    max_batch_tokens: usize,
+    padded: bool,
+) -> Vec<Vec<usize>> {
+    let mut order = (0..lengths.len()).collect::<Vec<_>>();
+    order.sort_by_key(|idx| lengths[*idx]);
+
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    let mut tokens = 0;
+    for idx in order {
+        let len = lengths[idx];
+        // Sorted by length, so this is the longest sequence in the batch
+        let batch_tokens = if padded {
+            len * (current.len() + 1)
+        } else {
+            tokens + len
+        };
+
+        if !current.is_empty()
+            && (current.len() >= max_batch_size || batch_tokens > max_batch_tokens)
+        {
+            batches.push(std::mem::take(&mut current));
+            tokens = 0;
+        }
+
+        current.push(idx);
+        tokens += len;
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    batches
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use tokenizers::models::wordlevel::WordLevel;
+    use tokenizers::pre_tokenizers::whitespace::Whitespace;
+    use tokenizers::{Tokenizer, TruncationParams};
+
+    use super::{plan_batches, EmbeddingModelOptions, Segmenter};
+    use crate::chunker::ChunkOptions;
+
+    #[test]
+    fn test_plan_batches() {
+        let lengths = [10, 500, 20, 510, 30];
+        assert_eq!(
+            plan_batches(&lengths, 2, 10_000, true),
+            vec![vec![0, 2], vec![4, 1], vec![3]]
+        );
+
+        // Padded batches count every sequence as long as the longest one
+        assert_eq!(
+            plan_batches(&lengths, 8, 1_000, true),
+            vec![vec![0, 2, 4], vec![1], vec![3]]
+        );
+        assert_eq!(
+            plan_batches(&lengths, 8, 1_000, false),
+            vec![vec![0, 2, 4, 1], vec![3]]
+        );
+
+        // Sequences longer than the token budget still get a batch
+        assert_eq!(plan_batches(&[2_000], 8, 1_000, false), vec![vec![0]]);
+        assert!(plan_batches(&[], 8, 1_000, false).is_empty());
+    }
+
+    #[test]
+    fn test_chunk_ignores_truncation() {
+        let vocab = HashMap::from([("[UNK]".to_string(), 0)]);
+        let model = WordLevel::builder()
+            .vocab(vocab)
+            .unk_token("[UNK]".into())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(model);
+        tokenizer.with_pre_tokenizer(Some(Whitespace::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: 8,
+                ..Default::default()
+            }))
+            .unwrap();
+
+        let mut segmenter = Segmenter::new(tokenizer, EmbeddingModelOptions::default());
+        segmenter.chunk_opts = ChunkOptions {
+            window: 4,
+            overlap: 0,
+        };
+
+        // Every word is chunked, not just the first 8 tokens
+        let content = (0..20)
+            .map(|idx| format!("word{idx}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let chunks = segmenter.chunk(&content, "").unwrap();
+        assert_eq!(chunks.last().map(|chunk| chunk.end), Some(content.len()));
+    }
+}
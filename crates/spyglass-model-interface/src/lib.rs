@@ -1,5 +1,6 @@
 // Everything in this lib is pulled from https://github.com/huggingface/text-embeddings-inference
 mod alibi;
+pub mod chunker;
 #[cfg(feature = "cuda")]
 mod compute_cap;
 pub mod embedding_api;
@@ -7,6 +8,9 @@ pub mod embedding_api;
 // mod flash_attn;
 mod layers;
 mod models;
+pub mod remote_embedding_api;
+pub mod rerank_api;
+pub mod sparse_api;
 
 #[cfg(feature = "cuda")]
 use crate::compute_cap::{
@@ -0,0 +1,292 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use crate::chunker::ChunkOptions;
+use crate::embedding_api::{
+    collect_segments, EmbeddingContentType, EmbeddingModelOptions, EmbeddingProvider,
+    SegmentEmbedding, Segmenter,
+};
+
+/// Max number of segments sent in a single request, unless configured otherwise
+const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// Generates embeddings w/ a service implementing the OpenAI `/v1/embeddings` API,
+/// e.g. an embedding server running on a GPU machine on the local network.
+/// Content is still split into chunks locally, using the tokenizer of the model
+/// the service runs.
+#[derive(Clone)]
+pub struct RemoteEmbeddingApi {
+    client: Client,
+    base_url: String,
+    model: String,
+    segmenter: Segmenter,
+    batch_size: usize,
+    /// Size the embeddings are expected to be, checked when set
+    dimensions: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: Vec<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    /// Position of the input this embedding is for
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl RemoteEmbeddingApi {
+    /// `base_url` is the root of the API, e.g. `http://192.168.1.10:8080/v1`, &
+    /// `model` is the name the service knows the model by.
+    pub fn new(
+        base_url: &str,
+        model: &str,
+        tokenizer: Tokenizer,
+        model_opts: EmbeddingModelOptions,
+    ) -> Self {
+        RemoteEmbeddingApi {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            segmenter: Segmenter::new(tokenizer, model_opts),
+            batch_size: DEFAULT_BATCH_SIZE,
+            dimensions: None,
+        }
+    }
+
+    /// Splits long content into chunks using `opts` instead of the defaults.
+    pub fn with_chunk_options(mut self, opts: ChunkOptions) -> Self {
+        self.segmenter.chunk_opts = opts;
+        self
+    }
+
+    /// Max number of segments sent in a single request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Rejects embeddings that aren't `dimensions` long, e.g. when the service
+    /// runs a different model than the one the stored embeddings are from.
+    pub fn with_dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    async fn embed_batch(&self, input: Vec<&str>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", self.base_url);
+        let count = input.len();
+        let request = EmbeddingRequest {
+            model: &self.model,
+            input,
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| anyhow::format_err!("Error requesting {url}. {err}"))?;
+
+        let mut data = response
+            .json::<EmbeddingResponse>()
+            .await
+            .map_err(|err| anyhow::format_err!("Invalid embedding response. {err}"))?
+            .data;
+        if data.len() != count {
+            return Err(anyhow::format_err!(
+                "Expected {} embeddings, received {}",
+                count,
+                data.len()
+            ));
+        }
+
+        if let Some(dimensions) = self.dimensions {
+            if let Some(data) = data.iter().find(|data| data.embedding.len() != dimensions) {
+                return Err(anyhow::format_err!(
+                    "Model {} returned a {} dimension embedding, expected {}. Check the remote model matches the selected embedding model.",
+                    self.model,
+                    data.embedding.len(),
+                    dimensions
+                ));
+            }
+        }
+
+        data.sort_by_key(|data| data.index);
+        Ok(data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for RemoteEmbeddingApi {
+    async fn embed_many(
+        &self,
+        contents: &[&str],
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<Vec<SegmentEmbedding>>> {
+        let segments = self.segmenter.segments(contents, content_type)?;
+
+        let mut embeddings = Vec::with_capacity(segments.len());
+        for batch in segments.chunks(self.batch_size) {
+            let input = batch
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect::<Vec<_>>();
+            embeddings.extend(self.embed_batch(input).await?.into_iter().map(Some));
+        }
+
+        Ok(collect_segments(contents.len(), segments, embeddings))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+    use tokenizers::Tokenizer;
+    use warp::Filter;
+
+    use super::RemoteEmbeddingApi;
+    use crate::embedding_api::{EmbeddingContentType, EmbeddingModelOptions, EmbeddingProvider};
+
+    // Splits on whitespace & punctuation, every word is an unknown token
+    const TOKENIZER: &str = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": { "type": "Whitespace" },
+        "post_processor": null,
+        "decoder": null,
+        "model": { "type": "WordLevel", "vocab": { "[UNK]": 0 }, "unk_token": "[UNK]" }
+    }"#;
+
+    type Captured = Arc<Mutex<Vec<Value>>>;
+
+    // Spins up an OpenAI compatible embedding endpoint that records every request
+    // & embeds each input as its length in chars, returned in reverse order.
+    fn mock_server() -> (SocketAddr, Captured) {
+        let captured: Captured = Arc::new(Mutex::new(Vec::new()));
+
+        let log = captured.clone();
+        let embeddings = warp::path!("v1" / "embeddings")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                log.lock().unwrap().push(body.clone());
+                let data = body["input"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, input)| {
+                        let len = input.as_str().unwrap().chars().count();
+                        json!({
+                            "object": "embedding",
+                            "index": index,
+                            "embedding": [len as f32, 1.0]
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                warp::reply::json(&json!({ "object": "list", "data": data }))
+            });
+
+        let (addr, server) = warp::serve(embeddings).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        (addr, captured)
+    }
+
+    fn api(base_url: &str) -> RemoteEmbeddingApi {
+        let tokenizer = Tokenizer::from_str(TOKENIZER).expect("Invalid tokenizer");
+        RemoteEmbeddingApi::new(
+            base_url,
+            "nomic-embed-text-v1",
+            tokenizer,
+            EmbeddingModelOptions::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_remote_embeddings() {
+        let (addr, captured) = mock_server();
+        let api = api(&format!("http://{addr}/v1/")).with_batch_size(1);
+
+        let contents = ["  The river runs deep.", "Willows line the bank."];
+        let embedded = api
+            .embed_many(&contents, EmbeddingContentType::Document)
+            .await
+            .expect("Unable to embed");
+        assert_eq!(embedded.len(), 2);
+        assert_eq!((embedded[0][0].start, embedded[0][0].end), (2, 22));
+        assert_eq!(embedded[0][0].embedding, vec![37.0, 1.0]);
+        assert_eq!((embedded[1][0].start, embedded[1][0].end), (0, 22));
+
+        // One request per batch, w/ the model prefix
+        let requests = captured.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["model"], "nomic-embed-text-v1");
+        assert_eq!(
+            requests[1]["input"],
+            json!(["search_document: Willows line the bank."])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_embedding_order() {
+        let (addr, _) = mock_server();
+        let api = api(&format!("http://{addr}/v1"));
+
+        // Responses are matched to inputs by index, not position
+        let embedded = api
+            .embed_many(&["a", "abc"], EmbeddingContentType::Query)
+            .await
+            .expect("Unable to embed");
+        assert_eq!(embedded[0][0].embedding, vec![15.0, 1.0]);
+        assert_eq!(embedded[1][0].embedding, vec![17.0, 1.0]);
+
+        // Unknown endpoint
+        let broken = self::api(&format!("http://{addr}/v2"));
+        assert!(broken
+            .embed("a", EmbeddingContentType::Query)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remote_embedding_dimensions() {
+        let (addr, _) = mock_server();
+        let api = api(&format!("http://{addr}/v1"));
+
+        assert!(api
+            .clone()
+            .with_dimensions(2)
+            .embed("a", EmbeddingContentType::Query)
+            .await
+            .is_ok());
+
+        let err = api
+            .with_dimensions(768)
+            .embed("a", EmbeddingContentType::Query)
+            .await
+            .expect_err("Wrong sized embedding accepted");
+        assert!(err.to_string().contains("expected 768"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::{path::PathBuf, sync::Arc, time::Instant};
+
+use tokenizers::{Encoding, Tokenizer, TruncationParams, TruncationStrategy};
+
+use crate::{batch, load_tokenizer, Backend, CandleBackend, ModelType};
+
+/// Max number of (query, passage) pairs scored at once, unless configured otherwise
+const DEFAULT_MAX_BATCH_SIZE: usize = 8;
+/// Max number of tokens in a (query, passage) pair, the passage is truncated to fit
+const MAX_PAIR_TOKENS: usize = 512;
+
+/// Scores how relevant passages are to a query w/ a cross-encoder, a classifier
+/// that reads the query & passage together. Much more accurate than comparing
+/// embeddings, but too slow to run on more than the top few results.
+#[derive(Clone)]
+pub struct RerankApi {
+    backend: Arc<CandleBackend>,
+    tokenizer: Tokenizer,
+    max_batch_size: usize,
+}
+
+impl RerankApi {
+    pub fn new(model_root: PathBuf) -> anyhow::Result<Self> {
+        let mut tokenizer = load_tokenizer(&model_root)?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_PAIR_TOKENS,
+                strategy: TruncationStrategy::OnlySecond,
+                ..Default::default()
+            }))
+            .map_err(|error| anyhow::format_err!("Error configuring tokenizer {:?}", error))?;
+        let backend = CandleBackend::new(model_root, "float32".to_string(), ModelType::Classifier)?;
+
+        Ok(RerankApi {
+            backend: Arc::new(backend),
+            tokenizer,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        })
+    }
+
+    /// Max number of pairs scored at once, capped by what the backend supports.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Relevance of each passage to `query`, higher is more relevant. Passages are
+    /// scored in order, a batch at a time. No batch is started once `deadline` has
+    /// passed, in which case only the first passages are scored.
+    pub fn score(
+        &self,
+        query: &str,
+        passages: &[&str],
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<Vec<f32>> {
+        let max_batch_size = self
+            .backend
+            .max_batch_size()
+            .map_or(self.max_batch_size, |max| max.min(self.max_batch_size));
+
+        let mut scores = Vec::with_capacity(passages.len());
+        for passages in passages.chunks(max_batch_size) {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+
+            let encodings = passages
+                .iter()
+                .map(|passage| {
+                    self.tokenizer
+                        .encode((query, *passage), true)
+                        .map_err(|err| anyhow::format_err!("Error tokenizing {:?}", err))
+                })
+                .collect::<anyhow::Result<Vec<Encoding>>>()?;
+            scores.extend(self.predict(encodings)?);
+        }
+
+        Ok(scores)
+    }
+
+    fn predict(&self, encodings: Vec<Encoding>) -> anyhow::Result<Vec<f32>> {
+        let count = encodings.len();
+        let start = Instant::now();
+        let input_batch = batch(encodings, (0..count as u32).collect(), vec![]);
+        let mut predictions = self
+            .backend
+            .predict(input_batch)
+            .map_err(|error| anyhow::format_err!("Reranking failed {:?}", error))?;
+        log::debug!(
+            "Reranking {} passages took {}",
+            count,
+            start.elapsed().as_millis()
+        );
+
+        (0..count)
+            .map(|idx| {
+                predictions
+                    .remove(&idx)
+                    .and_then(|logits| relevance(&logits))
+                    .ok_or_else(|| anyhow::format_err!("Unable to process reranking score"))
+            })
+            .collect()
+    }
+}
+
+// Cross-encoders either output a single relevance logit or one logit per label,
+// w/ the last label meaning relevant.
+fn relevance(logits: &[f32]) -> Option<f32> {
+    match logits {
+        [score] => Some(*score),
+        [.., irrelevant, relevant] => Some(relevant - irrelevant),
+        [] => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::relevance;
+
+    #[test]
+    fn test_relevance() {
+        assert_eq!(relevance(&[2.5]), Some(2.5));
+        // Log odds of the relevant label
+        assert_eq!(relevance(&[-1.0, 3.0]), Some(4.0));
+        assert_eq!(relevance(&[]), None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::{path::PathBuf, time::Instant};
+
+use tokenizers::Encoding;
+
+use crate::embedding_api::{EmbeddingApi, EmbeddingContentType, EmbeddingModelOptions, Segmenter};
+use crate::{load_tokenizer, Pool};
+
+/// Max number of segments encoded at once
+const MAX_BATCH_SIZE: usize = 4;
+/// Max number of tokens SPLADE models can read at once
+const MAX_TOKENS: usize = 512;
+/// Only the first segments of long documents are encoded, by then the expansion
+/// terms of a document are mostly settled.
+const MAX_DOCUMENT_SEGMENTS: usize = 8;
+/// Number of expansion terms kept for a document
+const MAX_DOCUMENT_TERMS: usize = 256;
+/// Number of expansion terms kept for a query
+const MAX_QUERY_TERMS: usize = 32;
+
+/// Vocabulary terms & their weight, highest weight first
+pub type SparseTerms = Vec<(String, f32)>;
+
+/// Expands content into weighted vocabulary terms w/ a SPLADE model, a masked
+/// language model predicting which terms are relevant to the content, including
+/// ones it doesn't contain. Documents & queries expanded this way can be matched
+/// through a regular inverted index.
+#[derive(Clone)]
+pub struct SparseApi {
+    embedder: EmbeddingApi,
+    segmenter: Segmenter,
+}
+
+impl SparseApi {
+    pub fn new(model_root: PathBuf) -> anyhow::Result<Self> {
+        let model_opts = EmbeddingModelOptions {
+            pool: Pool::Splade,
+            document_prefix: String::new(),
+            query_prefix: String::new(),
+            max_tokens: MAX_TOKENS,
+        };
+        let tokenizer = load_tokenizer(&model_root)?;
+        let embedder = EmbeddingApi::new(model_root, model_opts.clone())?;
+
+        Ok(SparseApi {
+            embedder,
+            segmenter: Segmenter::new(tokenizer, model_opts),
+        })
+    }
+
+    /// Expansion terms of `content`
+    pub fn encode(
+        &self,
+        content: &str,
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<SparseTerms> {
+        self.encode_many(&[content], content_type)
+            .map(|mut encoded| encoded.pop().unwrap_or_default())
+    }
+
+    /// Expansion terms of each of `contents`, in the same order. The weight of a
+    /// term is its highest weight in any segment of the content.
+    pub fn encode_many(
+        &self,
+        contents: &[&str],
+        content_type: EmbeddingContentType,
+    ) -> anyhow::Result<Vec<SparseTerms>> {
+        let (max_segments, max_terms) = match content_type {
+            EmbeddingContentType::Document => (MAX_DOCUMENT_SEGMENTS, MAX_DOCUMENT_TERMS),
+            EmbeddingContentType::Query => (1, MAX_QUERY_TERMS),
+        };
+
+        let mut segments = self.segmenter.segments(contents, content_type)?;
+        let mut num_segments = vec![0; contents.len()];
+        segments.retain(|segment| {
+            num_segments[segment.content_idx] += 1;
+            num_segments[segment.content_idx] <= max_segments
+        });
+
+        let start = Instant::now();
+        let mut pooled: Vec<Vec<f32>> = vec![Vec::new(); contents.len()];
+        for batch in segments.chunks(MAX_BATCH_SIZE) {
+            let encodings = batch
+                .iter()
+                .map(|segment| {
+                    self.segmenter
+                        .tokenizer
+                        .encode(segment.text.as_str(), true)
+                        .map_err(|err| anyhow::format_err!("Error tokenizing {:?}", err))
+                })
+                .collect::<anyhow::Result<Vec<Encoding>>>()?;
+
+            for (segment, weights) in batch.iter().zip(self.embedder.embed_encodings(encodings)?) {
+                merge_max(&mut pooled[segment.content_idx], &weights);
+            }
+        }
+        log::debug!(
+            "Encoding {} segments took {}",
+            segments.len(),
+            start.elapsed().as_millis()
+        );
+
+        Ok(pooled
+            .iter()
+            .map(|weights| self.to_terms(weights, max_terms))
+            .collect())
+    }
+
+    // Maps the top weighted vocabulary ids back to their tokens, skipping special
+    // tokens such as `[CLS]` or `[unused0]`.
+    fn to_terms(&self, weights: &[f32], limit: usize) -> SparseTerms {
+        top_weights(weights)
+            .into_iter()
+            .filter_map(|(id, weight)| {
+                let token = self.segmenter.tokenizer.id_to_token(id)?;
+                (!is_special(&token)).then_some((token, weight))
+            })
+            .take(limit)
+            .collect()
+    }
+}
+
+fn is_special(token: &str) -> bool {
+    let bracketed = (token.starts_with('[') && token.ends_with(']'))
+        || (token.starts_with('<') && token.ends_with('>'));
+    (bracketed && token.len() > 2) || token.chars().any(char::is_whitespace)
+}
+
+// Keeps the highest weight of each term across segments
+fn merge_max(pooled: &mut Vec<f32>, weights: &[f32]) {
+    if pooled.is_empty() {
+        pooled.extend_from_slice(weights);
+    } else {
+        for (pooled, weight) in pooled.iter_mut().zip(weights) {
+            *pooled = pooled.max(*weight);
+        }
+    }
+}
+
+// Ids of the terms w/ a positive weight, highest weight first
+fn top_weights(weights: &[f32]) -> Vec<(u32, f32)> {
+    let mut top = weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0.0)
+        .map(|(id, weight)| (id as u32, *weight))
+        .collect::<Vec<_>>();
+    top.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then_with(|| a_id.cmp(b_id)));
+    top
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_special, merge_max, top_weights};
+
+    #[test]
+    fn test_top_weights() {
+        let mut pooled = Vec::new();
+        merge_max(&mut pooled, &[0.0, 1.5, 0.2, 0.0]);
+        merge_max(&mut pooled, &[0.7, 0.5, 0.2, 0.0]);
+        assert_eq!(pooled, vec![0.7, 1.5, 0.2, 0.0]);
+
+        // Terms that were never predicted are dropped
+        assert_eq!(top_weights(&pooled), vec![(1, 1.5), (0, 0.7), (2, 0.2)]);
+    }
+
+    #[test]
+    fn test_is_special() {
+        assert!(is_special("[CLS]"));
+        assert!(is_special("[unused12]"));
+        assert!(is_special("<s>"));
+        assert!(!is_special("["));
+        assert!(!is_special("##ing"));
+        assert!(!is_special("river"));
+    }
+}
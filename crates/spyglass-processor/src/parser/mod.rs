@@ -1,5 +1,6 @@
 use anyhow::anyhow;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use std::{ffi::OsStr, path::Path};
 
 use crate::utils;
@@ -57,14 +58,7 @@ pub fn parse_content(mime_type_str: &str, content: &Bytes) -> anyhow::Result<Par
                         ..Default::default()
                     })
                 }
-                utils::mime::PDF => {
-                    let pdf_content = pdf_parser::parse_bytes(content.clone())?;
-                    Ok(ParsedDocument {
-                        content: pdf_content.content,
-                        title: pdf_content.metadata.title,
-                        author: pdf_content.metadata.author,
-                    })
-                }
+                utils::mime::PDF => Ok(pdf_parser::parse_bytes(content.clone())?.into()),
                 _ => Err(anyhow!(format!(
                     "Document Mimetype {mime_type_str:?} not supported"
                 ))),
@@ -85,6 +79,10 @@ pub struct ParsedDocument {
     pub title: Option<String>,
     pub author: Option<String>,
     pub content: String,
+    /// When the document was created, if available in the document metadata
+    pub created: Option<DateTime<Utc>>,
+    /// When the document was last modified, if available in the document metadata
+    pub modified: Option<DateTime<Utc>>,
 }
 
 impl From<pdf_parser::Pdf> for ParsedDocument {
@@ -93,6 +91,8 @@ impl From<pdf_parser::Pdf> for ParsedDocument {
             title: value.metadata.title,
             author: value.metadata.author,
             content: value.content,
+            created: value.metadata.created,
+            modified: value.metadata.modified,
         }
     }
 }
@@ -1,4 +1,5 @@
 use bytes::Bytes;
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
 use pdf::file::FileOptions;
 use std::{
     env,
@@ -149,6 +150,8 @@ pub struct Pdf {
 pub struct PdfMetadata {
     pub title: Option<String>,
     pub author: Option<String>,
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
 }
 
 impl PdfMetadata {
@@ -164,10 +167,74 @@ impl PdfMetadata {
         Self {
             title: pdf_info.get("Title").and_then(|v| v.to_string().ok()),
             author: pdf_info.get("Author").and_then(|v| v.to_string().ok()),
+            created: pdf_info
+                .get("CreationDate")
+                .and_then(|v| v.to_string().ok())
+                .and_then(|v| parse_pdf_date(&v)),
+            modified: pdf_info
+                .get("ModDate")
+                .and_then(|v| v.to_string().ok())
+                .and_then(|v| parse_pdf_date(&v)),
         }
     }
 }
 
+/// Parses a PDF date string, e.g. `D:20230131154512+01'00'`. Everything after the
+/// year is optional.
+pub fn parse_pdf_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let value = value.strip_prefix("D:").unwrap_or(value);
+    let digits = value
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    if digits.len() < 4 {
+        return None;
+    }
+
+    let part = |start: usize, len: usize, default: u32| -> u32 {
+        digits
+            .get(start..start + len)
+            .and_then(|x| x.parse::<u32>().ok())
+            .unwrap_or(default)
+    };
+
+    let year = digits[0..4].parse::<i32>().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, part(4, 2, 1), part(6, 2, 1))?.and_hms_opt(
+        part(8, 2, 0),
+        part(10, 2, 0),
+        part(12, 2, 0),
+    )?;
+
+    // Timezone is either "Z", "+HH'mm'" or "-HH'mm'", defaults to UTC if missing.
+    let tz = &value[digits.len()..];
+    let offset_secs = match tz.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let tz_digits = tz
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .collect::<String>();
+            let hours = tz_digits.get(0..2).and_then(|x| x.parse::<i32>().ok())?;
+            let minutes = tz_digits
+                .get(2..4)
+                .and_then(|x| x.parse::<i32>().ok())
+                .unwrap_or(0);
+            let secs = hours * 3600 + minutes * 60;
+            if sign == '-' {
+                -secs
+            } else {
+                secs
+            }
+        }
+        _ => 0,
+    };
+
+    FixedOffset::east_opt(offset_secs)?
+        .from_local_datetime(&date)
+        .single()
+        .map(|date| date.with_timezone(&Utc))
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
@@ -180,6 +247,21 @@ mod tests {
         assert_eq!(metadata.author, Some("PDF author".to_string()));
     }
 
+    #[test]
+    fn test_parse_pdf_date() {
+        use chrono::{TimeZone, Utc};
+
+        assert_eq!(
+            super::parse_pdf_date("D:20230131154512+01'00'"),
+            Utc.with_ymd_and_hms(2023, 1, 31, 14, 45, 12).single()
+        );
+        assert_eq!(
+            super::parse_pdf_date("D:20230131"),
+            Utc.with_ymd_and_hms(2023, 1, 31, 0, 0, 0).single()
+        );
+        assert_eq!(super::parse_pdf_date("yesterday"), None);
+    }
+
     #[test]
     fn test_pdf_metadata_extraction_from_pdf_with_missing_metadata() {
         let path_with_metadata = Path::new("../../fixtures/pdf/pdf_without_metadata.pdf");
@@ -3,11 +3,14 @@ use jsonrpsee::proc_macros::rpc;
 use jsonrpsee::types::{ErrorObject, ErrorObjectOwned};
 use serde::Serialize;
 use shared::config::UserSettings;
-use shared::llm::{ChatMessage, LlmSession};
-use shared::request::{BatchDocumentRequest, RawDocumentRequest, SearchLensesParam, SearchParam};
+use shared::llm::{ChatResponse, LlmSession};
+use shared::request::{
+    AutocompleteParam, BatchDocumentRequest, ExplainParam, RawDocumentRequest, RelatedParam,
+    SearchLensesParam, SearchParam,
+};
 use shared::response::{
-    AppStatus, DefaultIndices, LensResult, LibraryStats, ListConnectionResult, PluginResult,
-    SearchLensesResp, SearchResults,
+    AppStatus, AutocompleteResults, DefaultIndices, LensResult, LibraryStats, ListConnectionResult,
+    PluginResult, RelatedResults, ScoreExplanation, SearchLensesResp, SearchResults,
 };
 use std::collections::HashMap;
 
@@ -54,11 +57,28 @@ pub trait Rpc {
     #[method(name = "authorize_connection")]
     async fn authorize_connection(&self, id: String) -> RpcResult<()>;
 
+    /// Query completions for a partially typed query, drawn from recent
+    /// queries, document titles & terms in the index.
+    #[method(name = "autocomplete")]
+    async fn autocomplete(&self, query: AutocompleteParam) -> RpcResult<AutocompleteResults>;
+
+    /// Documents related to a document, based on shared terms & the similarity of
+    /// their embeddings.
+    #[method(name = "related_documents")]
+    async fn related_documents(&self, param: RelatedParam) -> RpcResult<RelatedResults>;
+
+    /// Breaks down how a document is scored for a query, e.g. to tune lenses.
+    #[method(name = "explain_result")]
+    async fn explain_result(&self, param: ExplainParam) -> RpcResult<ScoreExplanation>;
+
     #[method(name = "app_status")]
     async fn app_status(&self) -> RpcResult<AppStatus>;
 
     #[method(name = "chat_completion")]
-    async fn chat_completion(&self, session: LlmSession) -> RpcResult<ChatMessage>;
+    async fn chat_completion(&self, session: LlmSession) -> RpcResult<ChatResponse>;
+
+    #[method(name = "cancel_chat")]
+    async fn cancel_chat(&self, chat_id: String) -> RpcResult<()>;
 
     #[method(name = "default_indices")]
     async fn default_indices(&self) -> RpcResult<DefaultIndices>;
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::Term;
+
+use crate::document_to_struct;
+use crate::spelling;
+
+/// Max number of title terms matching the prefix used to look up titles
+const MAX_TITLE_PREFIX_TERMS: usize = 50;
+
+/// Completions for a partially typed query
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Completions {
+    /// The query w/ its last word completed using terms from the index, most
+    /// common terms first.
+    pub terms: Vec<String>,
+    /// Titles of documents matching the query so far
+    pub titles: Vec<String>,
+}
+
+/// Splits a query into the words already typed & the (possibly partial) word
+/// being typed, if any. Words are normalized w/ `analyzer` so they line up w/ the
+/// terms in the index.
+pub fn split_prefix(analyzer: &TextAnalyzer, query: &str) -> (Vec<String>, Option<String>) {
+    let mut words = Vec::new();
+    analyzer
+        .token_stream(query)
+        .process(&mut |token| words.push(token.text.clone()));
+
+    // A trailing space means the last word is done.
+    let is_typing = query.chars().last().map_or(false, char::is_alphanumeric);
+    let prefix = if is_typing { words.pop() } else { None };
+    (words, prefix)
+}
+
+/// Walks the term dictionary of each field for terms starting w/ `prefix`,
+/// returning up to `limit` terms along w/ the number of docs containing them.
+pub fn complete_terms(
+    searcher: &tantivy::Searcher,
+    fields: &[Field],
+    prefix: &str,
+    limit: usize,
+) -> Vec<(String, u64)> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+
+    let mut doc_freqs: HashMap<String, u64> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        for field in fields {
+            let inverted_index = match segment_reader.inverted_index(*field) {
+                Ok(index) => index,
+                Err(_) => continue,
+            };
+
+            let mut terms = match inverted_index.terms().range().ge(prefix).into_stream() {
+                Ok(terms) => terms,
+                Err(_) => continue,
+            };
+
+            // Terms are sorted, so we're done once they stop matching the prefix
+            while terms.advance() && terms.key().starts_with(prefix.as_bytes()) {
+                if let Ok(term) = std::str::from_utf8(terms.key()) {
+                    *doc_freqs.entry(term.to_string()).or_default() +=
+                        terms.value().doc_freq as u64;
+                }
+            }
+        }
+    }
+
+    let mut completions = doc_freqs.into_iter().collect::<Vec<_>>();
+    completions.sort_by(|(a_term, a_freq), (b_term, b_freq)| {
+        b_freq.cmp(a_freq).then_with(|| a_term.cmp(b_term))
+    });
+    completions.truncate(limit);
+    completions
+}
+
+/// The partial word `prefix` completed w/ words from the `title` & `content` term
+/// dictionaries, most common first. Content terms are stemmed, so each is replaced
+/// w/ the word it was most often stemmed from, found w/ `content_analyzer`.
+pub fn complete_words(
+    searcher: &tantivy::Searcher,
+    title: Field,
+    content: Field,
+    content_analyzer: &TextAnalyzer,
+    prefix: &str,
+    limit: usize,
+) -> Vec<String> {
+    let mut doc_freqs: HashMap<String, u64> = complete_terms(searcher, &[title], prefix, limit + 1)
+        .into_iter()
+        .collect();
+
+    for (stem, doc_freq) in complete_terms(searcher, &[content], prefix, limit + 1) {
+        let word = spelling::unstem(searcher, content, content_analyzer, &stem).unwrap_or(stem);
+        // Words stemmed to something shorter than what was typed don't complete it
+        if word.starts_with(prefix) {
+            *doc_freqs.entry(word).or_default() += doc_freq;
+        }
+    }
+
+    let mut completions = doc_freqs
+        .into_iter()
+        .filter(|(word, _)| word != prefix)
+        .collect::<Vec<_>>();
+    completions.sort_by(|(a_word, a_freq), (b_word, b_freq)| {
+        b_freq.cmp(a_freq).then_with(|| a_word.cmp(b_word))
+    });
+    completions
+        .into_iter()
+        .take(limit)
+        .map(|(word, _)| word)
+        .collect()
+}
+
+/// Finds up to `limit` document titles containing all the typed `words` & a word
+/// starting w/ `prefix`.
+pub fn complete_titles(
+    searcher: &tantivy::Searcher,
+    title: Field,
+    words: &[String],
+    prefix: Option<&str>,
+    limit: usize,
+) -> Vec<String> {
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = words
+        .iter()
+        .map(|word| -> (Occur, Box<dyn Query>) {
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(title, word),
+                    IndexRecordOption::WithFreqs,
+                )),
+            )
+        })
+        .collect();
+
+    if let Some(prefix) = prefix {
+        let prefix_terms = complete_terms(searcher, &[title], prefix, MAX_TITLE_PREFIX_TERMS)
+            .into_iter()
+            .map(|(term, _)| -> (Occur, Box<dyn Query>) {
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(title, &term),
+                        IndexRecordOption::WithFreqs,
+                    )),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Nothing in the index starts w/ the prefix
+        if prefix_terms.is_empty() {
+            return Vec::new();
+        }
+
+        clauses.push((Occur::Must, Box::new(BooleanQuery::new(prefix_terms))));
+    }
+
+    if clauses.is_empty() {
+        return Vec::new();
+    }
+
+    // Grab a few extra to account for docs w/ the same title
+    let top_docs = searcher
+        .search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit * 2))
+        .unwrap_or_default();
+
+    let mut seen = HashSet::new();
+    top_docs
+        .into_iter()
+        .filter_map(|(_, addr)| searcher.doc(addr).ok())
+        .filter_map(|doc| document_to_struct(&doc))
+        .map(|doc| doc.title)
+        .filter(|title| !title.is_empty() && seen.insert(title.to_lowercase()))
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_prefix;
+    use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+    #[test]
+    fn test_split_prefix() {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer).filter(LowerCaser);
+
+        assert_eq!(
+            split_prefix(&analyzer, "Of Mice an"),
+            (
+                vec!["of".to_string(), "mice".to_string()],
+                Some("an".into())
+            )
+        );
+        assert_eq!(
+            split_prefix(&analyzer, "of mice "),
+            (vec!["of".to_string(), "mice".to_string()], None)
+        );
+        assert_eq!(split_prefix(&analyzer, ""), (Vec::new(), None));
+    }
+}
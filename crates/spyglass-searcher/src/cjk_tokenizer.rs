@@ -0,0 +1,131 @@
+use tantivy::tokenizer::{BoxTokenStream, Token, TokenStream, Tokenizer};
+
+/// Tokenizer for text w/o whitespace between words (Japanese, Chinese, Korean).
+/// Runs of CJK characters are split into overlapping bigrams while everything else
+/// is split on non-alphanumeric characters, the same as the `SimpleTokenizer`.
+#[derive(Clone, Default)]
+pub struct CjkTokenizer;
+
+pub struct CjkTokenStream {
+    tokens: Vec<Token>,
+    // Index of the next token to return.
+    cursor: usize,
+}
+
+impl Tokenizer for CjkTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CjkTokenStream {
+            tokens: tokenize(text),
+            cursor: 0,
+        })
+    }
+}
+
+impl TokenStream for CjkTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.cursor < self.tokens.len() {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.cursor - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.cursor - 1]
+    }
+}
+
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        // Hiragana & Katakana
+        '\u{3040}'..='\u{30FF}'
+        // CJK unified ideographs & extension A
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        // CJK compatibility ideographs
+        | '\u{F900}'..='\u{FAFF}'
+        // Halfwidth Katakana
+        | '\u{FF66}'..='\u{FF9F}'
+        // Hangul syllables
+        | '\u{AC00}'..='\u{D7AF}'
+    )
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    // Byte offset right after the character at `idx`
+    let end_of = |idx: usize| chars[idx].0 + chars[idx].1.len_utf8();
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut push = |from: usize, to: usize| {
+        tokens.push(Token {
+            offset_from: from,
+            offset_to: to,
+            position: tokens.len(),
+            text: text[from..to].to_string(),
+            position_length: 1,
+        });
+    };
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (start, c) = chars[idx];
+        if is_cjk(c) {
+            let mut end = idx;
+            while end < chars.len() && is_cjk(chars[end].1) {
+                end += 1;
+            }
+
+            if end - idx == 1 {
+                push(start, end_of(idx));
+            } else {
+                for pos in idx..end - 1 {
+                    push(chars[pos].0, end_of(pos + 1));
+                }
+            }
+            idx = end;
+        } else if c.is_alphanumeric() {
+            let mut end = idx;
+            while end < chars.len() && chars[end].1.is_alphanumeric() && !is_cjk(chars[end].1) {
+                end += 1;
+            }
+
+            push(start, end_of(end - 1));
+            idx = end;
+        } else {
+            idx += 1;
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::CjkTokenizer;
+    use tantivy::tokenizer::Tokenizer;
+
+    fn tokens(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        CjkTokenizer
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.text.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_bigrams() {
+        assert_eq!(tokens("東京都"), vec!["東京", "京都"]);
+        assert_eq!(tokens("猫"), vec!["猫"]);
+        assert_eq!(
+            tokens("Spyglassで検索する"),
+            vec!["Spyglass", "で検", "検索", "索す", "する"]
+        );
+        assert_eq!(tokens("hello, world"), vec!["hello", "world"]);
+    }
+}
@@ -0,0 +1,205 @@
+use tantivy::schema::*;
+
+use crate::autocomplete::Completions;
+use crate::client::{HttpSearcher, Searcher};
+use crate::explain::ScoreExplanation;
+use crate::{
+    Boost, IndexBackend, QueryBoost, RetrievedDocument, Score, SearchOptions, SearchQueryResult,
+    SearchTrait, SearcherResult, WriteTrait,
+};
+
+/// Index the app reads from & writes to, either a local tantivy index or an
+/// Elasticsearch compatible index server shared by several daemons. Features that
+/// need direct access to the index terms (autocomplete, explanations, related
+/// documents & sparse retrieval) are only available w/ a local index.
+#[derive(Clone, Debug)]
+pub enum IndexClient {
+    Local(Searcher),
+    Http(HttpSearcher),
+}
+
+impl From<Searcher> for IndexClient {
+    fn from(searcher: Searcher) -> Self {
+        IndexClient::Local(searcher)
+    }
+}
+
+impl From<HttpSearcher> for IndexClient {
+    fn from(searcher: HttpSearcher) -> Self {
+        IndexClient::Http(searcher)
+    }
+}
+
+#[async_trait::async_trait]
+impl WriteTrait for IndexClient {
+    async fn delete_many_by_id(&self, doc_ids: &[String]) -> SearcherResult<usize> {
+        match self {
+            IndexClient::Local(searcher) => searcher.delete_many_by_id(doc_ids).await,
+            IndexClient::Http(searcher) => searcher.delete_many_by_id(doc_ids).await,
+        }
+    }
+
+    async fn upsert_many(&self, updates: &[Document]) -> SearcherResult<Vec<String>> {
+        match self {
+            IndexClient::Local(searcher) => searcher.upsert_many(updates).await,
+            IndexClient::Http(searcher) => searcher.upsert_many(updates).await,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchTrait for IndexClient {
+    async fn get(&self, doc_id: &str) -> Option<RetrievedDocument> {
+        match self {
+            IndexClient::Local(searcher) => searcher.get(doc_id).await,
+            IndexClient::Http(searcher) => searcher.get(doc_id).await,
+        }
+    }
+
+    async fn search_with_options(
+        &self,
+        query: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        num_results: usize,
+        offset: usize,
+        opts: SearchOptions,
+    ) -> SearchQueryResult {
+        match self {
+            IndexClient::Local(searcher) => {
+                searcher
+                    .search_with_options(query, filters, boosts, num_results, offset, opts)
+                    .await
+            }
+            IndexClient::Http(searcher) => {
+                searcher
+                    .search_with_options(query, filters, boosts, num_results, offset, opts)
+                    .await
+            }
+        }
+    }
+}
+
+impl IndexClient {
+    /// Opens the index @ `backend`. Remote indices are accessed w/ an `HttpSearcher`,
+    /// which leaves the schema up to the index server.
+    pub fn with_index(
+        backend: &IndexBackend,
+        schema: Schema,
+        readonly: bool,
+    ) -> SearcherResult<Self> {
+        match backend {
+            IndexBackend::Http(_) => HttpSearcher::with_index(backend).map(IndexClient::Http),
+            _ => Searcher::with_index(backend, schema, readonly).map(IndexClient::Local),
+        }
+    }
+
+    /// The local index, `None` when using an index server
+    pub fn local(&self) -> Option<&Searcher> {
+        match self {
+            IndexClient::Local(searcher) => Some(searcher),
+            IndexClient::Http(_) => None,
+        }
+    }
+
+    /// Number of documents in the index
+    pub async fn num_docs(&self) -> u64 {
+        match self {
+            IndexClient::Local(searcher) => searcher.reader.searcher().num_docs(),
+            IndexClient::Http(searcher) => searcher.num_docs().await.unwrap_or_else(|err| {
+                log::warn!("Unable to count remote documents: {err}");
+                0
+            }),
+        }
+    }
+
+    /// Commits any pending changes. Changes to a remote index are committed as
+    /// they're sent.
+    pub async fn save(&self) -> SearcherResult<()> {
+        match self {
+            IndexClient::Local(searcher) => searcher.save().await,
+            IndexClient::Http(_) => Ok(()),
+        }
+    }
+
+    /// Documents matching any of the `urls` & `ids` w/ all of `has_tags` & none of
+    /// `exclude_tags`.
+    pub async fn search_by_query(
+        &self,
+        urls: Option<Vec<Boost>>,
+        ids: Option<Vec<String>>,
+        has_tags: &[u64],
+        exclude_tags: &[u64],
+    ) -> Vec<(Score, RetrievedDocument)> {
+        match self {
+            IndexClient::Local(searcher) => {
+                searcher
+                    .search_by_query(urls, ids, has_tags, exclude_tags)
+                    .await
+            }
+            IndexClient::Http(searcher) => {
+                searcher
+                    .search_by_query(urls, ids, has_tags, exclude_tags)
+                    .await
+            }
+        }
+    }
+
+    /// Completions for a partially typed query, none w/ an index server.
+    pub fn autocomplete(&self, query: &str, limit: usize) -> Completions {
+        match self {
+            IndexClient::Local(searcher) => searcher.autocomplete(query, limit),
+            IndexClient::Http(_) => Completions::default(),
+        }
+    }
+
+    /// Documents sharing the most distinctive terms w/ `doc_id`, none w/ an index
+    /// server.
+    pub async fn more_like_this(
+        &self,
+        doc_id: &str,
+        filters: &[QueryBoost],
+        num_results: usize,
+    ) -> Vec<(Score, RetrievedDocument)> {
+        match self {
+            IndexClient::Local(searcher) => {
+                searcher.more_like_this(doc_id, filters, num_results).await
+            }
+            IndexClient::Http(_) => Vec::new(),
+        }
+    }
+
+    /// Documents whose SPLADE expansion best matches the expanded query, none w/ an
+    /// index server.
+    pub async fn search_sparse(
+        &self,
+        terms: &[(String, f32)],
+        filters: &[QueryBoost],
+        num_results: usize,
+    ) -> Vec<(Score, String)> {
+        match self {
+            IndexClient::Local(searcher) => {
+                searcher.search_sparse(terms, filters, num_results).await
+            }
+            IndexClient::Http(_) => Vec::new(),
+        }
+    }
+
+    /// Breaks down how `doc_id` is scored for the query, `None` if the document
+    /// isn't in the index or when using an index server.
+    pub fn explain(
+        &self,
+        doc_id: &str,
+        query_string: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+    ) -> Option<ScoreExplanation> {
+        match self {
+            IndexClient::Local(searcher) => searcher.explain(doc_id, query_string, filters, boosts),
+            IndexClient::Http(_) => {
+                log::warn!("Score explanations are only available w/ a local index");
+                None
+            }
+        }
+    }
+}
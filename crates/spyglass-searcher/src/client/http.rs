@@ -0,0 +1,682 @@
+use std::fmt::{Debug, Error, Formatter};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tantivy::schema::*;
+use url::Url;
+
+use crate::facets::FacetCounts;
+use crate::query::{build_remote_document_query, build_remote_query, QueryOptions};
+use crate::schema::{DocFields, SearchDocument};
+use crate::{
+    field_to_date, field_to_string, field_to_u64vec, Boost, IndexBackend, QueryBoost,
+    RetrievedDocument, Score, SearchError, SearchOptions, SearchQueryResult, SearchTrait,
+    SearcherResult, SortOrder, WriteTrait,
+};
+
+/// Client for an Elasticsearch compatible REST API (such as Quickwit). The
+/// endpoint is expected to point at the index itself, e.g.
+/// `http://localhost:9200/spyglass`.
+#[derive(Clone)]
+pub struct HttpSearcher {
+    client: Client,
+    endpoint: Url,
+}
+
+impl Debug for HttpSearcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_struct("HttpSearcher")
+            .field("endpoint", &self.endpoint.as_str())
+            .finish()
+    }
+}
+
+/// Document as it is stored in the remote index.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct RemoteDocument {
+    id: String,
+    #[serde(default)]
+    domain: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tags: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lastmodified: Option<DateTime<Utc>>,
+}
+
+impl RemoteDocument {
+    fn from_document(doc: &Document) -> Self {
+        let fields = DocFields::as_fields();
+        RemoteDocument {
+            id: field_to_string(doc, fields.id),
+            domain: field_to_string(doc, fields.domain),
+            title: field_to_string(doc, fields.title),
+            description: field_to_string(doc, fields.description),
+            url: field_to_string(doc, fields.url),
+            content: field_to_string(doc, fields.content),
+            tags: field_to_u64vec(doc, fields.tags),
+            published: field_to_date(doc, fields.published),
+            lastmodified: field_to_date(doc, fields.lastmodified),
+        }
+    }
+}
+
+impl From<RemoteDocument> for RetrievedDocument {
+    fn from(doc: RemoteDocument) -> Self {
+        RetrievedDocument {
+            doc_id: doc.id,
+            domain: doc.domain,
+            title: doc.title,
+            description: doc.description,
+            content: doc.content,
+            url: doc.url,
+            tags: doc.tags,
+            published: doc.published,
+            last_modified: doc.lastmodified,
+            // Remote indexes don't store expansion terms
+            sparse_terms: Vec::new(),
+        }
+    }
+}
+
+/// Max number of buckets requested per facet aggregation
+const FACET_SIZE: usize = 100;
+/// Max number of documents returned by `search_by_query`, the default max result
+/// window of Elasticsearch.
+const MAX_DOCUMENT_QUERY_SIZE: usize = 10_000;
+
+#[derive(Default)]
+struct RemoteSearchResult {
+    hits: Vec<(f32, RetrievedDocument)>,
+    facets: FacetCounts,
+    total_hits: usize,
+    suggestion: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    hits: SearchHits,
+    #[serde(default)]
+    aggregations: Option<FacetAggregations>,
+    #[serde(default)]
+    suggest: Option<SuggestResponse>,
+}
+
+impl SearchResponse {
+    /// Applies the first option for each misspelled word in `text`
+    fn suggestion(&self, text: &str) -> Option<String> {
+        let entries = self.suggest.as_ref()?.spelling.as_slice();
+        let mut chars = text.chars().collect::<Vec<_>>();
+        let mut corrected = false;
+        // Back to front so the offsets stay valid
+        for entry in entries.iter().rev() {
+            if let Some(option) = entry.options.first() {
+                let end = (entry.offset + entry.length).min(chars.len());
+                if entry.offset <= end {
+                    chars.splice(entry.offset..end, option.text.chars());
+                    corrected = true;
+                }
+            }
+        }
+
+        if corrected {
+            Some(chars.into_iter().collect())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SuggestResponse {
+    #[serde(default)]
+    spelling: Vec<SuggestEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SuggestEntry {
+    offset: usize,
+    length: usize,
+    #[serde(default)]
+    options: Vec<SuggestOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SuggestOption {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FacetAggregations {
+    tags: Option<AggregationBuckets>,
+    domains: Option<AggregationBuckets>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AggregationBuckets {
+    #[serde(default)]
+    buckets: Vec<AggregationBucket>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AggregationBucket {
+    key: serde_json::Value,
+    doc_count: u64,
+}
+
+impl From<FacetAggregations> for FacetCounts {
+    fn from(aggs: FacetAggregations) -> Self {
+        let mut counts = FacetCounts::default();
+        for bucket in aggs.tags.map(|x| x.buckets).unwrap_or_default() {
+            if let Some(tag) = bucket.key.as_u64() {
+                counts.tags.insert(tag, bucket.doc_count);
+            }
+        }
+
+        for bucket in aggs.domains.map(|x| x.buckets).unwrap_or_default() {
+            if let Some(domain) = bucket.key.as_str() {
+                counts.domains.insert(domain.to_string(), bucket.doc_count);
+            }
+        }
+
+        counts
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHits {
+    #[serde(default)]
+    total: Option<TotalHits>,
+    #[serde(default)]
+    hits: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TotalHits {
+    value: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    #[serde(rename = "_score", default)]
+    score: Option<f32>,
+    #[serde(rename = "_source")]
+    source: RemoteDocument,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountResponse {
+    count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BulkResponse {
+    #[serde(default)]
+    errors: bool,
+}
+
+#[async_trait::async_trait]
+impl WriteTrait for HttpSearcher {
+    async fn delete_many_by_id(&self, doc_ids: &[String]) -> SearcherResult<usize> {
+        if doc_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut body = String::new();
+        for doc_id in doc_ids {
+            body.push_str(&json!({ "delete": { "_id": doc_id } }).to_string());
+            body.push('\n');
+        }
+
+        self.bulk(body).await?;
+        Ok(doc_ids.len())
+    }
+
+    async fn upsert_many(&self, updates: &[Document]) -> SearcherResult<Vec<String>> {
+        if updates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut upserted = Vec::new();
+        let mut body = String::new();
+        for doc_update in updates {
+            let doc = RemoteDocument::from_document(doc_update);
+            body.push_str(&json!({ "index": { "_id": doc.id } }).to_string());
+            body.push('\n');
+            body.push_str(&serde_json::to_string(&doc).map_err(anyhow::Error::from)?);
+            body.push('\n');
+            upserted.push(doc.id);
+        }
+
+        self.bulk(body).await?;
+        Ok(upserted)
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchTrait for HttpSearcher {
+    /// Get a single document by id
+    async fn get(&self, doc_id: &str) -> Option<RetrievedDocument> {
+        let request = json!({
+            "size": 1,
+            "query": { "term": { "id": doc_id } }
+        });
+
+        match self.search_raw(&request).await {
+            Ok(result) => result.hits.into_iter().next().map(|(_, doc)| doc),
+            Err(err) => {
+                log::warn!("Unable to get doc {doc_id}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Runs a search against the remote index w/ the provided `SearchOptions`
+    async fn search_with_options(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        num_results: usize,
+        offset: usize,
+        opts: SearchOptions,
+    ) -> SearchQueryResult {
+        let start_timer = Instant::now();
+
+        let query_opts = QueryOptions::default().with_terms_optional(opts.terms_optional);
+        let (term_counts, query) = build_remote_query(query_string, filters, boosts, query_opts);
+        let check_typos = opts.min_exact_hits > 0 && term_counts > 0;
+        let request = search_request(query, query_string, num_results, offset, &opts, check_typos);
+        let mut result = match self.search_raw(&request).await {
+            Ok(result) => result,
+            Err(err) => {
+                log::warn!("Unable to execute query `{query_string}`: {err}");
+                RemoteSearchResult::default()
+            }
+        };
+
+        // Too few exact matches, see if the query has any typos in it.
+        let mut is_fuzzy = false;
+        if check_typos && result.total_hits < opts.min_exact_hits {
+            let (_, fuzzy_query) =
+                build_remote_query(query_string, filters, boosts, QueryOptions::fuzzy());
+            let request =
+                search_request(fuzzy_query, query_string, num_results, offset, &opts, false);
+            match self.search_raw(&request).await {
+                Ok(fuzzy_result) if fuzzy_result.total_hits > result.total_hits => {
+                    result.hits = fuzzy_result.hits;
+                    result.facets = fuzzy_result.facets;
+                    result.total_hits = fuzzy_result.total_hits;
+                    is_fuzzy = true;
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("Unable to execute fuzzy query `{query_string}`: {err}"),
+            }
+        }
+
+        let documents = result
+            .hits
+            .into_iter()
+            // Filter out negative scores
+            .filter(|(score, _)| *score > 0.0)
+            .collect::<Vec<_>>();
+
+        // Saves a `_count` round trip on every search, the response already says
+        // how many docs matched.
+        let num_docs = result.total_hits as u64;
+        log::debug!(
+            "query `{}` returned {} results from {} docs in {} ms",
+            query_string,
+            documents.len(),
+            num_docs,
+            Instant::now().duration_since(start_timer).as_millis()
+        );
+
+        SearchQueryResult {
+            wall_time_ms: Instant::now().duration_since(start_timer).as_millis(),
+            num_docs,
+            term_counts,
+            total_hits: result.total_hits,
+            documents,
+            facets: result.facets,
+            is_fuzzy,
+            suggestion: result.suggestion,
+        }
+    }
+}
+
+// Builds the body of a `_search` request
+fn search_request(
+    query: serde_json::Value,
+    query_string: &str,
+    num_results: usize,
+    offset: usize,
+    opts: &SearchOptions,
+    with_suggestions: bool,
+) -> serde_json::Value {
+    let mut request = json!({
+        "from": offset,
+        "size": num_results,
+        "query": query,
+        "track_total_hits": true,
+        "aggs": {
+            "tags": { "terms": { "field": "tags", "size": FACET_SIZE } },
+            "domains": { "terms": { "field": "domain", "size": FACET_SIZE } },
+        },
+    });
+
+    let sort_order = match opts.order {
+        SortOrder::Relevance => None,
+        SortOrder::NewestFirst => Some("desc"),
+        SortOrder::OldestFirst => Some("asc"),
+    };
+    if let Some(sort_order) = sort_order {
+        request["sort"] = json!([
+            { "lastmodified": { "order": sort_order, "missing": "_last" } }
+        ]);
+    }
+
+    if with_suggestions {
+        request["suggest"] = json!({
+            "text": query_string,
+            "spelling": { "term": { "field": "content", "suggest_mode": "missing" } }
+        });
+    }
+
+    request
+}
+
+impl HttpSearcher {
+    pub fn new(endpoint: Url) -> Self {
+        HttpSearcher {
+            client: Client::new(),
+            endpoint,
+        }
+    }
+
+    /// Constructs a new HttpSearcher from an index backend, only `IndexBackend::Http`
+    /// is supported.
+    pub fn with_index(backend: &IndexBackend) -> SearcherResult<Self> {
+        match backend {
+            IndexBackend::Http(endpoint) => Ok(Self::new(endpoint.clone())),
+            other => Err(SearchError::Other(anyhow::anyhow!(
+                "{other:?} is not a remote index"
+            ))),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.endpoint.as_str().trim_end_matches('/'), path)
+    }
+
+    /// Total number of documents in the remote index.
+    pub async fn num_docs(&self) -> SearcherResult<u64> {
+        let resp = self
+            .client
+            .get(self.url("_count"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CountResponse>()
+            .await?;
+
+        Ok(resp.count)
+    }
+
+    /// Documents matching any of the `urls` & `ids` w/ all of `has_tags` & none of
+    /// `exclude_tags`, see `Searcher::search_by_query`.
+    pub async fn search_by_query(
+        &self,
+        urls: Option<Vec<Boost>>,
+        ids: Option<Vec<String>>,
+        has_tags: &[u64],
+        exclude_tags: &[u64],
+    ) -> Vec<(Score, RetrievedDocument)> {
+        let query = build_remote_document_query(
+            &urls.unwrap_or_default(),
+            &ids.unwrap_or_default(),
+            has_tags,
+            exclude_tags,
+        );
+        let request = json!({ "size": MAX_DOCUMENT_QUERY_SIZE, "query": query });
+
+        match self.search_raw(&request).await {
+            Ok(result) => result.hits,
+            Err(err) => {
+                log::warn!("Unable to execute document query: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn search_raw(&self, request: &serde_json::Value) -> SearcherResult<RemoteSearchResult> {
+        let resp = self
+            .client
+            .post(self.url("_search"))
+            .json(request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SearchResponse>()
+            .await?;
+
+        let suggestion = request["suggest"]["text"]
+            .as_str()
+            .and_then(|text| resp.suggestion(text));
+        let facets = resp.aggregations.map(FacetCounts::from).unwrap_or_default();
+        let total_hits = resp.hits.total.map(|total| total.value).unwrap_or_default();
+        let hits = resp
+            .hits
+            .hits
+            .into_iter()
+            // Hits aren't scored when sorting by a field
+            .map(|hit| (hit.score.unwrap_or(1.0), hit.source.into()))
+            .collect();
+
+        Ok(RemoteSearchResult {
+            hits,
+            facets,
+            total_hits,
+            suggestion,
+        })
+    }
+
+    async fn bulk(&self, body: String) -> SearcherResult<()> {
+        let resp = self
+            .client
+            .post(self.url("_bulk?refresh=true"))
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<BulkResponse>()
+            .await?;
+
+        if resp.errors {
+            return Err(SearchError::Other(anyhow::anyhow!(
+                "Index server rejected one or more bulk actions"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+    use url::Url;
+    use warp::Filter;
+
+    use crate::client::HttpSearcher;
+    use crate::schema::{DocumentUpdate, ToDocument};
+    use crate::{Boost, QueryBoost, SearchTrait, WriteTrait};
+
+    type Captured = Arc<Mutex<Vec<String>>>;
+
+    // Spins up a tiny Elasticsearch look-alike that records every request body
+    // it receives and replies with canned responses.
+    fn mock_server() -> (SocketAddr, Captured) {
+        let captured: Captured = Arc::new(Mutex::new(Vec::new()));
+
+        let search_log = captured.clone();
+        let search = warp::path!("spyglass" / "_search")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                search_log.lock().unwrap().push(body.to_string());
+                warp::reply::json(&json!({
+                    "hits": {
+                        "total": { "value": 1 },
+                        "hits": [{
+                            "_id": "doc_1",
+                            "_score": 2.5,
+                            "_source": {
+                                "id": "doc_1",
+                                "domain": "example.com",
+                                "title": "Of Mice and Men",
+                                "url": "https://example.com/mice_and_men",
+                                "content": "A few miles south of Soledad",
+                                "tags": [1]
+                            }
+                        }]
+                    },
+                    "aggregations": {
+                        "tags": { "buckets": [{ "key": 1, "doc_count": 1 }] },
+                        "domains": { "buckets": [{ "key": "example.com", "doc_count": 1 }] }
+                    }
+                }))
+            });
+
+        let count = warp::path!("spyglass" / "_count")
+            .and(warp::get())
+            .map(|| warp::reply::json(&json!({ "count": 4 })));
+
+        let bulk_log = captured.clone();
+        let bulk = warp::path!("spyglass" / "_bulk")
+            .and(warp::post())
+            .and(warp::body::bytes())
+            .map(move |body: warp::hyper::body::Bytes| {
+                bulk_log
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&body).to_string());
+                warp::reply::json(&json!({ "errors": false, "items": [] }))
+            });
+
+        let (addr, server) =
+            warp::serve(search.or(count).or(bulk)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        (addr, captured)
+    }
+
+    fn searcher(addr: SocketAddr) -> HttpSearcher {
+        let endpoint = Url::parse(&format!("http://{addr}/spyglass")).expect("Invalid url");
+        HttpSearcher::new(endpoint)
+    }
+
+    #[tokio::test]
+    async fn test_remote_search() {
+        let (addr, captured) = mock_server();
+        let searcher = searcher(addr);
+
+        let filters = vec![QueryBoost::new(Boost::Tag(2_u64))];
+        let results = searcher.search("salinas river", &filters, &[], 5, 0).await;
+        assert_eq!(results.num_docs, 1);
+        assert_eq!(results.total_hits, 1);
+        assert_eq!(results.term_counts, 2);
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.doc_id, "doc_1");
+        assert_eq!(results.documents[0].1.tags, vec![1]);
+        assert_eq!(results.facets.tags.get(&1), Some(&1));
+        assert_eq!(results.facets.domains.get("example.com"), Some(&1));
+
+        let requests = captured.lock().unwrap();
+        let request: Value = serde_json::from_str(&requests[0]).expect("Invalid request");
+        assert_eq!(request["size"], 5);
+        assert_eq!(
+            request["query"]["bool"]["filter"][0],
+            json!({ "term": { "tags": { "value": 2 } } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_upsert_and_delete() {
+        let (addr, captured) = mock_server();
+        let searcher = searcher(addr);
+
+        let doc = DocumentUpdate {
+            doc_id: Some("doc_1".into()),
+            title: "Of Mice and Men",
+            domain: "example.com",
+            url: "https://example.com/mice_and_men",
+            content: "A few miles south of Soledad",
+            tags: &[1_i64],
+            published_at: None,
+            last_modified: None,
+        }
+        .to_document();
+
+        let upserted = searcher.upsert(&doc).await.expect("Unable to upsert");
+        assert_eq!(upserted, "doc_1");
+
+        let deleted = searcher
+            .delete_many_by_id(&["doc_1".to_string()])
+            .await
+            .expect("Unable to delete");
+        assert_eq!(deleted, 1);
+
+        let requests = captured.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let lines = requests[0].lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], r#"{"index":{"_id":"doc_1"}}"#);
+        assert!(lines[1].contains(r#""title":"Of Mice and Men""#));
+        assert_eq!(requests[1].trim(), r#"{"delete":{"_id":"doc_1"}}"#);
+    }
+
+    #[tokio::test]
+    async fn test_remote_search_by_query() {
+        let (addr, captured) = mock_server();
+        let searcher = searcher(addr);
+
+        let doc = searcher.get("doc_1").await.expect("Doc not found");
+        assert_eq!(doc.title, "Of Mice and Men");
+
+        let urls = vec![Boost::UrlPrefix("https://example.com/".into())];
+        let docs = searcher.search_by_query(Some(urls), None, &[1], &[2]).await;
+        assert_eq!(docs.len(), 1);
+
+        let requests = captured.lock().unwrap();
+        let request: Value = serde_json::from_str(&requests[1]).expect("Invalid request");
+        let query = &request["query"]["bool"];
+        assert_eq!(
+            query["filter"][0]["bool"]["should"][0],
+            json!({ "prefix": { "url": { "value": "https://example.com/" } } })
+        );
+        assert_eq!(
+            query["filter"][1],
+            json!({ "term": { "tags": { "value": 1 } } })
+        );
+        assert_eq!(
+            query["must_not"][0],
+            json!({ "term": { "tags": { "value": 2 } } })
+        );
+    }
+}
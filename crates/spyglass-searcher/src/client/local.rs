@@ -2,18 +2,25 @@ use std::fmt::{Debug, Error, Formatter};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Instant;
 
-use tantivy::collector::TopDocs;
+use tantivy::collector::{Count, TopDocs};
 use tantivy::directory::error::LockError;
-use tantivy::query::TermQuery;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
 use tantivy::{schema::*, TantivyError};
-use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy};
+use tantivy::{DocAddress, DocId, Index, IndexReader, IndexWriter, ReloadPolicy, SegmentReader};
 use uuid::Uuid;
 
-use crate::query::{build_document_query, build_query, terms_for_field, QueryOptions};
+use crate::autocomplete::{self, Completions};
+use crate::explain::{ScoreComponent, ScoreExplanation};
+use crate::facets::{FacetCollector, FacetCounts};
+use crate::language::DocLanguage;
+use crate::query::{build_document_query, build_query, build_scored_query, QueryOptions};
+use crate::related;
 use crate::schema::{self, DocFields, SearchDocument};
+use crate::sparse;
 use crate::{
-    document_to_struct, field_to_string, Boost, IndexBackend, QueryBoost, RetrievedDocument, Score,
-    SearchError, SearchQueryResult, SearchTrait, SearcherResult, WriteTrait,
+    document_to_struct, field_to_string, spelling, Boost, IndexBackend, QueryBoost,
+    RetrievedDocument, Score, SearchError, SearchOptions, SearchQueryResult, SearchTrait,
+    SearcherResult, SortOrder, WriteTrait,
 };
 
 pub const SPYGLASS_NS: Uuid = uuid::uuid!("5fdfe40a-de2c-11ed-bfa7-00155deae876");
@@ -94,14 +101,15 @@ impl SearchTrait for Searcher {
         None
     }
 
-    /// Runs a search against the index
-    async fn search(
+    /// Runs a search against the index w/ the provided `SearchOptions`
+    async fn search_with_options(
         &self,
         query_string: &str,
         filters: &[QueryBoost],
         boosts: &[QueryBoost],
         num_results: usize,
         offset: usize,
+        opts: SearchOptions,
     ) -> SearchQueryResult {
         let start_timer = Instant::now();
 
@@ -114,14 +122,37 @@ impl SearchTrait for Searcher {
             query_string,
             filters,
             boosts,
-            QueryOptions::default(),
+            QueryOptions::default().with_terms_optional(opts.terms_optional),
         );
 
-        let collector = TopDocs::with_limit(num_results).and_offset(offset);
+        let (mut top_docs, mut facets, mut num_hits) =
+            self.collect(&searcher, &query, num_results, offset, opts.order);
 
-        let top_docs = searcher
-            .search(&query, &collector)
-            .expect("Unable to execute query");
+        // Too few exact matches, see if the query has any typos in it.
+        let mut is_fuzzy = false;
+        let mut suggestion = None;
+        if num_hits < opts.min_exact_hits && term_counts > 0 {
+            let fields = DocFields::as_fields();
+            if let Ok(analyzer) = index.tokenizer_for_field(fields.content) {
+                suggestion = spelling::suggest_correction(
+                    &searcher,
+                    fields.content,
+                    &analyzer,
+                    query_string,
+                );
+            }
+
+            let (_, fuzzy_query) =
+                build_query(index, query_string, filters, boosts, QueryOptions::fuzzy());
+            let (fuzzy_docs, fuzzy_facets, fuzzy_hits) =
+                self.collect(&searcher, &fuzzy_query, num_results, offset, opts.order);
+            if fuzzy_hits > num_hits {
+                top_docs = fuzzy_docs;
+                facets = fuzzy_facets;
+                is_fuzzy = true;
+                num_hits = fuzzy_hits;
+            }
+        }
 
         log::debug!(
             "query `{}` returned {} results from {} docs in {} ms",
@@ -134,8 +165,6 @@ impl SearchTrait for Searcher {
         let doc_reader = self.reader.searcher();
         let docs = top_docs
             .into_iter()
-            // Filter out negative scores
-            .filter(|(score, _)| *score > 0.0)
             .flat_map(|(score, addr)| {
                 if let Ok(Some(doc)) = doc_reader.doc(addr).map(|x| document_to_struct(&x)) {
                     Some((score, doc))
@@ -149,12 +178,78 @@ impl SearchTrait for Searcher {
             wall_time_ms: Instant::now().duration_since(start_timer).as_millis(),
             num_docs: searcher.num_docs(),
             term_counts,
+            total_hits: num_hits,
             documents: docs,
+            facets,
+            is_fuzzy,
+            suggestion,
         }
     }
 }
 
 impl Searcher {
+    /// Runs the query, returning the requested page of results ordered by `order`,
+    /// the facet counts & the total number of matching docs.
+    fn collect(
+        &self,
+        searcher: &tantivy::Searcher,
+        query: &dyn Query,
+        num_results: usize,
+        offset: usize,
+        order: SortOrder,
+    ) -> (Vec<(Score, DocAddress)>, FacetCounts, usize) {
+        let fields = DocFields::as_fields();
+        let collector = TopDocs::with_limit(num_results).and_offset(offset);
+        let facet_collector = FacetCollector::new(fields.tags, fields.domain);
+
+        match order {
+            SortOrder::Relevance => {
+                let (top_docs, facets, num_hits) = searcher
+                    .search(query, &(collector, facet_collector, Count))
+                    .expect("Unable to execute query");
+
+                let top_docs = top_docs
+                    .into_iter()
+                    // Filter out negative scores
+                    .filter(|(score, _)| *score > 0.0)
+                    .collect::<Vec<_>>();
+                (top_docs, facets, num_hits)
+            }
+            SortOrder::NewestFirst | SortOrder::OldestFirst => {
+                let field = fields.lastmodified;
+                let newest_first = order == SortOrder::NewestFirst;
+                let collector = collector.custom_score(move |segment_reader: &SegmentReader| {
+                    let dates = segment_reader
+                        .fast_fields()
+                        .date(field)
+                        .expect("lastmodified is not a fast field");
+
+                    move |doc: DocId| {
+                        let micros = dates.get_val(doc as u64).into_timestamp_micros();
+                        // Documents w/o a date always go last
+                        if micros == 0 {
+                            i64::MIN
+                        } else if newest_first {
+                            micros
+                        } else {
+                            -micros
+                        }
+                    }
+                });
+
+                let (top_docs, facets, num_hits) = searcher
+                    .search(query, &(collector, facet_collector, Count))
+                    .expect("Unable to execute query");
+
+                let top_docs = top_docs
+                    .into_iter()
+                    .map(|(_, addr)| (0.0, addr))
+                    .collect::<Vec<_>>();
+                (top_docs, facets, num_hits)
+            }
+        }
+    }
+
     pub fn is_readonly(&self) -> bool {
         self.writer.is_none()
     }
@@ -185,7 +280,11 @@ impl Searcher {
         let index = match index_path {
             IndexBackend::LocalPath(path) => schema::initialize_index(schema, path)?,
             IndexBackend::Memory => schema::initialize_in_memory_index(schema),
-            IndexBackend::Http(_) => unimplemented!(""),
+            IndexBackend::Http(endpoint) => {
+                return Err(SearchError::Other(anyhow::anyhow!(
+                    "Remote index at {endpoint} must be opened with HttpSearcher"
+                )))
+            }
         };
 
         // Should only be one writer at a time. This single IndexWriter is already
@@ -217,10 +316,11 @@ impl Searcher {
         })
     }
 
-    /// Helper method to execute a search based on the provided document query
+    /// Helper method to execute a search based on the provided document query. URLs
+    /// can be matched exactly, by prefix or by regex.
     pub async fn search_by_query(
         &self,
-        urls: Option<Vec<String>>,
+        urls: Option<Vec<Boost>>,
         ids: Option<Vec<String>>,
         has_tags: &[u64],
         exclude_tags: &[u64],
@@ -252,52 +352,220 @@ impl Searcher {
             .collect()
     }
 
-    pub async fn explain_search_with_lens(
+    /// Completions for a partially typed query, drawn from the `title` & `content`
+    /// term dictionaries & the titles of matching documents.
+    pub fn autocomplete(&self, query: &str, limit: usize) -> Completions {
+        let fields = DocFields::as_fields();
+        let searcher = self.reader.searcher();
+
+        // Titles use the default tokenizer, which only lowercases.
+        let analyzer = match self.index.tokenizer_for_field(fields.title) {
+            Ok(analyzer) => analyzer,
+            Err(err) => {
+                log::warn!("Unable to load title tokenizer: {}", err);
+                return Completions::default();
+            }
+        };
+
+        let content_analyzer = match self.index.tokenizer_for_field(fields.content) {
+            Ok(analyzer) => analyzer,
+            Err(err) => {
+                log::warn!("Unable to load content tokenizer: {}", err);
+                return Completions::default();
+            }
+        };
+
+        let (words, prefix) = autocomplete::split_prefix(&analyzer, query);
+        let terms = prefix
+            .as_deref()
+            .map(|prefix| {
+                // Keep whatever was typed before the partial word as is.
+                let typed = query.trim_end();
+                let typed = typed.trim_end_matches(char::is_alphanumeric);
+                autocomplete::complete_words(
+                    &searcher,
+                    fields.title,
+                    fields.content,
+                    &content_analyzer,
+                    prefix,
+                    limit,
+                )
+                .into_iter()
+                .map(|word| format!("{typed}{word}"))
+                .collect()
+            })
+            .unwrap_or_default();
+
+        let titles = autocomplete::complete_titles(
+            &searcher,
+            fields.title,
+            &words,
+            prefix.as_deref(),
+            limit,
+        );
+
+        Completions { terms, titles }
+    }
+
+    /// Documents sharing the most distinctive terms w/ `doc_id`, most related first.
+    /// Only documents passing the `filters` are returned.
+    pub async fn more_like_this(
+        &self,
+        doc_id: &str,
+        filters: &[QueryBoost],
+        num_results: usize,
+    ) -> Vec<(Score, RetrievedDocument)> {
+        let fields = DocFields::as_fields();
+        let Some(doc) = self.get(doc_id).await else {
+            return Vec::new();
+        };
+
+        let searcher = self.reader.searcher();
+        let mut terms = Vec::new();
+        if let Ok(analyzer) = self.index.tokenizer_for_field(fields.title) {
+            terms.extend(related::top_terms(
+                &searcher,
+                &analyzer,
+                fields.title,
+                &doc.title,
+                related::MAX_QUERY_TERMS,
+            ));
+        }
+
+        // Content is indexed w/ the analyzer for its language or the code analyzer,
+        // the terms need to line up w/ the ones in the index.
+        let analyzer = schema::content_analyzer(&doc.url, DocLanguage::detect(&doc.content));
+        terms.extend(related::top_terms(
+            &searcher,
+            &analyzer,
+            fields.content,
+            &doc.content,
+            related::MAX_QUERY_TERMS,
+        ));
+
+        // Best terms across both fields
+        terms.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        terms.truncate(related::MAX_QUERY_TERMS);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let (_, filter_query) = build_query(
+            &self.index,
+            "",
+            filters,
+            &[],
+            QueryOptions::default().with_terms_optional(true),
+        );
+        let query = BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(related::more_like_this_query(doc_id, &terms)),
+            ),
+            (Occur::Must, Box::new(filter_query)),
+        ]);
+
+        searcher
+            .search(&query, &TopDocs::with_limit(num_results))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(score, addr)| {
+                let doc = searcher.doc(addr).ok()?;
+                document_to_struct(&doc).map(|doc| (score, doc))
+            })
+            .collect()
+    }
+
+    /// Documents whose SPLADE expansion best matches the expanded query, best first,
+    /// along w/ the dot product of the two. Only documents passing the `filters`
+    /// are returned.
+    pub async fn search_sparse(
         &self,
-        doc_id: String,
+        terms: &[(String, f32)],
+        filters: &[QueryBoost],
+        num_results: usize,
+    ) -> Vec<(Score, String)> {
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let fields = DocFields::as_fields();
+        let searcher = self.reader.searcher();
+        let (_, filter_query) = build_query(
+            &self.index,
+            "",
+            filters,
+            &[],
+            QueryOptions::default().with_terms_optional(true),
+        );
+        let query = BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(sparse::sparse_query(fields.sparse, terms)),
+            ),
+            (Occur::Must, Box::new(filter_query)),
+        ]);
+
+        searcher
+            .search(&query, &TopDocs::with_limit(num_results))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(score, addr)| {
+                let doc = searcher.doc(addr).ok()?;
+                let doc_id = field_to_string(&doc, fields.id);
+                (!doc_id.is_empty()).then_some((score, doc_id))
+            })
+            .collect()
+    }
+
+    /// Breaks down how `doc_id` is scored for the query w/ the provided filters &
+    /// boosts applied. Returns `None` if the document isn't in the index.
+    pub fn explain(
+        &self,
+        doc_id: &str,
         query_string: &str,
+        filters: &[QueryBoost],
         boosts: &[QueryBoost],
-    ) -> Option<f32> {
-        let index = &self.index;
-        let reader = &self.reader;
+    ) -> Option<ScoreExplanation> {
         let fields = DocFields::as_fields();
+        let searcher = self.reader.searcher();
+
+        let doc_query = TermQuery::new(
+            Term::from_field_text(fields.id, doc_id),
+            IndexRecordOption::Basic,
+        );
+        let (_, addr) = searcher
+            .search(&doc_query, &TopDocs::with_limit(1))
+            .ok()?
+            .into_iter()
+            .next()?;
 
-        let tantivy_searcher = reader.searcher();
-        let filters = vec![QueryBoost::new(Boost::DocId(doc_id.clone()))];
-        let (_, final_query) = build_query(
+        let (_, query, scored) = build_scored_query(
             &self.index,
             query_string,
-            &filters,
+            filters,
             boosts,
             QueryOptions::default(),
         );
 
-        let tokenizers = index.tokenizers();
-        let content_terms =
-            terms_for_field(&index.schema(), tokenizers, query_string, fields.content);
-        log::info!("Content Tokens {:?}", content_terms);
-
-        let collector = tantivy::collector::TopDocs::with_limit(1);
-        let docs = tantivy_searcher
-            .search(&final_query, &collector)
-            .expect("Unable to execute query");
+        // Explaining a doc that doesn't match the query is an error
+        let (matches, score) = match query.explain(&searcher, addr) {
+            Ok(explanation) => (true, explanation.value()),
+            Err(_) => (false, 0.0),
+        };
 
-        for (score, addr) in docs {
-            if let Ok(Some(result)) = tantivy_searcher.doc(addr).map(|x| document_to_struct(&x)) {
-                if result.doc_id == doc_id {
-                    for t in content_terms {
-                        let info = tantivy_searcher
-                            .segment_reader(addr.segment_ord)
-                            .inverted_index(fields.content)
-                            .unwrap()
-                            .get_term_info(&t.1);
-                        log::info!("Term {:?} Info {:?} ", t, info);
-                    }
+        let components = scored
+            .into_iter()
+            .filter_map(|(source, query)| {
+                let score = query.explain(&searcher, addr).ok()?.value();
+                Some(ScoreComponent { source, score })
+            })
+            .collect();
 
-                    return Some(score);
-                }
-            }
-        }
-        None
+        Some(ScoreExplanation {
+            matches,
+            score,
+            components,
+        })
     }
 }
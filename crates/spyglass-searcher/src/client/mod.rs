@@ -1,2 +1,6 @@
+mod backend;
+mod http;
 mod local;
+pub use self::backend::*;
+pub use self::http::*;
 pub use self::local::*;
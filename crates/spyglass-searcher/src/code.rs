@@ -0,0 +1,321 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use tantivy::tokenizer::{
+    BoxTokenStream, LowerCaser, PreTokenizedString, RemoveLongFilter, TextAnalyzer, Token,
+    TokenStream, Tokenizer,
+};
+
+/// Name the code analyzer is registered under in the index tokenizer manager
+pub const CODE_TOKENIZER_NAME: &str = "spyglass_tokenizer_code";
+
+/// Analyzer for source code & symbols. Identifiers are split into their words, but
+/// unlike prose they aren't stemmed & stop words are kept, e.g. `getItemsByID`
+/// becomes `get`, `items`, `by`, `id`.
+pub fn analyzer() -> TextAnalyzer {
+    TextAnalyzer::from(CodeTokenizer)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+}
+
+/// Runs `text` through the code analyzer so it can be added to a field regardless
+/// of the tokenizer configured for that field.
+pub fn pre_tokenize(text: &str) -> PreTokenizedString {
+    let mut tokens: Vec<Token> = Vec::new();
+    analyzer()
+        .token_stream(text)
+        .process(&mut |token| tokens.push(token.clone()));
+
+    PreTokenizedString {
+        text: text.to_string(),
+        tokens,
+    }
+}
+
+/// Splits text on anything that can't be part of an identifier, then splits each
+/// identifier on underscores & camelCase boundaries.
+#[derive(Clone, Default)]
+pub struct CodeTokenizer;
+
+pub struct CodeTokenStream {
+    tokens: Vec<Token>,
+    // Index of the next token to return.
+    cursor: usize,
+}
+
+impl Tokenizer for CodeTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CodeTokenStream {
+            tokens: tokenize(text),
+            cursor: 0,
+        })
+    }
+}
+
+impl TokenStream for CodeTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.cursor < self.tokens.len() {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.cursor - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.cursor - 1]
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut push = |from: usize, to: usize| {
+        tokens.push(Token {
+            offset_from: from,
+            offset_to: to,
+            position: tokens.len(),
+            text: text[from..to].to_string(),
+            position_length: 1,
+        });
+    };
+
+    let mut word_start: Option<usize> = None;
+    for (idx, (offset, c)) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if let Some(start) = word_start.take() {
+                push(start, *offset);
+            }
+            continue;
+        }
+
+        let Some(start) = word_start else {
+            word_start = Some(*offset);
+            continue;
+        };
+
+        // camelCase, snake2Camel & the end of an acronym, e.g. the `S` in `HTTPServer`
+        let prev = chars[idx - 1].1;
+        let next = chars.get(idx + 1).map(|(_, c)| *c);
+        let is_boundary = c.is_uppercase()
+            && (prev.is_lowercase()
+                || prev.is_numeric()
+                || (prev.is_uppercase() && next.map_or(false, |next| next.is_lowercase())));
+        if is_boundary {
+            push(start, *offset);
+            word_start = Some(*offset);
+        }
+    }
+
+    if let Some(start) = word_start {
+        push(start, text.len());
+    }
+
+    tokens
+}
+
+/// Programming languages we extract symbols from, the same ones the crawler reads
+/// as code files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeLanguage {
+    C,
+    Cpp,
+    JavaScript,
+    Rust,
+    TypeScript,
+}
+
+impl CodeLanguage {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "c" => Some(CodeLanguage::C),
+            "cpp" => Some(CodeLanguage::Cpp),
+            "js" => Some(CodeLanguage::JavaScript),
+            "rs" => Some(CodeLanguage::Rust),
+            "ts" => Some(CodeLanguage::TypeScript),
+            _ => None,
+        }
+    }
+
+    /// Language of the file a document URL points to, if it's a code file
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let file_name = path.rsplit('/').next()?;
+        let (_, ext) = file_name.rsplit_once('.')?;
+        Self::from_extension(ext)
+    }
+
+    /// Names of the functions, types & classes defined in `content`, in the order
+    /// they're defined. Symbols are found w/ a few patterns per language rather
+    /// than a full parse, so some may be missed.
+    pub fn symbols(&self, content: &str) -> Vec<String> {
+        let mut found = self
+            .patterns()
+            .iter()
+            .flat_map(|pattern| pattern.captures_iter(content))
+            .filter_map(|captures| captures.get(1))
+            .map(|name| (name.start(), name.as_str()))
+            .collect::<Vec<_>>();
+        found.sort_by_key(|(start, _)| *start);
+
+        let mut symbols: Vec<String> = Vec::new();
+        for (_, name) in found {
+            // Only the name itself for qualified C++ definitions, e.g. `Class::method`
+            let name = name.rsplit("::").next().unwrap_or(name);
+            if !KEYWORDS.contains(&name) && !symbols.iter().any(|symbol| symbol == name) {
+                symbols.push(name.to_string());
+            }
+        }
+
+        symbols
+    }
+
+    fn patterns(&self) -> &'static [Regex] {
+        static C_LIKE: OnceLock<Vec<Regex>> = OnceLock::new();
+        static JS_LIKE: OnceLock<Vec<Regex>> = OnceLock::new();
+        static RUST: OnceLock<Vec<Regex>> = OnceLock::new();
+
+        let compile = |patterns: &[&str]| {
+            patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("Invalid symbol pattern"))
+                .collect::<Vec<_>>()
+        };
+
+        match self {
+            CodeLanguage::C | CodeLanguage::Cpp => C_LIKE.get_or_init(|| {
+                compile(&[
+                    // struct, class, union, enum & namespace definitions
+                    r"(?m)^\s*(?:typedef\s+)?(?:struct|class|union|enum(?:\s+class)?|namespace)\s+([A-Za-z_]\w*)\s*(?:final\s*)?(?:[:{]|$)",
+                    // function definitions, which start at the beginning of a line
+                    r"(?m)^(?:[A-Za-z_][\w:<>,]*[\s\*&]+)+([A-Za-z_~]\w*(?:::[A-Za-z_~]\w*)*)\s*\([^;\n]*$",
+                ])
+            }),
+            CodeLanguage::JavaScript | CodeLanguage::TypeScript => JS_LIKE.get_or_init(|| {
+                compile(&[
+                    // functions, classes & type definitions
+                    r"(?m)^\s*(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(?:function\s*\*?|class|interface|type|enum|namespace)\s+([A-Za-z_$][\w$]*)",
+                    // functions assigned to a variable
+                    r"(?m)^\s*(?:export\s+)?(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:function\b|(?:\([^)]*\)|[A-Za-z_$][\w$]*)\s*(?::[^=]+)?=>)",
+                    // class methods
+                    r"(?m)^\s+(?:(?:public|private|protected|static|async|readonly|override|get|set)\s+)*([A-Za-z_$][\w$]*)\s*\([^)]*\)\s*(?::\s*[^{;=]+)?\{",
+                ])
+            }),
+            CodeLanguage::Rust => RUST.get_or_init(|| {
+                compile(&[
+                    r#"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+"[^"]*")\s+)*(?:fn|struct|enum|union|trait|type|mod)\s+([A-Za-z_][A-Za-z0-9_]*)"#,
+                    r"(?m)^\s*macro_rules!\s*([A-Za-z_][A-Za-z0-9_]*)",
+                ])
+            }),
+        }
+    }
+}
+
+// Control flow that looks like a function definition to the patterns above
+const KEYWORDS: &[&str] = &[
+    "catch", "do", "else", "for", "function", "if", "return", "sizeof", "switch", "while",
+];
+
+#[cfg(test)]
+mod test {
+    use super::{analyzer, CodeLanguage};
+
+    fn tokens(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        analyzer()
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.text.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_tokenizer() {
+        assert_eq!(tokens("build_query"), vec!["build", "query"]);
+        assert_eq!(tokens("buildQuery"), vec!["build", "query"]);
+        assert_eq!(
+            tokens("let server = HTTPServer::new(utf8Decode);"),
+            vec!["let", "server", "http", "server", "new", "utf8", "decode"]
+        );
+        // No stemming or stop words
+        assert_eq!(tokens("the parsed values"), vec!["the", "parsed", "values"]);
+    }
+
+    #[test]
+    fn test_from_url() {
+        assert_eq!(
+            CodeLanguage::from_url("file://localhost/src/query.rs"),
+            Some(CodeLanguage::Rust)
+        );
+        assert_eq!(
+            CodeLanguage::from_url("https://example.com/static/app.js?v=2"),
+            Some(CodeLanguage::JavaScript)
+        );
+        assert_eq!(CodeLanguage::from_url("file://localhost/notes.md"), None);
+        assert_eq!(CodeLanguage::from_url("https://example.com/rs"), None);
+    }
+
+    #[test]
+    fn test_symbols() {
+        let rust = r#"
+            pub(crate) struct QueryOptions {
+                fuzzy: bool,
+            }
+
+            pub async fn build_query(index: &Index) -> BooleanQuery {
+                let query = BooleanQuery::new(vec![]);
+                query
+            }
+
+            impl Default for QueryOptions {}
+            macro_rules! boosted { () => {} }
+        "#;
+        assert_eq!(
+            CodeLanguage::Rust.symbols(rust),
+            vec!["QueryOptions", "build_query", "boosted"]
+        );
+
+        let ts = r#"
+            export interface SearchMeta { query: string }
+            export const fetchResults = async (query: string) => {
+                if (query) {
+                    return [];
+                }
+            };
+            export default class SearchPage {
+                private renderResult(result: SearchResult): void {
+                }
+            }
+            function clamp(value) { return value; }
+        "#;
+        assert_eq!(
+            CodeLanguage::TypeScript.symbols(ts),
+            vec![
+                "SearchMeta",
+                "fetchResults",
+                "SearchPage",
+                "renderResult",
+                "clamp"
+            ]
+        );
+
+        let cpp = "namespace search {\n\
+            class Index {\n\
+            };\n\
+            struct Point p;\n\
+            static int parse_query(const char *query, size_t len)\n\
+            {\n\
+                if (len == 0)\n\
+                    return 0;\n\
+            }\n\
+            void Index::commit() {\n\
+            }\n";
+        assert_eq!(
+            CodeLanguage::Cpp.symbols(cpp),
+            vec!["search", "Index", "parse_query", "commit"]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{RetrievedDocument, Score};
+
+/// Number of results fetched on either side of a cursor, so the page can still be
+/// lined up w/ the last seen document if it moved a bit since the last request.
+pub const CURSOR_SLACK: usize = 20;
+
+/// Points just past the last result a client has seen. Unlike a plain offset, the
+/// next page continues after that document even if documents were added or
+/// removed in between, so results don't get skipped or repeated while the index
+/// is being committed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor {
+    /// Number of results returned so far
+    pub offset: usize,
+    /// Score of the last result returned
+    pub score: Score,
+    /// Id of the last result returned
+    pub doc_id: String,
+}
+
+impl Cursor {
+    /// Cursor for the page following `doc`, the last result at `offset - 1`.
+    pub fn after(offset: usize, score: Score, doc: &RetrievedDocument) -> Self {
+        Cursor {
+            offset,
+            score,
+            doc_id: doc.doc_id.clone(),
+        }
+    }
+
+    /// Offset & number of results to fetch to be able to resume from this cursor
+    pub fn window(&self, num_results: usize) -> (usize, usize) {
+        let start = self.offset.saturating_sub(CURSOR_SLACK);
+        (start, num_results + (self.offset - start) + CURSOR_SLACK)
+    }
+
+    /// Picks the next page out of the results fetched for `window`. If the last
+    /// seen document is in there, the page starts right after it. Otherwise it
+    /// falls back to the first result ranked below it, or the original offset when
+    /// results don't have a score (e.g. sorted by date).
+    pub fn resume(
+        &self,
+        results: Vec<(Score, RetrievedDocument)>,
+        num_results: usize,
+    ) -> Vec<(Score, RetrievedDocument)> {
+        let skip = match results
+            .iter()
+            .position(|(_, doc)| doc.doc_id == self.doc_id)
+        {
+            Some(pos) => pos + 1,
+            None if self.score > 0.0 => results
+                .iter()
+                .position(|(score, _)| *score < self.score)
+                .unwrap_or(results.len()),
+            None => self.offset - self.offset.saturating_sub(CURSOR_SLACK),
+        };
+
+        results.into_iter().skip(skip).take(num_results).collect()
+    }
+}
+
+// Cursors are handed to clients as opaque strings: `<offset>:<score bits>:<doc_id>`.
+// The score is stored as its raw bits so it survives the round trip exactly.
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{:08x}:{}",
+            self.offset,
+            self.score.to_bits(),
+            self.doc_id
+        )
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+        let (Some(offset), Some(score), Some(doc_id)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow::anyhow!("expected `<offset>:<score>:<doc_id>`"));
+        };
+
+        Ok(Cursor {
+            offset: offset.parse()?,
+            score: f32::from_bits(u32::from_str_radix(score, 16)?),
+            doc_id: doc_id.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cursor;
+    use crate::RetrievedDocument;
+
+    fn results(ids: &[(&str, f32)]) -> Vec<(f32, RetrievedDocument)> {
+        ids.iter()
+            .map(|(id, score)| {
+                (
+                    *score,
+                    RetrievedDocument {
+                        doc_id: id.to_string(),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn ids(results: &[(f32, RetrievedDocument)]) -> Vec<&str> {
+        results.iter().map(|(_, doc)| doc.doc_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cursor = Cursor {
+            offset: 10,
+            score: 1.2345,
+            doc_id: "abc:def".into(),
+        };
+
+        let parsed = cursor.to_string().parse::<Cursor>().expect("valid cursor");
+        assert_eq!(parsed, cursor);
+        assert!("garbage".parse::<Cursor>().is_err());
+    }
+
+    #[test]
+    fn test_resume() {
+        let cursor = Cursor {
+            offset: 2,
+            score: 3.0,
+            doc_id: "b".into(),
+        };
+
+        // A new doc was committed ahead of the last seen doc, it doesn't shift the page
+        let fetched = results(&[("new", 9.0), ("a", 4.0), ("b", 3.0), ("c", 2.0), ("d", 1.0)]);
+        assert_eq!(ids(&cursor.resume(fetched, 2)), vec!["c", "d"]);
+
+        // The last seen doc was deleted, continue w/ whatever ranked below it
+        let fetched = results(&[("a", 4.0), ("c", 2.0), ("d", 1.0)]);
+        assert_eq!(ids(&cursor.resume(fetched, 2)), vec!["c", "d"]);
+    }
+}
@@ -0,0 +1,156 @@
+/// Fingerprints w/ at most this many differing bits belong to near-duplicate documents
+pub const NEAR_DUPLICATE_BITS: u32 = 3;
+/// Number of words in each shingle hashed into the fingerprint
+const SHINGLE_SIZE: usize = 3;
+/// Documents w/ fewer words than this don't get a fingerprint, there's too little
+/// content to tell them apart from other short documents.
+const MIN_WORDS: usize = 16;
+
+/// SimHash of the content, similar content results in fingerprints that differ by
+/// only a few bits. Content is compared as lowercased word shingles, so formatting
+/// & punctuation don't matter.
+pub fn fingerprint(content: &str) -> Option<u64> {
+    let words = content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>();
+
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut weights = [0i32; 64];
+    for shingle in words.windows(SHINGLE_SIZE) {
+        let hash = hash_shingle(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit)),
+    )
+}
+
+/// Whether two fingerprints belong to near-duplicate documents
+pub fn is_near_duplicate(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= NEAR_DUPLICATE_BITS
+}
+
+/// Collapses near-duplicates into the first (i.e. highest ranked) item of their
+/// group, returning each remaining item along w/ its duplicates. Items w/o a
+/// fingerprint are never collapsed.
+pub fn collapse<T>(items: Vec<T>, fingerprint: impl Fn(&T) -> Option<u64>) -> Vec<(T, Vec<T>)> {
+    let mut collapsed: Vec<(Option<u64>, T, Vec<T>)> = Vec::new();
+    for item in items {
+        let print = fingerprint(&item);
+        let original = print.and_then(|print| {
+            collapsed
+                .iter_mut()
+                .find(|(other, _, _)| other.map_or(false, |other| is_near_duplicate(print, other)))
+        });
+
+        match original {
+            Some((_, _, duplicates)) => duplicates.push(item),
+            None => collapsed.push((print, item, Vec::new())),
+        }
+    }
+
+    collapsed
+        .into_iter()
+        .map(|(_, item, duplicates)| (item, duplicates))
+        .collect()
+}
+
+// FNV-1a over the shingle followed by a final mix, since SimHash needs every bit
+// of the hash to be equally likely to be set. The fingerprints are stored, so this
+// needs to stay stable (unlike std's `DefaultHasher`).
+fn hash_shingle(words: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for word in words {
+        for byte in word.bytes().chain(std::iter::once(b' ')) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{collapse, fingerprint, is_near_duplicate};
+
+    const ARTICLE: &str = "A few miles south of Soledad, the Salinas River drops in close to the
+        hillside bank and runs deep and green. The water is warm too, for it has slipped
+        twinkling over the yellow sands in the sunlight before reaching the narrow pool. On
+        one side of the river the golden foothill slopes curve up to the strong and rocky
+        Gabilan Mountains, but on the valley side the water is lined with trees—willows fresh
+        and green with every spring, carrying in their lower leaf junctures the debris of the
+        winter's flooding; and sycamores with mottled, white, recumbent limbs and branches
+        that arch over the pool.";
+
+    const OTHER: &str = "You will rejoice to hear that no disaster has accompanied the
+        commencement of an enterprise which you have regarded with such evil forebodings. I
+        arrived here yesterday, and my first task is to assure my dear sister of my welfare
+        and increasing confidence in the success of my undertaking.";
+
+    #[test]
+    fn test_fingerprint() {
+        let original = fingerprint(ARTICLE).expect("Enough content to fingerprint");
+        // Same article w/ different formatting
+        let reformatted = fingerprint(&ARTICLE.to_uppercase().replace(',', " ;"));
+        assert_eq!(reformatted, Some(original));
+
+        // A mirror w/ a small banner added
+        let mirror = fingerprint(&format!("{ARTICLE} Archived copy")).unwrap();
+        assert!(is_near_duplicate(original, mirror));
+
+        let other = fingerprint(OTHER).unwrap();
+        assert!(!is_near_duplicate(original, other));
+
+        assert_eq!(fingerprint("Too short to tell apart"), None);
+    }
+
+    #[test]
+    fn test_collapse() {
+        let original = fingerprint(ARTICLE);
+        let mirror = fingerprint(&format!("{ARTICLE} Archived copy"));
+        let other = fingerprint(OTHER);
+
+        let items = vec![
+            ("a", original),
+            ("b", other),
+            ("c", None),
+            ("d", mirror),
+            ("e", None),
+        ];
+        let collapsed = collapse(items, |(_, print)| *print)
+            .into_iter()
+            .map(|((id, _), dupes)| (id, dupes.iter().map(|(id, _)| *id).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            collapsed,
+            vec![
+                ("a", vec!["d"]),
+                ("b", vec![]),
+                ("c", vec![]),
+                ("e", vec![]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use crate::Score;
+
+/// What part of the query a score contribution came from
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoreSource {
+    /// BM25 score for a single query term found in `field`
+    Term { field: String, term: String },
+    /// Typo tolerant match for a query term in `field`
+    FuzzyTerm { field: String, term: String },
+    /// Boost for the query terms appearing together as a phrase in `field`
+    Phrase { field: String },
+    /// Boost for documents w/ this tag, e.g. lens tags or tags mentioned in the query
+    Tag(u64),
+    /// Boost for favorited documents
+    Favorite(u64),
+    /// Boost for a specific document
+    DocId(String),
+    /// Boost for a specific URL, or any URL w/ a prefix or matching a pattern
+    Url(String),
+    /// Boost for a custom field value
+    CustomField { field: String, value: u64 },
+    /// Query operators such as `domain:` or `"exact phrases"`
+    Operator(String),
+}
+
+/// Score a single part of the query contributed to a document's score
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreComponent {
+    pub source: ScoreSource,
+    pub score: Score,
+}
+
+/// Breakdown of how a document was scored for a query
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScoreExplanation {
+    /// Whether the document matches the query & filters at all
+    pub matches: bool,
+    /// Score of the document for the full query
+    pub score: Score,
+    /// Parts of the query that contributed to the score, in query order. These add
+    /// up to `score`, w/ the exception of constant scored clauses used to match
+    /// every document.
+    pub components: Vec<ScoreComponent>,
+}
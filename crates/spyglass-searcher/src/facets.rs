@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::fastfield::MultiValuedFastFieldReader;
+use tantivy::schema::Field;
+use tantivy::{DocId, InvertedIndexReader, Score, SegmentOrdinal, SegmentReader};
+
+/// Number of matched documents per tag id & per domain
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetCounts {
+    pub tags: HashMap<u64, u64>,
+    pub domains: HashMap<String, u64>,
+}
+
+impl FacetCounts {
+    fn merge(&mut self, other: FacetCounts) {
+        for (tag, count) in other.tags {
+            *self.tags.entry(tag).or_default() += count;
+        }
+
+        for (domain, count) in other.domains {
+            *self.domains.entry(domain).or_default() += count;
+        }
+    }
+}
+
+/// Collects facet counts over every document matched by a query using the
+/// multi-valued `tags` fast field & the term ordinals of the `domain` fast field.
+pub struct FacetCollector {
+    tags: Field,
+    domain: Field,
+}
+
+impl FacetCollector {
+    pub fn new(tags: Field, domain: Field) -> Self {
+        Self { tags, domain }
+    }
+}
+
+impl Collector for FacetCollector {
+    type Fruit = FacetCounts;
+    type Child = FacetSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(FacetSegmentCollector {
+            tag_reader: segment.fast_fields().u64s(self.tags)?,
+            // Text fast fields hold the ordinals of the terms in the segment
+            domain_reader: segment.fast_fields().u64s_lenient(self.domain)?,
+            domain_index: segment.inverted_index(self.domain)?,
+            domain_ords: HashMap::new(),
+            buffer: Vec::new(),
+            counts: FacetCounts::default(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<FacetCounts>) -> tantivy::Result<FacetCounts> {
+        let mut counts = FacetCounts::default();
+        for fruit in segment_fruits {
+            counts.merge(fruit);
+        }
+
+        Ok(counts)
+    }
+}
+
+pub struct FacetSegmentCollector {
+    tag_reader: MultiValuedFastFieldReader<u64>,
+    domain_reader: MultiValuedFastFieldReader<u64>,
+    domain_index: Arc<InvertedIndexReader>,
+    /// Number of matched docs per domain term ordinal
+    domain_ords: HashMap<u64, u64>,
+    buffer: Vec<u64>,
+    counts: FacetCounts,
+}
+
+impl FacetSegmentCollector {
+    /// Looks up the domain for each term ordinal counted in this segment.
+    fn count_domains(&mut self) -> std::io::Result<()> {
+        let terms = self.domain_index.terms();
+        let mut bytes = Vec::new();
+        for (ord, count) in self.domain_ords.drain() {
+            bytes.clear();
+            if terms.ord_to_term(ord, &mut bytes)? {
+                let domain = String::from_utf8_lossy(&bytes).to_string();
+                *self.counts.domains.entry(domain).or_default() += count;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SegmentCollector for FacetSegmentCollector {
+    type Fruit = FacetCounts;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.buffer.clear();
+        self.tag_reader.get_vals(doc, &mut self.buffer);
+        for tag in &self.buffer {
+            *self.counts.tags.entry(*tag).or_default() += 1;
+        }
+
+        self.buffer.clear();
+        self.domain_reader.get_vals(doc, &mut self.buffer);
+        for ord in &self.buffer {
+            *self.domain_ords.entry(*ord).or_default() += 1;
+        }
+    }
+
+    fn harvest(mut self) -> FacetCounts {
+        if let Err(err) = self.count_domains() {
+            log::warn!("Unable to count domain facets: {}", err);
+        }
+
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FacetCounts;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_merge_counts() {
+        let mut counts = FacetCounts {
+            tags: HashMap::from([(1, 2), (2, 1)]),
+            domains: HashMap::from([("example.com".to_string(), 3)]),
+        };
+
+        counts.merge(FacetCounts {
+            tags: HashMap::from([(1, 1), (3, 4)]),
+            domains: HashMap::from([
+                ("example.com".to_string(), 1),
+                ("spyglass.fyi".to_string(), 2),
+            ]),
+        });
+
+        assert_eq!(counts.tags, HashMap::from([(1, 3), (2, 1), (3, 4)]));
+        assert_eq!(counts.domains.get("example.com"), Some(&4));
+        assert_eq!(counts.domains.get("spyglass.fyi"), Some(&2));
+    }
+}
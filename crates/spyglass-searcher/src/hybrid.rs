@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use crate::{RetrievedDocument, Score, SortOrder};
+
+/// A document found through vector search
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticHit {
+    pub doc_id: String,
+    /// Distance between the query & document embeddings, smaller is closer.
+    pub distance: f32,
+    /// Offsets of the document segment closest to the query, as stored w/ its
+    /// embedding.
+    pub segment: Option<(usize, usize)>,
+}
+
+/// How lexical & semantic results are merged into a single ranking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FusionMethod {
+    /// Reciprocal rank fusion, each list contributes `1 / (k + rank)` for a doc.
+    /// Only ranks are used, so the scales of the two scores don't matter.
+    ReciprocalRank { k: f32 },
+    /// Scores from each list are min-max normalized & combined, w/ `semantic_weight`
+    /// (0-1) given to the vector search score & the rest to the lexical score.
+    Weighted { semantic_weight: f32 },
+}
+
+impl Default for FusionMethod {
+    fn default() -> Self {
+        FusionMethod::ReciprocalRank { k: 60.0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HybridOptions {
+    pub method: FusionMethod,
+    /// Number of candidates retrieved from each retriever before fusion.
+    pub candidates: usize,
+}
+
+impl Default for HybridOptions {
+    fn default() -> Self {
+        HybridOptions {
+            method: FusionMethod::default(),
+            candidates: 50,
+        }
+    }
+}
+
+/// Merges the ranked `lexical` results w/ `semantic` results, which are ordered
+/// by distance (closest first). Documents found by both are only returned once.
+pub fn fuse(
+    lexical: Vec<(Score, RetrievedDocument)>,
+    semantic: Vec<(f32, RetrievedDocument)>,
+    method: FusionMethod,
+) -> Vec<(Score, RetrievedDocument)> {
+    let lexical_scores = lexical_scores(
+        &lexical.iter().map(|(score, _)| *score).collect::<Vec<_>>(),
+        method,
+    );
+    let semantic_scores = semantic_scores(
+        &semantic
+            .iter()
+            .map(|(distance, _)| *distance)
+            .collect::<Vec<_>>(),
+        method,
+    );
+
+    let mut fused: Vec<(Score, RetrievedDocument)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let ranked = lexical
+        .into_iter()
+        .map(|(_, doc)| doc)
+        .zip(lexical_scores)
+        .chain(
+            semantic
+                .into_iter()
+                .map(|(_, doc)| doc)
+                .zip(semantic_scores),
+        );
+
+    for (doc, score) in ranked {
+        match positions.get(&doc.doc_id) {
+            Some(pos) => fused[*pos].0 += score,
+            None => {
+                positions.insert(doc.doc_id.clone(), fused.len());
+                fused.push((score, doc));
+            }
+        }
+    }
+
+    // Stable, so ties keep the lexical order
+    fused.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    fused
+}
+
+/// What each of the ranked lexical results adds to their fused score
+pub fn lexical_scores(scores: &[Score], method: FusionMethod) -> Vec<Score> {
+    match method {
+        FusionMethod::ReciprocalRank { k } => reciprocal_ranks(scores.len(), k),
+        FusionMethod::Weighted { semantic_weight } => normalize(scores.iter().copied())
+            .into_iter()
+            .map(|score| score * (1.0 - semantic_weight))
+            .collect(),
+    }
+}
+
+/// What each of the semantic results, ordered by distance, adds to their fused score
+pub fn semantic_scores(distances: &[f32], method: FusionMethod) -> Vec<Score> {
+    match method {
+        FusionMethod::ReciprocalRank { k } => reciprocal_ranks(distances.len(), k),
+        // Closer is better, so flip the distances before normalizing
+        FusionMethod::Weighted { semantic_weight } => {
+            normalize(distances.iter().map(|distance| -distance))
+                .into_iter()
+                .map(|score| score * semantic_weight)
+                .collect()
+        }
+    }
+}
+
+/// Orders merged results by last modified date instead of score, documents w/o a
+/// date go last.
+pub fn sort_by_date(results: &mut [(Score, RetrievedDocument)], order: SortOrder) {
+    match order {
+        SortOrder::Relevance => {}
+        SortOrder::NewestFirst => {
+            results.sort_by(|(_, a), (_, b)| match (a.last_modified, b.last_modified) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            })
+        }
+        SortOrder::OldestFirst => {
+            results.sort_by(|(_, a), (_, b)| match (a.last_modified, b.last_modified) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            })
+        }
+    }
+}
+
+fn reciprocal_ranks(len: usize, k: f32) -> Vec<Score> {
+    (0..len).map(|rank| 1.0 / (k + rank as f32 + 1.0)).collect()
+}
+
+// Scales scores to 0-1, if all the scores are the same they're all 1.
+fn normalize(scores: impl Iterator<Item = f32>) -> Vec<Score> {
+    let scores = scores.collect::<Vec<_>>();
+    let min = scores.iter().copied().reduce(f32::min).unwrap_or_default();
+    let max = scores.iter().copied().reduce(f32::max).unwrap_or_default();
+
+    scores
+        .into_iter()
+        .map(|score| {
+            if max > min {
+                (score - min) / (max - min)
+            } else {
+                1.0
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fuse, FusionMethod};
+    use crate::RetrievedDocument;
+
+    fn doc(doc_id: &str) -> RetrievedDocument {
+        RetrievedDocument {
+            doc_id: doc_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(results: &[(f32, RetrievedDocument)]) -> Vec<&str> {
+        results.iter().map(|(_, doc)| doc.doc_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let lexical = vec![(4.0, doc("a")), (2.0, doc("b")), (1.0, doc("c"))];
+        let semantic = vec![(0.1, doc("c")), (0.2, doc("d")), (0.3, doc("b"))];
+
+        let fused = fuse(lexical, semantic, FusionMethod::ReciprocalRank { k: 60.0 });
+        // Found by both retrievers beats a single top rank, semantic only
+        // matches are included.
+        assert_eq!(ids(&fused), vec!["c", "b", "a", "d"]);
+        assert!((fused[0].0 - (1.0 / 63.0 + 1.0 / 61.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_weighted_fusion() {
+        let lexical = vec![(4.0, doc("a")), (2.0, doc("b")), (0.0, doc("c"))];
+        let semantic = vec![(0.1, doc("c")), (0.5, doc("a"))];
+
+        let fused = fuse(
+            lexical.clone(),
+            semantic.clone(),
+            FusionMethod::Weighted {
+                semantic_weight: 0.8,
+            },
+        );
+        assert_eq!(ids(&fused), vec!["c", "a", "b"]);
+
+        let fused = fuse(
+            lexical,
+            semantic,
+            FusionMethod::Weighted {
+                semantic_weight: 0.2,
+            },
+        );
+        assert_eq!(ids(&fused), vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,179 @@
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, Language, LowerCaser, PreTokenizedString, RemoveLongFilter,
+    SimpleTokenizer, Stemmer, TextAnalyzer, Token,
+};
+use whatlang::Lang;
+
+use crate::cjk_tokenizer::CjkTokenizer;
+use crate::schema::TOKENIZER_NAME;
+use crate::stop_word_filter::StopWordFilter;
+
+/// Only the start of a document is used to detect its language.
+const DETECTION_SAMPLE_BYTES: usize = 4096;
+
+/// Languages w/ a dedicated analyzer. Content in any other language is indexed
+/// w/ the English analyzer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocLanguage {
+    English,
+    German,
+    Polish,
+    Japanese,
+}
+
+impl DocLanguage {
+    pub const ALL: [DocLanguage; 4] = [
+        DocLanguage::English,
+        DocLanguage::German,
+        DocLanguage::Polish,
+        DocLanguage::Japanese,
+    ];
+
+    /// Detects the language of `text`, if it is one we have an analyzer for.
+    pub fn detect(text: &str) -> Option<Self> {
+        let mut end = text.len().min(DETECTION_SAMPLE_BYTES);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let info = whatlang::detect(&text[..end])?;
+        // Japanese is detected from the script alone, which is reliable enough
+        // even when whatlang isn't confident about the exact language.
+        if !info.is_reliable() && info.lang() != Lang::Jpn {
+            return None;
+        }
+
+        match info.lang() {
+            Lang::Eng => Some(DocLanguage::English),
+            Lang::Deu => Some(DocLanguage::German),
+            Lang::Pol => Some(DocLanguage::Polish),
+            Lang::Jpn => Some(DocLanguage::Japanese),
+            _ => None,
+        }
+    }
+
+    /// ISO 639-1 code, used as the value of the language tag
+    pub fn code(&self) -> &'static str {
+        match self {
+            DocLanguage::English => "en",
+            DocLanguage::German => "de",
+            DocLanguage::Polish => "pl",
+            DocLanguage::Japanese => "ja",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|lang| lang.code().eq_ignore_ascii_case(code))
+    }
+
+    /// Name the analyzer is registered under in the index tokenizer manager
+    pub fn tokenizer_name(&self) -> String {
+        match self {
+            // Default tokenizer for the content field
+            DocLanguage::English => TOKENIZER_NAME.to_string(),
+            other => format!("spyglass_tokenizer_{}", other.code()),
+        }
+    }
+
+    pub fn analyzer(&self) -> TextAnalyzer {
+        match self {
+            DocLanguage::English => TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(AsciiFoldingFilter)
+                .filter(StopWordFilter::default())
+                .filter(Stemmer::new(Language::English)),
+            // Stemmer first, it expects umlauts & ß to still be around.
+            DocLanguage::German => TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(StopWordFilter::german())
+                .filter(Stemmer::new(Language::German))
+                .filter(AsciiFoldingFilter),
+            // There is no Polish stemmer available, folding at least lets queries
+            // w/o diacritics match.
+            DocLanguage::Polish => TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(StopWordFilter::polish())
+                .filter(AsciiFoldingFilter),
+            DocLanguage::Japanese => TextAnalyzer::from(CjkTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser),
+        }
+    }
+
+    /// Runs `text` through this language's analyzer so it can be added to a field
+    /// regardless of the tokenizer configured for that field.
+    pub fn pre_tokenize(&self, text: &str) -> PreTokenizedString {
+        let mut tokens: Vec<Token> = Vec::new();
+        self.analyzer()
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.clone()));
+
+        PreTokenizedString {
+            text: text.to_string(),
+            tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DocLanguage;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            DocLanguage::detect(
+                "A few miles south of Soledad, the Salinas River drops in close to the hillside \
+                 bank and runs deep and green."
+            ),
+            Some(DocLanguage::English)
+        );
+        assert_eq!(
+            DocLanguage::detect(
+                "Ein paar Meilen südlich von Soledad fließt der Salinas dicht am Hang entlang \
+                 und ist tief und grün."
+            ),
+            Some(DocLanguage::German)
+        );
+        assert_eq!(
+            DocLanguage::detect(
+                "Kilka mil na południe od Soledad rzeka Salinas płynie blisko zbocza, \
+                 głęboka i zielona."
+            ),
+            Some(DocLanguage::Polish)
+        );
+        assert_eq!(
+            DocLanguage::detect("東京都は日本の首都であり、世界でも有数の大都市です。"),
+            Some(DocLanguage::Japanese)
+        );
+    }
+
+    #[test]
+    fn test_analyzers() {
+        let tokens = |lang: DocLanguage, text: &str| {
+            lang.pre_tokenize(text)
+                .tokens
+                .into_iter()
+                .map(|token| token.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            tokens(DocLanguage::German, "Die Häuser und die Bäume"),
+            vec!["haus", "baum"]
+        );
+        assert_eq!(
+            tokens(DocLanguage::Polish, "Zażółć gęślą jaźń"),
+            vec!["zazolc", "gesla", "jazn"]
+        );
+        assert_eq!(
+            tokens(DocLanguage::Japanese, "東京都"),
+            vec!["東京", "京都"]
+        );
+    }
+}
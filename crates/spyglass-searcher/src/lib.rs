@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::path::PathBuf;
@@ -5,13 +6,26 @@ use tantivy::schema::*;
 use thiserror::Error;
 use url::Url;
 
+pub mod autocomplete;
+pub mod cjk_tokenizer;
 pub mod client;
+pub mod code;
+pub mod cursor;
+pub mod dedup;
+pub mod explain;
+pub mod facets;
+pub mod hybrid;
+pub mod language;
 pub mod schema;
+pub mod sparse;
+pub mod spelling;
 pub mod stop_word_filter;
+use hybrid::{HybridOptions, SemanticHit};
 use schema::{DocFields, SearchDocument};
 
 mod query;
-pub mod similarity;
+pub mod query_parser;
+pub mod related;
 pub mod utils;
 
 type Score = f32;
@@ -38,9 +52,14 @@ impl QueryBoost {
     pub fn new(boost: Boost) -> Self {
         let value = &match boost {
             Boost::DocId(_) => 3.0,
+            Boost::DocIds(_) => 0.0,
             Boost::Favorite { .. } => 3.0,
             Boost::Tag(_) => 1.5,
+            Boost::ExcludeTag(_) => 0.0,
+            Boost::LastModified { .. } => 0.0,
             Boost::Url(_) => 3.0,
+            Boost::UrlPrefix(_) => 3.0,
+            Boost::UrlRegex(_) => 3.0,
             Boost::CustomField { .. } => 0.0,
         };
 
@@ -61,11 +80,56 @@ impl QueryBoost {
 #[derive(Clone, Serialize, Deserialize)]
 pub enum Boost {
     // If required is set to true, _only_ favorites will be searched.
-    Favorite { id: u64, required: bool },
+    Favorite {
+        id: u64,
+        required: bool,
+    },
     Url(String),
+    // Any URL starting w/ this prefix, e.g. `https://docs.rs/tokio/`
+    UrlPrefix(String),
+    // Any URL matching this regex, the whole URL has to match.
+    UrlRegex(String),
     DocId(String),
+    // Only considered in filters, limits results to any of these documents. No
+    // documents match an empty list.
+    DocIds(Vec<String>),
     Tag(u64),
-    CustomField { field_name: String, value: u64 },
+    // Only considered in filters, removes any document w/ this tag.
+    ExcludeTag(u64),
+    // Only considered in filters, limits results to documents last modified
+    // within this range (unix timestamps, in seconds).
+    LastModified {
+        after: Option<i64>,
+        before: Option<i64>,
+    },
+    CustomField {
+        field_name: String,
+        value: u64,
+    },
+}
+
+/// How search results should be ordered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// Most relevant results first
+    #[default]
+    Relevance,
+    /// Most recently modified documents first
+    NewestFirst,
+    /// Least recently modified documents first
+    OldestFirst,
+}
+
+/// Extra knobs for a search request
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    pub order: SortOrder,
+    /// When fewer than this many docs match the query exactly, the search is retried
+    /// w/ fuzzy term matching & a spelling correction is suggested. 0 disables both.
+    pub min_exact_hits: usize,
+    /// Documents don't need to contain any of the query terms to match, the terms
+    /// only add to the score. Filters & query operators still apply.
+    pub terms_optional: bool,
 }
 
 /// Contains stats & results for a search request
@@ -74,7 +138,16 @@ pub struct SearchQueryResult {
     pub wall_time_ms: u128,
     pub num_docs: u64,
     pub term_counts: usize,
+    /// Number of documents matching the query, not just the ones returned
+    pub total_hits: usize,
     pub documents: Vec<(Score, RetrievedDocument)>,
+    /// Facet counts over all the documents matched by the query
+    pub facets: facets::FacetCounts,
+    /// Whether the results came from the fuzzy (typo tolerant) retry
+    pub is_fuzzy: bool,
+    /// Spelling correction for the query, only looked up when too few documents
+    /// matched exactly.
+    pub suggestion: Option<String>,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -86,6 +159,8 @@ pub enum SearchError {
     ReadOnly,
     #[error("Index writer is deadlocked")]
     WriterLocked,
+    #[error("Unable to reach remote index: {0}")]
+    HttpError(#[from] reqwest::Error),
     #[error("{0}")]
     Other(#[from] anyhow::Error),
 }
@@ -103,7 +178,166 @@ pub trait SearchTrait {
         boosts: &[QueryBoost],
         num_results: usize,
         offset: usize,
+    ) -> SearchQueryResult {
+        self.search_with_order(
+            query,
+            filters,
+            boosts,
+            num_results,
+            offset,
+            SortOrder::Relevance,
+        )
+        .await
+    }
+    /// Runs a search against the index, ordering the results by `order`. Scores are
+    /// not computed when ordering by date.
+    async fn search_with_order(
+        &self,
+        query: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        num_results: usize,
+        offset: usize,
+        order: SortOrder,
+    ) -> SearchQueryResult {
+        let opts = SearchOptions {
+            order,
+            ..Default::default()
+        };
+        self.search_with_options(query, filters, boosts, num_results, offset, opts)
+            .await
+    }
+    /// Runs a search against the index w/ the provided `SearchOptions`
+    async fn search_with_options(
+        &self,
+        query: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        num_results: usize,
+        offset: usize,
+        opts: SearchOptions,
     ) -> SearchQueryResult;
+    /// Runs the query & a search limited to the `semantic` hits from a vector
+    /// search separately, then merges the two w/ rank fusion, or by date when
+    /// ordering by date. Documents only found through vector search are included
+    /// as long as they pass the filters.
+    #[allow(clippy::too_many_arguments)]
+    async fn search_hybrid(
+        &self,
+        query: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        semantic: &[SemanticHit],
+        num_results: usize,
+        offset: usize,
+        opts: SearchOptions,
+        hybrid_opts: HybridOptions,
+    ) -> SearchQueryResult {
+        if semantic.is_empty() {
+            return self
+                .search_with_options(query, filters, boosts, num_results, offset, opts)
+                .await;
+        }
+
+        let start_timer = std::time::Instant::now();
+        // Fusion needs the top candidates from both lists, paging is done afterwards.
+        // When ordering by date the index sorts the lexical matches instead, so newer
+        // matches outside the top relevance candidates aren't dropped.
+        let by_date = opts.order != SortOrder::Relevance;
+        let (num_candidates, lexical_opts) = if by_date {
+            (offset + num_results, opts)
+        } else {
+            let relevance = SearchOptions {
+                order: SortOrder::Relevance,
+                ..opts
+            };
+            (hybrid_opts.candidates.max(offset + num_results), relevance)
+        };
+        let mut lexical = self
+            .search_with_options(query, filters, boosts, num_candidates, 0, lexical_opts)
+            .await;
+
+        let mut semantic_filters = filters.to_vec();
+        semantic_filters.push(QueryBoost::new(Boost::DocIds(
+            semantic.iter().map(|hit| hit.doc_id.clone()).collect(),
+        )));
+        let candidate_opts = SearchOptions {
+            order: SortOrder::Relevance,
+            min_exact_hits: 0,
+            terms_optional: true,
+        };
+        let candidates = self
+            .search_with_options(
+                query,
+                &semantic_filters,
+                &[],
+                semantic.len(),
+                0,
+                candidate_opts,
+            )
+            .await;
+
+        // Back into vector search order
+        let mut candidates = candidates
+            .documents
+            .into_iter()
+            .map(|(_, doc)| (doc.doc_id.clone(), doc))
+            .collect::<std::collections::HashMap<_, _>>();
+        let semantic_docs = semantic
+            .iter()
+            .filter_map(|hit| {
+                candidates
+                    .remove(&hit.doc_id)
+                    .map(|doc| (hit.distance, doc))
+            })
+            .collect::<Vec<_>>();
+
+        // Semantic only matches add to the number of matching documents
+        let lexical_ids = lexical
+            .documents
+            .iter()
+            .map(|(_, doc)| doc.doc_id.clone())
+            .collect::<std::collections::HashSet<_>>();
+        let semantic_only = if by_date {
+            // Only the first page of lexical matches was retrieved, so count the
+            // semantic hits matching the query separately.
+            let exact_opts = SearchOptions {
+                order: SortOrder::Relevance,
+                min_exact_hits: 0,
+                terms_optional: false,
+            };
+            let matched = self
+                .search_with_options(query, &semantic_filters, &[], 1, 0, exact_opts)
+                .await
+                .total_hits;
+            semantic_docs.len().saturating_sub(matched)
+        } else {
+            semantic_docs
+                .iter()
+                .filter(|(_, doc)| !lexical_ids.contains(&doc.doc_id))
+                .count()
+        };
+        lexical.total_hits += semantic_only;
+
+        let merged = if by_date {
+            // Semantic hits are merged into the lexical matches by date
+            let mut merged = lexical.documents;
+            merged.extend(
+                semantic_docs
+                    .into_iter()
+                    .filter(|(_, doc)| !lexical_ids.contains(&doc.doc_id))
+                    .map(|(_, doc)| (0.0, doc)),
+            );
+            hybrid::sort_by_date(&mut merged, opts.order);
+            merged
+        } else {
+            hybrid::fuse(lexical.documents, semantic_docs, hybrid_opts.method)
+        };
+
+        lexical.documents = merged.into_iter().skip(offset).take(num_results).collect();
+        lexical.wall_time_ms = start_timer.elapsed().as_millis();
+        lexical
+    }
 }
 
 #[async_trait::async_trait]
@@ -127,7 +361,7 @@ pub trait WriteTrait {
 
 type SearcherResult<T> = Result<T, SearchError>;
 
-#[derive(Clone, Serialize, Debug)]
+#[derive(Clone, Default, Serialize, Debug)]
 pub struct RetrievedDocument {
     pub doc_id: String,
     pub domain: String,
@@ -136,12 +370,17 @@ pub struct RetrievedDocument {
     pub content: String,
     pub url: String,
     pub tags: Vec<u64>,
+    pub published: Option<DateTime<Utc>>,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// SPLADE expansion terms, if the document was indexed w/ them
+    #[serde(skip)]
+    pub sparse_terms: Vec<(String, f32)>,
 }
 
 // Helper method used to get the string value from a field
 fn field_to_string(doc: &Document, field: Field) -> String {
     doc.get_first(field)
-        .map(|x| x.as_text().unwrap_or_default())
+        .and_then(schema::value_text)
         .map(|x| x.to_string())
         .unwrap_or_default()
 }
@@ -151,6 +390,13 @@ fn field_to_u64vec(doc: &Document, field: Field) -> Vec<u64> {
     doc.get_all(field).filter_map(|val| val.as_u64()).collect()
 }
 
+// Helper method used to get the date value from a field
+fn field_to_date(doc: &Document, field: Field) -> Option<DateTime<Utc>> {
+    doc.get_first(field)
+        .and_then(|val| val.as_date())
+        .and_then(|date| DateTime::<Utc>::from_timestamp_micros(date.into_timestamp_micros()))
+}
+
 /// Helper method used to convert the provided document to a struct
 pub fn document_to_struct(doc: &Document) -> Option<RetrievedDocument> {
     let fields = DocFields::as_fields();
@@ -165,6 +411,9 @@ pub fn document_to_struct(doc: &Document) -> Option<RetrievedDocument> {
     let url = field_to_string(doc, fields.url);
     let content = field_to_string(doc, fields.content);
     let tags = field_to_u64vec(doc, fields.tags);
+    let published = field_to_date(doc, fields.published);
+    let last_modified = field_to_date(doc, fields.lastmodified);
+    let sparse_terms = sparse::parse_terms(&field_to_string(doc, fields.sparse));
 
     Some(RetrievedDocument {
         doc_id,
@@ -174,14 +423,22 @@ pub fn document_to_struct(doc: &Document) -> Option<RetrievedDocument> {
         content,
         url,
         tags,
+        published,
+        last_modified,
+        sparse_terms,
     })
 }
 
 #[cfg(test)]
 mod test {
     use crate::client::Searcher;
+    use crate::explain::ScoreSource;
+    use crate::hybrid::{HybridOptions, SemanticHit};
     use crate::schema::{DocFields, DocumentUpdate, SearchDocument, ToDocument};
-    use crate::{Boost, IndexBackend, QueryBoost, SearchTrait, WriteTrait};
+    use crate::sparse;
+    use crate::{
+        Boost, IndexBackend, QueryBoost, SearchOptions, SearchTrait, SortOrder, WriteTrait,
+    };
 
     async fn _build_test_index(searcher: &mut Searcher) {
         searcher
@@ -314,4 +571,649 @@ mod test {
         let results = searcher.search(query, &filters, &[], 5, 0).await;
         assert_eq!(results.documents.len(), 0);
     }
+
+    #[tokio::test]
+    pub async fn test_url_pattern_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let filters = vec![QueryBoost::new(Boost::UrlPrefix(
+            "https://en.wikipedia.org/".into(),
+        ))];
+        let results = searcher.search("salinas", &filters, &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(
+            results.documents[0].1.url,
+            "https://en.wikipedia.org/mice_and_men"
+        );
+
+        let filters = vec![QueryBoost::new(Boost::UrlRegex(".*/mice_and_men".into()))];
+        let results = searcher.search("salinas", &filters, &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 2);
+
+        // Invalid patterns don't match anything
+        let filters = vec![QueryBoost::new(Boost::UrlRegex("(".into()))];
+        let results = searcher.search("salinas", &filters, &[], 5, 0).await;
+        assert!(results.documents.is_empty());
+
+        // As a boost, matching docs are ranked first
+        for url in ["https://example.com/", "https://en.wikipedia.org/"] {
+            let boosts = vec![QueryBoost::new(Boost::UrlPrefix(url.into()))];
+            let results = searcher.search("salinas", &[], &boosts, 5, 0).await;
+            assert_eq!(results.documents.len(), 2);
+            assert!(results.documents[0].1.url.starts_with(url));
+        }
+
+        let docs = searcher
+            .search_by_query(
+                Some(vec![Boost::UrlPrefix("https://en.wikipedia.org/".into())]),
+                None,
+                &[],
+                &[],
+            )
+            .await;
+        assert_eq!(docs.len(), 2);
+    }
+
+    #[tokio::test]
+    pub async fn test_fuzzy_fallback_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let opts = SearchOptions {
+            min_exact_hits: 1,
+            ..Default::default()
+        };
+
+        let filters = vec![QueryBoost::new(Boost::Tag(2_u64))];
+        let results = searcher
+            .search_with_options("salinasd", &filters, &[], 5, 0, opts)
+            .await;
+        assert!(results.is_fuzzy);
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.suggestion, Some("salinas".to_string()));
+
+        // Enough exact matches, no need to fall back
+        let results = searcher
+            .search_with_options("salinas", &filters, &[], 5, 0, opts)
+            .await;
+        assert!(!results.is_fuzzy);
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.suggestion, None);
+    }
+
+    #[tokio::test]
+    pub async fn test_structured_query_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let results = searcher
+            .search("salinas -domain:example.com", &[], &[], 5, 0)
+            .await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.domain, "en.wikipedia.org");
+
+        let results = searcher.search("\"golden foothill\"", &[], &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 2);
+
+        let results = searcher.search("title:cheese", &[], &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+
+        let filters = vec![QueryBoost::new(Boost::ExcludeTag(2_u64))];
+        let results = searcher.search("mice", &filters, &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.tags, vec![1]);
+
+        // e.g. a tag no document has
+        let filters = vec![QueryBoost::new(Boost::DocIds(Vec::new()))];
+        let results = searcher.search("mice", &filters, &[], 5, 0).await;
+        assert!(results.documents.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_search_facets() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        // Facets are counted over every matched doc, not just the returned page
+        let results = searcher.search("salinas", &[], &[], 1, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.total_hits, 2);
+        assert_eq!(results.facets.tags.get(&1), Some(&1));
+        assert_eq!(results.facets.tags.get(&2), Some(&1));
+        assert_eq!(results.facets.domains.get("example.com"), Some(&1));
+        assert_eq!(results.facets.domains.get("en.wikipedia.org"), Some(&1));
+        assert_eq!(results.facets.domains.get("monster.com"), None);
+    }
+
+    #[tokio::test]
+    pub async fn test_hybrid_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let frankenstein = searcher.search("frankenstein", &[], &[], 1, 0).await;
+        let frankenstein_id = frankenstein.documents[0].1.doc_id.clone();
+        let semantic = vec![SemanticHit {
+            doc_id: frankenstein_id.clone(),
+            distance: 0.5,
+            segment: None,
+        }];
+
+        // Semantic only matches show up even though they don't contain the query
+        let results = searcher
+            .search_hybrid(
+                "salinas",
+                &[],
+                &[],
+                &semantic,
+                5,
+                0,
+                SearchOptions::default(),
+                HybridOptions::default(),
+            )
+            .await;
+        assert_eq!(results.documents.len(), 3);
+        assert_eq!(results.total_hits, 3);
+        assert!(results
+            .documents
+            .iter()
+            .any(|(_, doc)| doc.doc_id == frankenstein_id));
+
+        // ...as long as they pass the filters
+        let filters = vec![QueryBoost::new(Boost::Tag(2_u64))];
+        let results = searcher
+            .search_hybrid(
+                "salinas",
+                &filters,
+                &[],
+                &semantic,
+                5,
+                0,
+                SearchOptions::default(),
+                HybridOptions::default(),
+            )
+            .await;
+        assert_eq!(results.documents.len(), 1);
+        assert_ne!(results.documents[0].1.doc_id, frankenstein_id);
+    }
+
+    #[tokio::test]
+    pub async fn test_autocomplete() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let completions = searcher.autocomplete("Of mi", 5);
+        // Content terms are completed w/ the word they were stemmed from
+        assert_eq!(completions.terms, vec!["Of mice", "Of miles"]);
+        // Duplicate titles are only returned once
+        assert_eq!(completions.titles, vec!["Of Mice and Men"]);
+
+        let completions = searcher.autocomplete("frank", 5);
+        assert_eq!(completions.terms, vec!["frankenstein"]);
+        assert_eq!(
+            completions.titles,
+            vec!["Frankenstein: The Modern Prometheus"]
+        );
+
+        let completions = searcher.autocomplete("zzz", 5);
+        assert!(completions.terms.is_empty());
+        assert!(completions.titles.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_explain() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let filters = vec![QueryBoost::new(Boost::Tag(1_u64))];
+        let results = searcher.search("salinas river", &filters, &[], 1, 0).await;
+        let doc_id = results.documents[0].1.doc_id.clone();
+
+        let boosts = vec![QueryBoost::new(Boost::Tag(1_u64))];
+        let explanation = searcher
+            .explain(&doc_id, "salinas river", &[], &boosts)
+            .expect("Doc should exist");
+        assert!(explanation.matches);
+        assert!(explanation.components.iter().any(|component| {
+            component.source
+                == ScoreSource::Term {
+                    field: "content".into(),
+                    term: "salinas".into(),
+                }
+        }));
+        assert!(explanation.components.iter().any(|component| {
+            component.source
+                == ScoreSource::Phrase {
+                    field: "content".into(),
+                }
+        }));
+        assert!(explanation
+            .components
+            .iter()
+            .any(|component| component.source == ScoreSource::Tag(1)));
+
+        // The parts add up to the whole
+        let total = explanation
+            .components
+            .iter()
+            .map(|component| component.score)
+            .sum::<f32>();
+        assert!((total - explanation.score).abs() < 1e-4);
+
+        // Docs that don't match are still explained, they just don't score
+        let results = searcher.search("cheese", &[], &[], 1, 0).await;
+        let explanation = searcher
+            .explain(&results.documents[0].1.doc_id, "salinas", &[], &[])
+            .expect("Doc should exist");
+        assert!(!explanation.matches);
+        assert!(explanation.components.is_empty());
+
+        assert!(searcher.explain("missing", "salinas", &[], &[]).is_none());
+    }
+
+    #[tokio::test]
+    pub async fn test_more_like_this() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let filters = vec![QueryBoost::new(Boost::Tag(1_u64))];
+        let results = searcher.search("salinas", &filters, &[], 1, 0).await;
+        let doc_id = results.documents[0].1.doc_id.clone();
+
+        // The copy of the same text is most related, the doc itself is excluded
+        let related = searcher.more_like_this(&doc_id, &[], 5).await;
+        assert!(!related.is_empty());
+        assert_eq!(related[0].1.url, "https://en.wikipedia.org/mice_and_men");
+        assert!(related.iter().all(|(_, doc)| doc.doc_id != doc_id));
+
+        // Filters still apply
+        let related = searcher.more_like_this(&doc_id, &filters, 5).await;
+        assert!(related
+            .iter()
+            .all(|(_, doc)| doc.url != "https://en.wikipedia.org/mice_and_men"));
+
+        assert!(searcher.more_like_this("missing", &[], 5).await.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_more_like_this_multilingual() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+
+        for (url, content) in [
+            (
+                "https://example.com/ja/tokyo",
+                "東京都は日本の首都であり、世界でも有数の大都市です。",
+            ),
+            (
+                "https://example.com/ja/capital",
+                "東京は日本の首都で、世界の大都市の一つです。",
+            ),
+            (
+                "https://example.com/en/river",
+                "A few miles south of Soledad, the Salinas River drops in close",
+            ),
+            (
+                "https://example.com/en/valley",
+                "The valley side of the river is lined with willows and sycamores",
+            ),
+        ] {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "",
+                        domain: "example.com",
+                        url,
+                        content,
+                        tags: &[],
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        let results = searcher.search("首都", &[], &[], 5, 0).await;
+        let doc = results
+            .documents
+            .iter()
+            .find(|(_, doc)| doc.url == "https://example.com/ja/tokyo")
+            .expect("Expected the Tokyo doc");
+
+        // Terms are picked w/ the Japanese analyzer the content was indexed w/
+        let related = searcher.more_like_this(&doc.1.doc_id, &[], 5).await;
+        assert!(!related.is_empty());
+        assert_eq!(related[0].1.url, "https://example.com/ja/capital");
+    }
+
+    #[tokio::test]
+    pub async fn test_sparse_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+
+        let expansions = [
+            (
+                "river",
+                vec![("river", 2.0), ("water", 1.0), ("stream", 0.5)],
+            ),
+            (
+                "valley",
+                vec![("valley", 2.0), ("water", 0.5), ("hill", 1.5)],
+            ),
+            ("desert", vec![("sand", 2.0), ("dry", 1.0)]),
+        ];
+        for (name, terms) in &expansions {
+            let terms = terms
+                .iter()
+                .map(|(term, weight)| (term.to_string(), *weight))
+                .collect::<Vec<_>>();
+            let mut doc = DocumentUpdate {
+                doc_id: Some(name.to_string()),
+                title: name,
+                domain: "example.com",
+                url: &format!("https://example.com/{name}"),
+                content: "",
+                tags: &[],
+                published_at: None,
+                last_modified: None,
+            }
+            .to_document();
+            sparse::add_terms(&mut doc, &terms);
+            searcher.upsert(&doc).await.expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        // Ranked by the dot product of the expansions
+        let query = vec![("water".to_string(), 1.0), ("stream".to_string(), 1.0)];
+        let results = searcher.search_sparse(&query, &[], 5).await;
+        let ids = results
+            .iter()
+            .map(|(_, doc_id)| doc_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["river", "valley"]);
+        assert!(results[0].0 > results[1].0);
+
+        // Expansion terms are kept w/ the doc
+        let doc = searcher.get("valley").await.expect("Doc should exist");
+        assert_eq!(doc.sparse_terms[0], ("valley".to_string(), 2.0));
+
+        let filters = vec![QueryBoost::new(Boost::DocId("valley".into()))];
+        let results = searcher.search_sparse(&query, &filters, 5).await;
+        assert_eq!(results.len(), 1);
+        assert!(searcher.search_sparse(&[], &[], 5).await.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_multilingual_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+
+        for (url, content) in [
+            (
+                "https://example.com/de",
+                "Die alten Häuser stehen am Fluss und die Bäume wachsen hoch über den Dächern \
+                 der kleinen Stadt.",
+            ),
+            (
+                "https://example.com/ja",
+                "東京都は日本の首都であり、世界でも有数の大都市です。",
+            ),
+        ] {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "",
+                        domain: "example.com",
+                        url,
+                        content,
+                        tags: &[],
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        // Stemmed w/ the German analyzer
+        let results = searcher.search("haus", &[], &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.url, "https://example.com/de");
+        assert!(results.documents[0]
+            .1
+            .content
+            .starts_with("Die alten Häuser"));
+
+        let results = searcher.search("首都", &[], &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.url, "https://example.com/ja");
+    }
+
+    #[tokio::test]
+    pub async fn test_code_search() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+
+        for (url, content) in [
+            (
+                "file://localhost/src/query.rs",
+                "pub fn build_query(index: &Index) -> BooleanQuery {\n    \
+                 let terms = terms_for_field(index);\n    BooleanQuery::new(terms)\n}",
+            ),
+            (
+                "file://localhost/src/search.rs",
+                "fn search(index: &Index) {\n    let query = build_query(index);\n    \
+                 let fuzzy = build_query(index);\n    let exact = build_query(index);\n    \
+                 run(query, fuzzy, exact)\n}",
+            ),
+        ] {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "",
+                        domain: "localhost",
+                        url,
+                        content,
+                        tags: &[],
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        // The definition ranks above the places it's used
+        for query in ["build_query", "buildQuery"] {
+            let results = searcher.search(query, &[], &[], 5, 0).await;
+            assert_eq!(results.documents.len(), 2);
+            assert_eq!(results.documents[0].1.url, "file://localhost/src/query.rs");
+        }
+
+        // Identifiers are split into words, which aren't stemmed
+        let results = searcher.search("boolean", &[], &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.url, "file://localhost/src/query.rs");
+    }
+
+    #[tokio::test]
+    pub async fn test_hybrid_sort_by_date() {
+        use chrono::{TimeZone, Utc};
+
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+
+        let mut doc_ids = Vec::new();
+        for (url, content, year) in [
+            (
+                "https://example.com/old",
+                "Salinas, Salinas, the Salinas River drops in close to the hillside",
+                2020,
+            ),
+            (
+                "https://example.com/new",
+                "A few miles south of Soledad, the Salinas River drops in close",
+                2023,
+            ),
+            (
+                "https://example.com/semantic",
+                "The river runs deep & green in the valley",
+                2021,
+            ),
+        ] {
+            let doc_id = searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "River",
+                        domain: "example.com",
+                        url,
+                        content,
+                        tags: &[],
+                        published_at: None,
+                        last_modified: Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single(),
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+            doc_ids.push(doc_id);
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        let semantic = vec![SemanticHit {
+            doc_id: doc_ids[2].clone(),
+            distance: 0.1,
+            segment: None,
+        }];
+        // The newest match is outside the relevance candidates
+        let hybrid_opts = HybridOptions {
+            candidates: 1,
+            ..Default::default()
+        };
+        let opts = SearchOptions {
+            order: SortOrder::NewestFirst,
+            ..Default::default()
+        };
+        let results = searcher
+            .search_hybrid("salinas", &[], &[], &semantic, 5, 0, opts, hybrid_opts)
+            .await;
+        let urls = results
+            .documents
+            .iter()
+            .map(|(_, doc)| doc.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/new",
+                "https://example.com/semantic",
+                "https://example.com/old"
+            ]
+        );
+        assert_eq!(results.total_hits, 3);
+
+        // Paging happens after the semantic hits are merged in
+        let results = searcher
+            .search_hybrid("salinas", &[], &[], &semantic, 1, 1, opts, hybrid_opts)
+            .await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.url, "https://example.com/semantic");
+    }
+
+    #[tokio::test]
+    pub async fn test_date_filter_and_sort() {
+        use chrono::{TimeZone, Utc};
+
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+
+        for (url, year) in [
+            ("https://example.com/old", 2020),
+            ("https://example.com/new", 2023),
+        ] {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "Salinas river",
+                        domain: "example.com",
+                        url,
+                        content: "A few miles south of Soledad, the Salinas River drops in close",
+                        tags: &[],
+                        published_at: None,
+                        last_modified: Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single(),
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        let results = searcher
+            .search_with_order("salinas", &[], &[], 5, 0, SortOrder::NewestFirst)
+            .await;
+        assert_eq!(results.documents.len(), 2);
+        assert_eq!(results.documents[0].1.url, "https://example.com/new");
+        assert_eq!(
+            results.documents[0].1.last_modified,
+            Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).single()
+        );
+
+        let results = searcher
+            .search_with_order("salinas", &[], &[], 5, 0, SortOrder::OldestFirst)
+            .await;
+        assert_eq!(results.documents[0].1.url, "https://example.com/old");
+
+        let after = Utc
+            .with_ymd_and_hms(2022, 1, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp();
+        let filters = vec![QueryBoost::new(Boost::LastModified {
+            after: Some(after),
+            before: None,
+        })];
+        let results = searcher.search("salinas", &filters, &[], 5, 0).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.url, "https://example.com/new");
+    }
 }
@@ -1,14 +1,42 @@
-use tantivy::query::{BooleanQuery, BoostQuery, Occur, PhraseQuery, Query, TermQuery};
+use serde_json::json;
+use std::ops::Bound;
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, FuzzyTermQuery, Occur, PhraseQuery, Query,
+    RangeQuery, RegexQuery, TermQuery,
+};
 use tantivy::tokenizer::*;
-use tantivy::Score;
 use tantivy::{schema::*, Index};
+use tantivy::{DateTime, Score};
 
-use crate::schema::SearchDocument;
+use crate::code;
+use crate::explain::ScoreSource;
+use crate::language::DocLanguage;
+use crate::query_parser::{parse_query, ParsedQuery, QueryPart};
+use crate::schema::{to_tantivy_date, SearchDocument};
 use crate::{Boost, QueryBoost};
 
 use super::DocFields;
 
 type QueryVec = Vec<(Occur, Box<dyn Query>)>;
+/// Scored parts of a query & where they came from
+pub type ScoredClauses = Vec<(ScoreSource, Box<dyn Query>)>;
+
+// Keeps a copy of a scored clause around, so we can explain how a doc was scored
+fn _scored(
+    scored: &mut ScoredClauses,
+    source: ScoreSource,
+    query: Box<dyn Query>,
+) -> Box<dyn Query> {
+    scored.push((source, query.box_clone()));
+    query
+}
+
+fn _term_source(field: &str, term: &Term) -> ScoreSource {
+    ScoreSource::Term {
+        field: field.to_string(),
+        term: term.as_str().unwrap_or_default().to_string(),
+    }
+}
 
 fn _boosted_term(term: Term, boost: Score) -> Box<BoostQuery> {
     Box::new(BoostQuery::new(
@@ -21,6 +49,49 @@ fn _boosted_term(term: Term, boost: Score) -> Box<BoostQuery> {
     ))
 }
 
+// Allowed edit distance for a fuzzy term, based on its length (same as the
+// Elasticsearch `AUTO` fuzziness).
+fn _fuzzy_distance(term: &Term) -> u8 {
+    match term.as_str().map(|text| text.chars().count()).unwrap_or(0) {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+fn _fuzzy_term(term: Term, boost: Score) -> Option<Box<BoostQuery>> {
+    let distance = _fuzzy_distance(&term);
+    if distance == 0 {
+        return None;
+    }
+
+    Some(Box::new(BoostQuery::new(
+        Box::new(FuzzyTermQuery::new(term, distance, true)),
+        boost,
+    )))
+}
+
+// Matches the URLs a `Url`, `UrlPrefix` or `UrlRegex` boost applies to, `None` for
+// any other boost. Patterns have to match the whole URL, invalid ones match nothing.
+fn _url_query(field: Field, boost: &Boost, value: Score) -> Option<Box<BoostQuery>> {
+    let pattern = match boost {
+        Boost::Url(url) => return Some(_boosted_term(Term::from_field_text(field, url), value)),
+        Boost::UrlPrefix(prefix) => format!("{}.*", regex::escape(prefix)),
+        Boost::UrlRegex(pattern) => pattern.clone(),
+        _ => return None,
+    };
+
+    let query: Box<dyn Query> = match RegexQuery::from_pattern(&pattern, field) {
+        Ok(query) => Box::new(query),
+        Err(err) => {
+            log::warn!("Invalid URL pattern `{}`: {}", pattern, err);
+            Box::new(EmptyQuery)
+        }
+    };
+
+    Some(Box::new(BoostQuery::new(query, value)))
+}
+
 fn _boosted_phrase(terms: Vec<(usize, Term)>, boost: Score) -> Box<BoostQuery> {
     let slop = terms
         .last()
@@ -41,6 +112,30 @@ pub struct QueryOptions {
     title_boost: f32,
     /// full phrase matches in the title
     title_phrase_boost: f32,
+    /// single term matches in the symbols defined in code files
+    symbol_boost: f32,
+    /// full phrase matches in the symbols, e.g. `build query` for `build_query`
+    symbol_phrase_boost: f32,
+    /// also match terms within a small edit distance of the query terms
+    fuzzy: bool,
+    /// query terms only add to the score, documents don't need to contain any
+    terms_optional: bool,
+}
+
+impl QueryOptions {
+    /// Same weights, but also matching terms w/ typos in them
+    pub fn fuzzy() -> Self {
+        QueryOptions {
+            fuzzy: true,
+            ..Default::default()
+        }
+    }
+
+    /// Leaves matching up to filters & operators, query terms only add to the score
+    pub fn with_terms_optional(mut self, terms_optional: bool) -> Self {
+        self.terms_optional = terms_optional;
+        self
+    }
 }
 
 impl Default for QueryOptions {
@@ -51,6 +146,11 @@ impl Default for QueryOptions {
             // weight title matches a little more
             title_boost: 2.0,
             title_phrase_boost: 2.5,
+            // a symbol match is likely the definition of what's being searched for
+            symbol_boost: 3.0,
+            symbol_phrase_boost: 4.0,
+            fuzzy: false,
+            terms_optional: false,
         }
     }
 }
@@ -65,23 +165,56 @@ pub fn build_query(
     // title/content boost options
     opts: QueryOptions,
 ) -> (usize, BooleanQuery) {
+    let (term_count, query, _) = build_scored_query(index, query_string, filters, boosts, opts);
+    (term_count, query)
+}
+
+/// Same as `build_query`, but also returns each of the scored clauses that make up
+/// the query, labeled w/ the part of the query they came from.
+pub fn build_scored_query(
+    index: &Index,
+    query_string: &str,
+    filters: &[QueryBoost],
+    boosts: &[QueryBoost],
+    opts: QueryOptions,
+) -> (usize, BooleanQuery, ScoredClauses) {
     let schema = index.schema();
     let tokenizers = index.tokenizers();
     let fields = DocFields::as_fields();
 
-    let content_terms = terms_for_field(&schema, tokenizers, query_string, fields.content);
-    let title_terms = terms_for_field(&schema, tokenizers, query_string, fields.title);
+    // Callers are expected to validate the query beforehand, anything that
+    // doesn't parse is searched for as is.
+    let parsed =
+        parse_query(query_string).unwrap_or_else(|_| ParsedQuery::from_terms(query_string));
+    let free_text = parsed.terms();
+
+    let content_terms = terms_for_field(&schema, tokenizers, &free_text, fields.content);
+    let title_terms = terms_for_field(&schema, tokenizers, &free_text, fields.title);
+    let symbol_terms = terms_for_field(&schema, tokenizers, &free_text, fields.symbols);
+    let language_terms = language_terms_for_content(&free_text, &content_terms);
 
-    let term_count = content_terms.len();
+    let mut term_count = content_terms.len();
+    let has_text = !content_terms.is_empty() || !title_terms.is_empty() || !symbol_terms.is_empty();
 
     let mut term_query: QueryVec = Vec::new();
+    let mut scored: ScoredClauses = Vec::new();
 
     // Boost exact matches to the full query string
     if content_terms.len() > 1 {
         // boosting phrases relative to the number of segments in a
         // continuous phrase
         let boost = opts.content_phrase_boost * content_terms.len() as f32;
-        term_query.push((Occur::Should, _boosted_phrase(content_terms.clone(), boost)));
+        let phrase = ScoreSource::Phrase {
+            field: "content".into(),
+        };
+        term_query.push((
+            Occur::Should,
+            _scored(
+                &mut scored,
+                phrase,
+                _boosted_phrase(content_terms.clone(), boost),
+            ),
+        ));
     }
 
     // Boost exact matches to the full query string
@@ -90,58 +223,273 @@ pub fn build_query(
         // continuous phrase, base score higher for title
         // than content
         let boost = opts.title_phrase_boost * title_terms.len() as f32;
-        term_query.push((Occur::Should, _boosted_phrase(title_terms.clone(), boost)));
+        let phrase = ScoreSource::Phrase {
+            field: "title".into(),
+        };
+        term_query.push((
+            Occur::Should,
+            _scored(
+                &mut scored,
+                phrase,
+                _boosted_phrase(title_terms.clone(), boost),
+            ),
+        ));
     }
 
     for (_position, term) in content_terms {
-        term_query.push((Occur::Should, _boosted_term(term, opts.content_boost)));
+        if opts.fuzzy {
+            // Exact matches still score higher than typos
+            if let Some(query) = _fuzzy_term(term.clone(), opts.content_boost * 0.5) {
+                let fuzzy = ScoreSource::FuzzyTerm {
+                    field: "content".into(),
+                    term: term.as_str().unwrap_or_default().to_string(),
+                };
+                term_query.push((Occur::Should, _scored(&mut scored, fuzzy, query)));
+            }
+        }
+        let source = _term_source("content", &term);
+        term_query.push((
+            Occur::Should,
+            _scored(&mut scored, source, _boosted_term(term, opts.content_boost)),
+        ));
+    }
+
+    // Content in other languages was indexed w/ that language's analyzer, so match
+    // against the query as analyzed by each of them too.
+    for terms in language_terms {
+        if terms.len() > 1 {
+            let boost = opts.content_phrase_boost * terms.len() as f32;
+            let phrase = ScoreSource::Phrase {
+                field: "content".into(),
+            };
+            term_query.push((
+                Occur::Should,
+                _scored(&mut scored, phrase, _boosted_phrase(terms.clone(), boost)),
+            ));
+        }
+
+        for (_position, term) in terms {
+            let source = _term_source("content", &term);
+            term_query.push((
+                Occur::Should,
+                _scored(&mut scored, source, _boosted_term(term, opts.content_boost)),
+            ));
+        }
     }
 
     for (_position, term) in title_terms {
-        term_query.push((Occur::Should, _boosted_term(term, opts.title_boost)));
+        if opts.fuzzy {
+            if let Some(query) = _fuzzy_term(term.clone(), opts.title_boost * 0.5) {
+                let fuzzy = ScoreSource::FuzzyTerm {
+                    field: "title".into(),
+                    term: term.as_str().unwrap_or_default().to_string(),
+                };
+                term_query.push((Occur::Should, _scored(&mut scored, fuzzy, query)));
+            }
+        }
+        let source = _term_source("title", &term);
+        term_query.push((
+            Occur::Should,
+            _scored(&mut scored, source, _boosted_term(term, opts.title_boost)),
+        ));
+    }
+
+    if symbol_terms.len() > 1 {
+        let boost = opts.symbol_phrase_boost * symbol_terms.len() as f32;
+        let phrase = ScoreSource::Phrase {
+            field: "symbols".into(),
+        };
+        term_query.push((
+            Occur::Should,
+            _scored(
+                &mut scored,
+                phrase,
+                _boosted_phrase(symbol_terms.clone(), boost),
+            ),
+        ));
+    }
+
+    for (_position, term) in symbol_terms {
+        let source = _term_source("symbols", &term);
+        term_query.push((
+            Occur::Should,
+            _scored(&mut scored, source, _boosted_term(term, opts.symbol_boost)),
+        ));
     }
 
     // Boost fields that happen to have a value, such as
     // - Tags that might be represented by search terms (e.g. "repository" or "file")
     // - Certain URLs or documents we want to focus on
     for boost in boosts {
-        let term = match &boost.field {
-            Boost::DocId(doc_id) => {
+        let (source, term) = match &boost.field {
+            Boost::DocId(doc_id) => (
+                ScoreSource::DocId(doc_id.clone()),
                 // Originally boosted to 3.0
-                _boosted_term(Term::from_field_text(fields.id, doc_id), boost.value)
-            }
+                _boosted_term(Term::from_field_text(fields.id, doc_id), boost.value),
+            ),
             // Only considered in filters
-            Boost::Favorite { .. } => continue,
-            Boost::Tag(tag_id) => {
+            Boost::Favorite { .. }
+            | Boost::DocIds(_)
+            | Boost::ExcludeTag(_)
+            | Boost::LastModified { .. } => continue,
+            Boost::Tag(tag_id) => (
+                ScoreSource::Tag(*tag_id),
                 // Defaults to 1.5
-                _boosted_term(Term::from_field_u64(fields.tags, *tag_id), boost.value)
-            }
-            // todo: handle regex/prefixes?
-            Boost::Url(url) => {
-                // Originally boosted to 3.0
-                _boosted_term(Term::from_field_text(fields.url, url), boost.value)
+                _boosted_term(Term::from_field_u64(fields.tags, *tag_id), boost.value),
+            ),
+            Boost::Url(url) | Boost::UrlPrefix(url) | Boost::UrlRegex(url) => {
+                match _url_query(fields.url, &boost.field, boost.value) {
+                    // Originally boosted to 3.0
+                    Some(query) => (ScoreSource::Url(url.clone()), query),
+                    None => continue,
+                }
             }
             Boost::CustomField { field_name, value } => {
                 if let Some((field, _)) = schema.find_field(field_name) {
-                    _boosted_term(Term::from_field_u64(field, *value), boost.value)
+                    (
+                        ScoreSource::CustomField {
+                            field: field_name.clone(),
+                            value: *value,
+                        },
+                        _boosted_term(Term::from_field_u64(field, *value), boost.value),
+                    )
                 } else {
                     continue;
                 }
             }
         };
 
-        term_query.push((Occur::Should, term));
+        term_query.push((Occur::Should, _scored(&mut scored, source, term)));
     }
 
-    // Must hit at least one of the terms
-    let mut combined: QueryVec = vec![(Occur::Must, Box::new(BooleanQuery::new(term_query)))];
+    // Operators, phrases & exclusions from the structured query
+    let mut operator_query: QueryVec = Vec::new();
+    for clause in &parsed.clauses {
+        let occur = if clause.negated {
+            Occur::MustNot
+        } else {
+            Occur::Must
+        };
+
+        let query: Box<dyn Query> = match &clause.part {
+            QueryPart::Term(term) => {
+                // Only excluded terms are handled here, the rest are part of the
+                // free text above.
+                if !clause.negated {
+                    continue;
+                }
+
+                match _text_query(
+                    &schema,
+                    tokenizers,
+                    term,
+                    &[fields.content, fields.title],
+                    &opts,
+                ) {
+                    Some(query) => query,
+                    None => continue,
+                }
+            }
+            QueryPart::Phrase(phrase) => {
+                if !clause.negated {
+                    term_count +=
+                        terms_for_field(&schema, tokenizers, phrase, fields.content).len();
+                }
+
+                match _text_query(
+                    &schema,
+                    tokenizers,
+                    phrase,
+                    &[fields.content, fields.title],
+                    &opts,
+                ) {
+                    Some(query) => query,
+                    None => continue,
+                }
+            }
+            QueryPart::Title(title) => {
+                match _text_query(&schema, tokenizers, title, &[fields.title], &opts) {
+                    Some(query) => query,
+                    None => continue,
+                }
+            }
+            // Scored (unlike filters) so that operator only queries don't get
+            // dropped w/ a zero score.
+            QueryPart::Domain(domain) => {
+                _boosted_term(Term::from_field_text(fields.domain, domain), 1.0)
+            }
+            QueryPart::Url(url) => _boosted_term(Term::from_field_text(fields.url, url), 1.0),
+            // Resolved into tag filters by the caller
+            QueryPart::Lens(_) | QueryPart::Tag { .. } | QueryPart::FileType(_) => continue,
+            QueryPart::Before(date) => Box::new(RangeQuery::new_date_bounds(
+                fields.lastmodified,
+                Bound::Unbounded,
+                Bound::Excluded(to_tantivy_date(*date)),
+            )),
+            QueryPart::After(date) => Box::new(RangeQuery::new_date_bounds(
+                fields.lastmodified,
+                Bound::Included(to_tantivy_date(*date)),
+                Bound::Unbounded,
+            )),
+        };
+
+        // Exclusions never add to the score
+        let query = if clause.negated {
+            query
+        } else {
+            _scored(
+                &mut scored,
+                ScoreSource::Operator(clause.to_string()),
+                query,
+            )
+        };
+        operator_query.push((occur, query));
+    }
+
+    let has_operators = operator_query
+        .iter()
+        .any(|(occur, _)| *occur == Occur::Must);
+    let term_query: Box<dyn Query> = Box::new(BooleanQuery::new(term_query));
+    let mut combined: QueryVec = if opts.terms_optional {
+        // Filters & operators decide what matches, terms only add to the score
+        vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::Should, term_query),
+        ]
+    } else if has_text || operator_query.is_empty() {
+        // Must hit at least one of the terms
+        vec![(Occur::Must, term_query)]
+    } else if has_operators {
+        // Operators decide what matches, terms only add to the score
+        vec![(Occur::Should, term_query)]
+    } else {
+        // Only exclusions, so start w/ everything and remove from there.
+        vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::Should, term_query),
+        ]
+    };
+    combined.extend(operator_query);
+
     // Must have one of these, will filter out stuff that doesn't
     for filter in filters {
-        let term = match &filter.field {
+        let term: Box<dyn Query> = match &filter.field {
             Boost::DocId(doc_id) => {
                 // Originally boosted to 3.0
                 _boosted_term(Term::from_field_text(fields.id, doc_id), 0.0)
             }
+            Boost::DocIds(doc_ids) => {
+                let any_doc: QueryVec = doc_ids
+                    .iter()
+                    .map(|doc_id| -> (Occur, Box<dyn Query>) {
+                        (
+                            Occur::Should,
+                            _boosted_term(Term::from_field_text(fields.id, doc_id), 0.0),
+                        )
+                    })
+                    .collect();
+                Box::new(BooleanQuery::new(any_doc))
+            }
             Boost::Favorite { id, required } => {
                 let occur = if *required {
                     Occur::Must
@@ -149,9 +497,10 @@ pub fn build_query(
                     Occur::Should
                 };
 
+                let favorite = _boosted_term(Term::from_field_u64(fields.tags, *id), 3.0);
                 combined.push((
                     occur,
-                    _boosted_term(Term::from_field_u64(fields.tags, *id), 3.0),
+                    _scored(&mut scored, ScoreSource::Favorite(*id), favorite),
                 ));
 
                 continue;
@@ -160,10 +509,34 @@ pub fn build_query(
                 // Defaults to 1.5
                 _boosted_term(Term::from_field_u64(fields.tags, *tag_id), 0.0)
             }
-            // todo: handle regex/prefixes?
-            Boost::Url(url) => {
-                // Originally boosted to 3.0
-                _boosted_term(Term::from_field_text(fields.url, url), 0.0)
+            Boost::ExcludeTag(tag_id) => {
+                combined.push((
+                    Occur::MustNot,
+                    _boosted_term(Term::from_field_u64(fields.tags, *tag_id), 0.0),
+                ));
+                continue;
+            }
+            Boost::LastModified { after, before } => {
+                let lower = after
+                    .map(|ts| Bound::Included(DateTime::from_timestamp_secs(ts)))
+                    .unwrap_or(Bound::Unbounded);
+                let upper = before
+                    .map(|ts| Bound::Excluded(DateTime::from_timestamp_secs(ts)))
+                    .unwrap_or(Bound::Unbounded);
+                Box::new(BoostQuery::new(
+                    Box::new(RangeQuery::new_date_bounds(
+                        fields.lastmodified,
+                        lower,
+                        upper,
+                    )),
+                    0.0,
+                ))
+            }
+            Boost::Url(_) | Boost::UrlPrefix(_) | Boost::UrlRegex(_) => {
+                match _url_query(fields.url, &filter.field, 0.0) {
+                    Some(query) => query,
+                    None => continue,
+                }
             }
             Boost::CustomField { field_name, value } => {
                 if let Some((field, _)) = schema.find_field(field_name) {
@@ -177,13 +550,343 @@ pub fn build_query(
         combined.push((Occur::Must, term));
     }
 
-    (term_count, BooleanQuery::new(combined))
+    (term_count, BooleanQuery::new(combined), scored)
+}
+
+// Builds a query matching `text` in any of the `fields`. Multiple terms are
+// matched as an exact phrase.
+fn _text_query(
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    text: &str,
+    fields: &[Field],
+    opts: &QueryOptions,
+) -> Option<Box<dyn Query>> {
+    let doc_fields = DocFields::as_fields();
+    let mut queries: QueryVec = Vec::new();
+    for field in fields {
+        let (term_boost, phrase_boost) = if *field == doc_fields.title {
+            (opts.title_boost, opts.title_phrase_boost)
+        } else {
+            (opts.content_boost, opts.content_phrase_boost)
+        };
+
+        let terms = terms_for_field(schema, tokenizers, text, *field);
+        let mut analyzed = vec![terms.clone()];
+        if *field == doc_fields.content {
+            analyzed.extend(language_terms_for_content(text, &terms));
+        }
+
+        for mut terms in analyzed {
+            let query: Box<dyn Query> = match terms.len() {
+                0 => continue,
+                1 => _boosted_term(terms.remove(0).1, term_boost),
+                num_terms => Box::new(BoostQuery::new(
+                    Box::new(PhraseQuery::new_with_offset(terms)),
+                    phrase_boost * num_terms as f32,
+                )),
+            };
+            queries.push((Occur::Should, query));
+        }
+    }
+
+    if queries.is_empty() {
+        None
+    } else {
+        Some(Box::new(BooleanQuery::new(queries)))
+    }
+}
+
+// Analyzes `text` w/ each of the non-default language analyzers & the code
+// analyzer, returning the content terms for each analysis that differs from the
+// `default_terms`.
+fn language_terms_for_content(
+    text: &str,
+    default_terms: &[(usize, Term)],
+) -> Vec<Vec<(usize, Term)>> {
+    let content = DocFields::as_fields().content;
+    let analyzers = DocLanguage::ALL
+        .into_iter()
+        .filter(|lang| *lang != DocLanguage::English)
+        .map(|lang| lang.analyzer())
+        .chain(std::iter::once(code::analyzer()));
+
+    let mut analyzed: Vec<Vec<(usize, Term)>> = Vec::new();
+    for analyzer in analyzers {
+        let mut terms = Vec::new();
+        analyzer.token_stream(text).process(&mut |token| {
+            terms.push((token.position, Term::from_field_text(content, &token.text)));
+        });
+
+        if !terms.is_empty() && terms != default_terms && !analyzed.contains(&terms) {
+            analyzed.push(terms);
+        }
+    }
+
+    analyzed
+}
+
+/// Builds the equivalent of `build_query` using the Elasticsearch query DSL, for
+/// use with remote (HTTP) indices. Text analysis is left up to the remote index
+/// so the term count is based on whitespace separated words.
+pub fn build_remote_query(
+    query_string: &str,
+    // Applied filters
+    filters: &[QueryBoost],
+    // Applied boosts,
+    boosts: &[QueryBoost],
+    // title/content boost options
+    opts: QueryOptions,
+) -> (usize, serde_json::Value) {
+    let parsed =
+        parse_query(query_string).unwrap_or_else(|_| ParsedQuery::from_terms(query_string));
+    let free_text = parsed.terms();
+    let mut term_count = free_text.split_whitespace().count();
+    let mut term_query = Vec::new();
+
+    // Boost exact matches to the full query string, relative to the number of
+    // segments in a continuous phrase.
+    if term_count > 1 {
+        let slop = (term_count as i32 - 3).clamp(0, 3);
+        term_query.push(json!({
+            "match_phrase": { "content": {
+                "query": free_text,
+                "slop": slop,
+                "boost": opts.content_phrase_boost * term_count as f32
+            }}
+        }));
+        term_query.push(json!({
+            "match_phrase": { "title": {
+                "query": free_text,
+                "slop": slop,
+                "boost": opts.title_phrase_boost * term_count as f32
+            }}
+        }));
+    }
+
+    if term_count > 0 {
+        let fuzziness = if opts.fuzzy { "AUTO" } else { "0" };
+        term_query.push(json!({
+            "match": { "content": {
+                "query": free_text,
+                "boost": opts.content_boost,
+                "fuzziness": fuzziness
+            }}
+        }));
+        term_query.push(json!({
+            "match": { "title": {
+                "query": free_text,
+                "boost": opts.title_boost,
+                "fuzziness": fuzziness
+            }}
+        }));
+    }
+
+    for boost in boosts {
+        let term = match &boost.field {
+            Boost::DocId(doc_id) => _remote_term("id", json!(doc_id), boost.value),
+            // Only considered in filters
+            Boost::Favorite { .. }
+            | Boost::DocIds(_)
+            | Boost::ExcludeTag(_)
+            | Boost::LastModified { .. } => continue,
+            Boost::Tag(tag_id) => _remote_term("tags", json!(tag_id), boost.value),
+            Boost::Url(url) => _remote_term("url", json!(url), boost.value),
+            Boost::UrlPrefix(prefix) => {
+                json!({ "prefix": { "url": { "value": prefix, "boost": boost.value } } })
+            }
+            Boost::UrlRegex(pattern) => {
+                json!({ "regexp": { "url": { "value": pattern, "boost": boost.value } } })
+            }
+            Boost::CustomField { field_name, value } => {
+                _remote_term(field_name, json!(value), boost.value)
+            }
+        };
+
+        term_query.push(term);
+    }
+
+    let mut must = Vec::new();
+    let mut must_not = Vec::new();
+    let mut should = Vec::new();
+    let mut filter = Vec::new();
+
+    // Operators, phrases & exclusions from the structured query
+    let mut has_operators = false;
+    for clause in &parsed.clauses {
+        let (query, scored) = match &clause.part {
+            // Only excluded terms are handled here
+            QueryPart::Term(term) if clause.negated => (
+                json!({ "multi_match": { "query": term, "fields": ["content", "title"] } }),
+                true,
+            ),
+            QueryPart::Term(_) => continue,
+            QueryPart::Phrase(phrase) => {
+                if !clause.negated {
+                    term_count += phrase.split_whitespace().count();
+                }
+                (
+                    json!({ "multi_match": {
+                        "query": phrase,
+                        "type": "phrase",
+                        "fields": [
+                            format!("content^{}", opts.content_phrase_boost),
+                            format!("title^{}", opts.title_phrase_boost)
+                        ]
+                    }}),
+                    true,
+                )
+            }
+            QueryPart::Title(title) => (
+                json!({ "match_phrase": { "title": { "query": title, "boost": opts.title_boost } } }),
+                true,
+            ),
+            QueryPart::Domain(domain) => (json!({ "term": { "domain": domain } }), false),
+            QueryPart::Url(url) => (json!({ "term": { "url": url } }), false),
+            // Resolved into tag filters by the caller
+            QueryPart::Lens(_) | QueryPart::Tag { .. } | QueryPart::FileType(_) => continue,
+            QueryPart::Before(date) => (
+                json!({ "range": { "lastmodified": { "lt": date.to_rfc3339() } } }),
+                false,
+            ),
+            QueryPart::After(date) => (
+                json!({ "range": { "lastmodified": { "gte": date.to_rfc3339() } } }),
+                false,
+            ),
+        };
+
+        if clause.negated {
+            must_not.push(query);
+        } else if scored {
+            has_operators = true;
+            must.push(query);
+        } else {
+            has_operators = true;
+            filter.push(query);
+        }
+    }
+
+    if !term_query.is_empty() {
+        let term_query = json!({ "bool": { "should": term_query, "minimum_should_match": 1 } });
+        if (term_count > 0 || !has_operators) && !opts.terms_optional {
+            // Must hit at least one of the terms
+            must.push(term_query);
+        } else {
+            // Operators decide what matches, terms only add to the score
+            should.push(term_query);
+        }
+    }
+
+    // Unmatched docs would otherwise end up w/ a zero score & get dropped
+    if opts.terms_optional || (must.is_empty() && filter.is_empty()) {
+        must.push(json!({ "match_all": {} }));
+    }
+
+    for boost in filters {
+        let term = match &boost.field {
+            Boost::DocId(doc_id) => json!({ "term": { "id": { "value": doc_id } } }),
+            Boost::DocIds(doc_ids) => json!({ "terms": { "id": doc_ids } }),
+            Boost::Favorite { id, required } => {
+                let term = _remote_term("tags", json!(id), 3.0);
+                if *required {
+                    must.push(term);
+                } else {
+                    should.push(term);
+                }
+                continue;
+            }
+            Boost::Tag(tag_id) => json!({ "term": { "tags": { "value": tag_id } } }),
+            Boost::ExcludeTag(tag_id) => {
+                must_not.push(json!({ "term": { "tags": { "value": tag_id } } }));
+                continue;
+            }
+            Boost::LastModified { after, before } => {
+                let mut range = serde_json::Map::new();
+                if let Some(after) = after {
+                    range.insert("gte".into(), json!(after));
+                }
+                if let Some(before) = before {
+                    range.insert("lt".into(), json!(before));
+                }
+                range.insert("format".into(), json!("epoch_second"));
+                json!({ "range": { "lastmodified": range } })
+            }
+            Boost::Url(url) => json!({ "term": { "url": { "value": url } } }),
+            Boost::UrlPrefix(prefix) => json!({ "prefix": { "url": { "value": prefix } } }),
+            Boost::UrlRegex(pattern) => json!({ "regexp": { "url": { "value": pattern } } }),
+            Boost::CustomField { field_name, value } => {
+                json!({ "term": { field_name.as_str(): { "value": value } } })
+            }
+        };
+
+        filter.push(term);
+    }
+
+    let mut query = json!({ "must": must });
+    if !should.is_empty() {
+        query["should"] = json!(should);
+    }
+    if !must_not.is_empty() {
+        query["must_not"] = json!(must_not);
+    }
+    if !filter.is_empty() {
+        query["filter"] = json!(filter);
+    }
+
+    (term_count, json!({ "bool": query }))
+}
+
+fn _remote_term(field: &str, value: serde_json::Value, boost: Score) -> serde_json::Value {
+    json!({ "term": { field: { "value": value, "boost": boost } } })
+}
+
+/// Remote query DSL version of `build_document_query`
+pub fn build_remote_document_query(
+    urls: &[Boost],
+    ids: &[String],
+    tags: &[u64],
+    exclude_tags: &[u64],
+) -> serde_json::Value {
+    let mut filter = Vec::new();
+
+    let urls_query = urls
+        .iter()
+        .filter_map(|url| match url {
+            Boost::Url(url) => Some(json!({ "term": { "url": { "value": url } } })),
+            Boost::UrlPrefix(prefix) => Some(json!({ "prefix": { "url": { "value": prefix } } })),
+            Boost::UrlRegex(pattern) => Some(json!({ "regexp": { "url": { "value": pattern } } })),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !urls_query.is_empty() {
+        filter.push(json!({ "bool": { "should": urls_query, "minimum_should_match": 1 } }));
+    }
+
+    if !ids.is_empty() {
+        filter.push(json!({ "terms": { "id": ids } }));
+    }
+
+    for id in tags {
+        filter.push(json!({ "term": { "tags": { "value": id } } }));
+    }
+
+    let must_not = exclude_tags
+        .iter()
+        .map(|id| json!({ "term": { "tags": { "value": id } } }))
+        .collect::<Vec<_>>();
+
+    json!({ "bool": {
+        "must": [{ "match_all": {} }],
+        "filter": filter,
+        "must_not": must_not
+    }})
 }
 
-/// Helper method used to build a document query based on urls, ids or tags.
+/// Helper method used to build a document query based on urls, ids or tags. URLs
+/// can be any of `Boost::Url`, `Boost::UrlPrefix` or `Boost::UrlRegex`.
 pub fn build_document_query(
     fields: DocFields,
-    urls: &Vec<String>,
+    urls: &[Boost],
     ids: &Vec<String>,
     tags: &[u64],
     exclude_tags: &[u64],
@@ -193,10 +896,9 @@ pub fn build_document_query(
     let mut ids_query: QueryVec = Vec::new();
 
     for url in urls {
-        urls_query.push((
-            Occur::Should,
-            _boosted_term(Term::from_field_text(fields.url, url), 0.0),
-        ));
+        if let Some(query) = _url_query(fields.url, url, 0.0) {
+            urls_query.push((Occur::Should, query));
+        }
     }
 
     if !urls_query.is_empty() {
@@ -0,0 +1,421 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use std::fmt;
+use thiserror::Error;
+
+/// Errors returned when a query string can not be parsed. The messages are
+/// intended to be shown to the user as is.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum QueryParseError {
+    #[error("Missing closing quote for the phrase starting at position {0}")]
+    UnterminatedPhrase(usize),
+    #[error("`{0}:` requires a value, e.g. `{0}:something`")]
+    MissingValue(String),
+    #[error("Expected `tag:<label>=<value>`, got `tag:{0}`")]
+    InvalidTag(String),
+    #[error("Unable to parse `{value}` as a date for `{op}:`, expected YYYY-MM-DD")]
+    InvalidDate { op: String, value: String },
+    #[error("`{0}:` can not be negated")]
+    UnsupportedNegation(String),
+}
+
+/// A single piece of a structured query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryPart {
+    /// Plain search term, matched against the title & content
+    Term(String),
+    /// "exact phrase", matched against the title & content
+    Phrase(String),
+    /// title:<term> or title:"<phrase>"
+    Title(String),
+    /// domain:<domain>
+    Domain(String),
+    /// url:<url>
+    Url(String),
+    /// lens:<lens name>
+    Lens(String),
+    /// tag:<label>=<value>
+    Tag { label: String, value: String },
+    /// type:<ext> or ext:<ext>
+    FileType(String),
+    /// before:<date>, matched against the last modified date
+    Before(DateTime<Utc>),
+    /// after:<date>, matched against the last modified date
+    After(DateTime<Utc>),
+}
+
+impl QueryPart {
+    /// Lens, tag & file type operators map onto tags which can only be resolved
+    /// by the caller (e.g. into `Boost::Tag` filters).
+    pub fn is_tag(&self) -> bool {
+        matches!(
+            self,
+            QueryPart::Lens(_) | QueryPart::Tag { .. } | QueryPart::FileType(_)
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryClause {
+    pub part: QueryPart,
+    /// Set when the clause was prefixed with `-`, documents matching it are
+    /// excluded from the results.
+    pub negated: bool,
+}
+
+// Writes a clause back out in query syntax, e.g. to label it in score explanations.
+impl fmt::Display for QueryClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "-")?;
+        }
+
+        // Quote values w/ spaces so they read back as a single value
+        let quoted = |value: &str| {
+            if value.contains(char::is_whitespace) {
+                format!("\"{value}\"")
+            } else {
+                value.to_string()
+            }
+        };
+
+        match &self.part {
+            QueryPart::Term(term) => write!(f, "{term}"),
+            QueryPart::Phrase(phrase) => write!(f, "\"{phrase}\""),
+            QueryPart::Title(title) => write!(f, "title:{}", quoted(title)),
+            QueryPart::Domain(domain) => write!(f, "domain:{domain}"),
+            QueryPart::Url(url) => write!(f, "url:{url}"),
+            QueryPart::Lens(lens) => write!(f, "lens:{}", quoted(lens)),
+            QueryPart::Tag { label, value } => {
+                write!(f, "tag:{}", quoted(&format!("{label}={value}")))
+            }
+            QueryPart::FileType(ext) => write!(f, "type:{ext}"),
+            QueryPart::Before(date) => write!(f, "before:{}", format_date(date)),
+            QueryPart::After(date) => write!(f, "after:{}", format_date(date)),
+        }
+    }
+}
+
+// Dates w/o a time are written the way they're usually typed
+fn format_date(date: &DateTime<Utc>) -> String {
+    if date.time() == NaiveTime::MIN {
+        date.format("%Y-%m-%d").to_string()
+    } else {
+        date.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedQuery {
+    pub clauses: Vec<QueryClause>,
+}
+
+impl ParsedQuery {
+    /// Treats the entire query string as plain search terms.
+    pub fn from_terms(query: &str) -> Self {
+        ParsedQuery {
+            clauses: query
+                .split_whitespace()
+                .map(|term| QueryClause {
+                    part: QueryPart::Term(term.to_string()),
+                    negated: false,
+                })
+                .collect(),
+        }
+    }
+
+    /// Free text terms (not phrases), joined back together.
+    pub fn terms(&self) -> String {
+        self.clauses
+            .iter()
+            .filter(|clause| !clause.negated)
+            .filter_map(|clause| match &clause.part {
+                QueryPart::Term(term) => Some(term.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// All the text being searched for (terms, phrases & titles) without any of the
+    /// operators. Useful for highlighting or generating embeddings.
+    pub fn text(&self) -> String {
+        self.clauses
+            .iter()
+            .filter(|clause| !clause.negated)
+            .filter_map(|clause| match &clause.part {
+                QueryPart::Term(text) | QueryPart::Phrase(text) | QueryPart::Title(text) => {
+                    Some(text.as_str())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Clauses which need to be resolved into tag ids.
+    pub fn tag_clauses(&self) -> impl Iterator<Item = &QueryClause> {
+        self.clauses.iter().filter(|clause| clause.part.is_tag())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+}
+
+/// Parses a query string, supporting the following syntax:
+/// - `"exact phrase"`
+/// - `-excluded` (works w/ phrases & operators as well)
+/// - `title:`, `domain:`, `url:`, `lens:`, `tag:key=value`, `type:`/`ext:`
+/// - `before:YYYY-MM-DD` & `after:YYYY-MM-DD`
+///
+/// Unknown operators (e.g. `https://...`) are treated as regular terms.
+pub fn parse_query(query: &str) -> Result<ParsedQuery, QueryParseError> {
+    let chars = query.char_indices().collect::<Vec<_>>();
+    let mut clauses = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        if chars[idx].1.is_whitespace() {
+            idx += 1;
+            continue;
+        }
+
+        // A lone "-" is just a term, only treat it as negation when attached
+        // to something.
+        let negated = chars[idx].1 == '-'
+            && chars
+                .get(idx + 1)
+                .map(|(_, c)| !c.is_whitespace())
+                .unwrap_or(false);
+        if negated {
+            idx += 1;
+        }
+
+        if chars[idx].1 == '"' {
+            let (phrase, next) = read_phrase(&chars, idx)?;
+            idx = next;
+            if !phrase.trim().is_empty() {
+                clauses.push(QueryClause {
+                    part: QueryPart::Phrase(phrase),
+                    negated,
+                });
+            }
+            continue;
+        }
+
+        let start = idx;
+        while idx < chars.len() && !chars[idx].1.is_whitespace() && chars[idx].1 != ':' {
+            idx += 1;
+        }
+        let word = chars[start..idx].iter().map(|(_, c)| c).collect::<String>();
+
+        let op = if idx < chars.len() && chars[idx].1 == ':' {
+            let op = word.to_lowercase();
+            if is_operator(&op) {
+                Some(op)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let Some(op) = op else {
+            // Not an operator, consume the rest of the word.
+            while idx < chars.len() && !chars[idx].1.is_whitespace() {
+                idx += 1;
+            }
+            let term = chars[start..idx].iter().map(|(_, c)| c).collect::<String>();
+            clauses.push(QueryClause {
+                part: QueryPart::Term(term),
+                negated,
+            });
+            continue;
+        };
+
+        // Skip the ':' and read the operator value
+        idx += 1;
+        let value = if idx < chars.len() && chars[idx].1 == '"' {
+            let (value, next) = read_phrase(&chars, idx)?;
+            idx = next;
+            value
+        } else {
+            let start = idx;
+            while idx < chars.len() && !chars[idx].1.is_whitespace() {
+                idx += 1;
+            }
+            chars[start..idx].iter().map(|(_, c)| c).collect::<String>()
+        };
+
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            return Err(QueryParseError::MissingValue(op));
+        }
+
+        let part = match op.as_str() {
+            "title" => QueryPart::Title(value),
+            "domain" => QueryPart::Domain(value.to_lowercase()),
+            "url" => QueryPart::Url(value),
+            "lens" => QueryPart::Lens(value),
+            "tag" => match value.split_once('=') {
+                Some((label, tag_value)) if !label.is_empty() && !tag_value.is_empty() => {
+                    QueryPart::Tag {
+                        label: label.to_lowercase(),
+                        value: tag_value.to_string(),
+                    }
+                }
+                _ => return Err(QueryParseError::InvalidTag(value)),
+            },
+            "type" | "ext" => QueryPart::FileType(value.trim_start_matches('.').to_lowercase()),
+            "before" | "after" => {
+                if negated {
+                    return Err(QueryParseError::UnsupportedNegation(op));
+                }
+
+                let date = parse_date(&value).ok_or_else(|| QueryParseError::InvalidDate {
+                    op: op.clone(),
+                    value: value.clone(),
+                })?;
+
+                if op == "before" {
+                    QueryPart::Before(date)
+                } else {
+                    QueryPart::After(date)
+                }
+            }
+            _ => unreachable!("Unhandled operator {op}"),
+        };
+
+        clauses.push(QueryClause { part, negated });
+    }
+
+    Ok(ParsedQuery { clauses })
+}
+
+fn is_operator(op: &str) -> bool {
+    matches!(
+        op,
+        "title" | "domain" | "url" | "lens" | "tag" | "type" | "ext" | "before" | "after"
+    )
+}
+
+// Reads a quoted phrase starting at `start` (which must point to the opening quote),
+// returning the phrase & the index right after the closing quote.
+fn read_phrase(chars: &[(usize, char)], start: usize) -> Result<(String, usize), QueryParseError> {
+    let end = chars[start + 1..]
+        .iter()
+        .position(|(_, c)| *c == '"')
+        .map(|pos| start + 1 + pos)
+        .ok_or(QueryParseError::UnterminatedPhrase(chars[start].0))?;
+
+    let phrase = chars[start + 1..end]
+        .iter()
+        .map(|(_, c)| c)
+        .collect::<String>();
+    Ok((phrase, end + 1))
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_query, QueryClause, QueryParseError, QueryPart};
+    use chrono::{TimeZone, Utc};
+
+    fn clause(part: QueryPart, negated: bool) -> QueryClause {
+        QueryClause { part, negated }
+    }
+
+    #[test]
+    fn test_parse_terms_and_phrases() {
+        let parsed = parse_query(r#"salinas "golden foothill" -river https://example.com"#)
+            .expect("Unable to parse");
+        assert_eq!(
+            parsed.clauses,
+            vec![
+                clause(QueryPart::Term("salinas".into()), false),
+                clause(QueryPart::Phrase("golden foothill".into()), false),
+                clause(QueryPart::Term("river".into()), true),
+                clause(QueryPart::Term("https://example.com".into()), false),
+            ]
+        );
+        assert_eq!(parsed.terms(), "salinas https://example.com");
+        assert_eq!(parsed.text(), "salinas golden foothill https://example.com");
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let parsed = parse_query(
+            r#"title:"of mice" Domain:Example.com -lens:recipes tag:repository=spyglass ext:.PDF after:2023-01-31"#,
+        )
+        .expect("Unable to parse");
+        assert_eq!(
+            parsed.clauses,
+            vec![
+                clause(QueryPart::Title("of mice".into()), false),
+                clause(QueryPart::Domain("example.com".into()), false),
+                clause(QueryPart::Lens("recipes".into()), true),
+                clause(
+                    QueryPart::Tag {
+                        label: "repository".into(),
+                        value: "spyglass".into()
+                    },
+                    false
+                ),
+                clause(QueryPart::FileType("pdf".into()), false),
+                clause(
+                    QueryPart::After(Utc.with_ymd_and_hms(2023, 1, 31, 0, 0, 0).unwrap()),
+                    false
+                ),
+            ]
+        );
+        assert_eq!(parsed.tag_clauses().count(), 3);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_query(r#"mice "and men"#),
+            Err(QueryParseError::UnterminatedPhrase(5))
+        );
+        assert_eq!(
+            parse_query("title: mice"),
+            Err(QueryParseError::MissingValue("title".into()))
+        );
+        assert_eq!(
+            parse_query("tag:repository"),
+            Err(QueryParseError::InvalidTag("repository".into()))
+        );
+        assert_eq!(
+            parse_query("before:yesterday"),
+            Err(QueryParseError::InvalidDate {
+                op: "before".into(),
+                value: "yesterday".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let query =
+            r#"mice -"and men" title:"of mice" domain:example.com -lens:wiki after:2023-01-02"#;
+        let parsed = parse_query(query).expect("Unable to parse query");
+        let written = parsed
+            .clauses
+            .iter()
+            .map(|clause| clause.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(written.join(" "), query);
+        assert_eq!(parse_query(&written.join(" ")), Ok(parsed));
+    }
+}
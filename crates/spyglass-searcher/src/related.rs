@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use tantivy::query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::Term;
+
+use crate::cjk_tokenizer;
+use crate::schema::{DocFields, SearchDocument};
+use crate::Score;
+
+/// Max number of terms from the document used to find related documents
+pub const MAX_QUERY_TERMS: usize = 25;
+/// Terms found in fewer docs than this are likely typos, ids & the like
+const MIN_DOC_FREQ: u64 = 2;
+/// Terms found in more than this fraction of docs say little about the document
+const MAX_DOC_FREQ_RATIO: f32 = 0.5;
+/// Shorter terms are usually noise, except for CJK bigrams
+const MIN_TERM_LEN: usize = 3;
+
+/// Picks the terms that best describe `text` compared to the rest of the index,
+/// i.e. the ones w/ the highest TF-IDF, along w/ their TF-IDF weight.
+pub fn top_terms(
+    searcher: &tantivy::Searcher,
+    analyzer: &TextAnalyzer,
+    field: Field,
+    text: &str,
+    limit: usize,
+) -> Vec<(Term, Score)> {
+    let mut term_freqs: HashMap<String, u32> = HashMap::new();
+    analyzer.token_stream(text).process(&mut |token| {
+        let is_long = token.text.chars().count() >= MIN_TERM_LEN;
+        if is_long || token.text.chars().any(cjk_tokenizer::is_cjk) {
+            *term_freqs.entry(token.text.clone()).or_default() += 1;
+        }
+    });
+
+    let num_docs = searcher.num_docs();
+    let max_doc_freq = (num_docs as f32 * MAX_DOC_FREQ_RATIO).max(MIN_DOC_FREQ as f32) as u64;
+    let mut weighted = term_freqs
+        .into_iter()
+        .filter_map(|(text, term_freq)| {
+            let term = Term::from_field_text(field, &text);
+            let doc_freq = searcher.doc_freq(&term).ok()?;
+            if !(MIN_DOC_FREQ..=max_doc_freq).contains(&doc_freq) {
+                return None;
+            }
+
+            let idf = (1.0 + num_docs as f32 / doc_freq as f32).ln();
+            Some((term, term_freq as f32 * idf))
+        })
+        .collect::<Vec<_>>();
+
+    weighted.sort_by(|(a_term, a), (b_term, b)| {
+        b.total_cmp(a)
+            .then_with(|| a_term.as_str().cmp(&b_term.as_str()))
+    });
+    weighted.truncate(limit);
+    weighted
+}
+
+/// Matches documents containing any of the `terms`, weighted by how well they
+/// describe the original document, which is itself excluded.
+pub fn more_like_this_query(doc_id: &str, terms: &[(Term, Score)]) -> BooleanQuery {
+    let fields = DocFields::as_fields();
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = terms
+        .iter()
+        .map(|(term, weight)| -> (Occur, Box<dyn Query>) {
+            (
+                Occur::Should,
+                Box::new(BoostQuery::new(
+                    Box::new(TermQuery::new(term.clone(), IndexRecordOption::WithFreqs)),
+                    *weight,
+                )),
+            )
+        })
+        .collect();
+
+    clauses.push((
+        Occur::MustNot,
+        Box::new(TermQuery::new(
+            Term::from_field_text(fields.id, doc_id),
+            IndexRecordOption::Basic,
+        )),
+    ));
+
+    BooleanQuery::new(clauses)
+}
@@ -1,25 +1,19 @@
-use super::stop_word_filter::StopWordFilter;
 use chrono::Utc;
 use std::path::PathBuf;
-use tantivy::{
-    directory::MmapDirectory,
-    schema::*,
-    tokenizer::{
-        AsciiFoldingFilter, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer,
-        TextAnalyzer,
-    },
-    Index,
-};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::{directory::MmapDirectory, schema::*, Index};
 use uuid::Uuid;
 
 use crate::client::SPYGLASS_NS;
+use crate::code::{self, CodeLanguage, CODE_TOKENIZER_NAME};
+use crate::language::DocLanguage;
 
 pub type FieldName = String;
 
 pub const TOKENIZER_NAME: &str = "spyglass_tokenizer_en";
 
 /// The current schema version
-pub const SCHEMA_VERSION: &str = "4";
+pub const SCHEMA_VERSION: &str = "5";
 pub struct SchemaMapping {
     pub text_fields: Option<Vec<(FieldName, TextOptions)>>,
     pub date_fields: Option<Vec<(FieldName, DateOptions)>>,
@@ -74,18 +68,17 @@ pub fn initialize_in_memory_index(schema: Schema) -> Index {
     index
 }
 
-/// Register custom tokenizer
+/// Register custom tokenizers, one per supported language & one for code
 pub fn register_tokenizer(index: &Index) {
-    let full_content_tokenizer_en = TextAnalyzer::from(SimpleTokenizer)
-        .filter(RemoveLongFilter::limit(40))
-        .filter(LowerCaser)
-        .filter(AsciiFoldingFilter)
-        .filter(StopWordFilter::default())
-        .filter(Stemmer::new(Language::English));
+    for lang in DocLanguage::ALL {
+        index
+            .tokenizers()
+            .register(&lang.tokenizer_name(), lang.analyzer());
+    }
 
     index
         .tokenizers()
-        .register(TOKENIZER_NAME, full_content_tokenizer_en);
+        .register(CODE_TOKENIZER_NAME, code::analyzer());
 }
 
 pub trait ToDocument {
@@ -107,6 +100,20 @@ pub struct DocumentUpdate<'a> {
 
 impl<'a> ToDocument for DocumentUpdate<'a> {
     fn to_document(&self) -> Document {
+        let language = if CodeLanguage::from_url(self.url).is_some() {
+            None
+        } else {
+            DocLanguage::detect(self.content)
+        };
+
+        self.to_document_with_language(language)
+    }
+}
+
+impl<'a> DocumentUpdate<'a> {
+    /// Same as `to_document`, for when the `language` of the content was already
+    /// detected. Detection looks at the content, so it's only done once.
+    pub fn to_document_with_language(&self, language: Option<DocLanguage>) -> Document {
         let fields = DocFields::as_fields();
 
         let doc_id = self.doc_id.clone().map_or_else(
@@ -119,7 +126,25 @@ impl<'a> ToDocument for DocumentUpdate<'a> {
         );
 
         let mut doc = Document::default();
-        doc.add_text(fields.content, self.content);
+        // Code & content in other languages is analyzed up front w/ the matching
+        // analyzer instead of the default (English) one for the field.
+        let code_lang = CodeLanguage::from_url(self.url);
+        if code_lang.is_some() {
+            doc.add_pre_tokenized_text(fields.content, code::pre_tokenize(self.content));
+        } else {
+            match language {
+                Some(lang) if lang != DocLanguage::English => {
+                    doc.add_pre_tokenized_text(fields.content, lang.pre_tokenize(self.content));
+                }
+                _ => doc.add_text(fields.content, self.content),
+            }
+        }
+
+        if let Some(code_lang) = code_lang {
+            for symbol in code_lang.symbols(self.content) {
+                doc.add_text(fields.symbols, symbol);
+            }
+        }
         doc.add_text(fields.domain, self.domain);
         doc.add_text(fields.id, &doc_id);
         doc.add_text(fields.title, self.title);
@@ -128,10 +153,42 @@ impl<'a> ToDocument for DocumentUpdate<'a> {
             doc.add_u64(fields.tags, *t as u64);
         }
 
+        if let Some(published_at) = self.published_at {
+            doc.add_date(fields.published, to_tantivy_date(published_at));
+        }
+
+        if let Some(last_modified) = self.last_modified {
+            doc.add_date(fields.lastmodified, to_tantivy_date(last_modified));
+        }
+
         doc
     }
 }
 
+/// Analyzer the content of the document @ `url` is indexed w/, the code analyzer
+/// for code files or the analyzer for the `language` of the content otherwise.
+pub fn content_analyzer(url: &str, language: Option<DocLanguage>) -> TextAnalyzer {
+    if CodeLanguage::from_url(url).is_some() {
+        code::analyzer()
+    } else {
+        language.unwrap_or(DocLanguage::English).analyzer()
+    }
+}
+
+/// Text of a stored field value. Content analyzed w/ a language specific or code
+/// analyzer is stored pre-tokenized, so `Value::as_text` alone misses it.
+pub fn value_text(value: &Value) -> Option<&str> {
+    match value {
+        Value::PreTokStr(pre_tokenized) => Some(pre_tokenized.text.as_str()),
+        value => value.as_text(),
+    }
+}
+
+/// Converts a chrono date into the date type used by the index
+pub fn to_tantivy_date(date: chrono::DateTime<Utc>) -> tantivy::DateTime {
+    tantivy::DateTime::from_timestamp_micros(date.timestamp_micros())
+}
+
 #[derive(Clone)]
 pub struct DocFields {
     pub id: Field,
@@ -143,6 +200,8 @@ pub struct DocFields {
     pub tags: Field,
     pub published: Field,
     pub lastmodified: Field,
+    pub symbols: Field,
+    pub sparse: Field,
 }
 
 impl SearchDocument for DocFields {
@@ -153,6 +212,19 @@ impl SearchDocument for DocFields {
         let text_options = TextOptions::default()
             .set_indexing_options(text_field_indexing)
             .set_stored();
+        let symbol_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(CODE_TOKENIZER_NAME)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        // Tokens are added pre-tokenized, see `sparse::add_terms`
+        let sparse_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer("raw")
+                    .set_index_option(IndexRecordOption::Basic),
+            )
+            .set_stored();
 
         // FAST:    Fast fields can be random-accessed rapidly. Use this for fields useful
         //          for scoring, filtering, or collection.
@@ -175,6 +247,10 @@ impl SearchDocument for DocFields {
                 ("url".into(), STRING | STORED | FAST),
                 // Indexed
                 ("content".into(), text_options),
+                // Names of the functions, types, etc. defined in code files
+                ("symbols".into(), symbol_options),
+                // SPLADE expansion terms, stored so they survive re-adding the doc
+                ("sparse".into(), sparse_options),
             ]),
             date_fields: Some(vec![
                 (
@@ -222,6 +298,8 @@ impl SearchDocument for DocFields {
             lastmodified: schema
                 .get_field("lastmodified")
                 .expect("No last modified date in schema"),
+            symbols: schema.get_field("symbols").expect("No symbols in schema"),
+            sparse: schema.get_field("sparse").expect("No sparse in schema"),
         }
     }
 }
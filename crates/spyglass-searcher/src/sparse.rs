@@ -0,0 +1,122 @@
+use tantivy::query::{BooleanQuery, ConstScoreQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::tokenizer::{PreTokenizedString, Token};
+use tantivy::{Document, Term};
+
+use crate::schema::{DocFields, SearchDocument};
+
+/// Term weights are stored as one of this many levels
+const MAX_LEVEL: u32 = 16;
+/// Number of levels per unit of weight, SPLADE weights rarely go past 3-4.
+const LEVELS_PER_UNIT: f32 = 4.0;
+
+/// Level a term weight is stored at, None if it rounds down to nothing
+fn level(weight: f32) -> Option<u32> {
+    let level = (weight * LEVELS_PER_UNIT).round().min(MAX_LEVEL as f32);
+    (level >= 1.0).then_some(level as u32)
+}
+
+/// Each expansion term is indexed as a single `term:level` token, so a posting
+/// list holds the documents w/ that term at that weight.
+fn token(term: &str, level: u32) -> String {
+    format!("{term}:{level}")
+}
+
+/// Compact form of expansion `terms`, as stored in the `sparse` field
+pub fn encode_terms(terms: &[(String, f32)]) -> String {
+    terms
+        .iter()
+        .filter_map(|(term, weight)| level(*weight).map(|level| token(term, level)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads back the expansion terms stored in the `sparse` field. Weights are
+/// rounded to the level they were stored at.
+pub fn parse_terms(text: &str) -> Vec<(String, f32)> {
+    text.split_whitespace()
+        .filter_map(|token| {
+            let (term, level) = token.rsplit_once(':')?;
+            let level = level.parse::<u32>().ok()?;
+            Some((term.to_string(), level as f32 / LEVELS_PER_UNIT))
+        })
+        .collect()
+}
+
+/// Adds the SPLADE expansion `terms` of a document to the `sparse` field. The
+/// tokens are added as is, the field isn't analyzed.
+pub fn add_terms(doc: &mut Document, terms: &[(String, f32)]) {
+    let text = encode_terms(terms);
+    if text.is_empty() {
+        return;
+    }
+
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    for (position, token) in text.split(' ').enumerate() {
+        tokens.push(Token {
+            offset_from: offset,
+            offset_to: offset + token.len(),
+            position,
+            text: token.to_string(),
+            position_length: 1,
+        });
+        offset += token.len() + 1;
+    }
+
+    let fields = DocFields::as_fields();
+    doc.add_pre_tokenized_text(fields.sparse, PreTokenizedString { text, tokens });
+}
+
+/// Scores documents by the dot product of their expansion terms & the query's.
+/// Every level a query term could be stored at gets its own clause, scoring the
+/// query weight times the weight of that level.
+pub fn sparse_query(field: Field, terms: &[(String, f32)]) -> BooleanQuery {
+    let clauses = terms
+        .iter()
+        .flat_map(|(term, weight)| {
+            (1..=MAX_LEVEL).map(move |level| -> (Occur, Box<dyn Query>) {
+                let query = TermQuery::new(
+                    Term::from_field_text(field, &token(term, level)),
+                    IndexRecordOption::Basic,
+                );
+                let score = weight * level as f32 / LEVELS_PER_UNIT;
+                (
+                    Occur::Should,
+                    Box::new(ConstScoreQuery::new(Box::new(query), score)),
+                )
+            })
+        })
+        .collect();
+
+    BooleanQuery::new(clauses)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_terms, parse_terms};
+
+    #[test]
+    fn test_encode_terms() {
+        let terms = vec![
+            ("river".to_string(), 2.3),
+            ("##ing".to_string(), 0.26),
+            (":".to_string(), 0.5),
+            ("bank".to_string(), 9.0),
+            ("the".to_string(), 0.05),
+        ];
+
+        // Weights are rounded to a level, negligible ones are dropped
+        let encoded = encode_terms(&terms);
+        assert_eq!(encoded, "river:9 ##ing:1 ::2 bank:16");
+        assert_eq!(
+            parse_terms(&encoded),
+            vec![
+                ("river".to_string(), 2.25),
+                ("##ing".to_string(), 0.25),
+                (":".to_string(), 0.5),
+                ("bank".to_string(), 4.0),
+            ]
+        );
+    }
+}
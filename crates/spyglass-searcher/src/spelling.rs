@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA, SINK_STATE};
+use tantivy::collector::TopDocs;
+use tantivy::query::TermQuery;
+use tantivy::schema::{Field, IndexRecordOption, Value};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::Term;
+use tantivy_fst::Automaton;
+
+/// Number of documents w/ a term looked through to find the word it was stemmed from
+const UNSTEM_SAMPLE_DOCS: usize = 3;
+
+/// Suggests a spelling correction for `text` using the term dictionary of `field`.
+/// Words w/o any match in the index are replaced w/ the most common term within
+/// a small edit distance, as it was written in the documents rather than its
+/// stem. Returns `None` if there is nothing to correct.
+pub fn suggest_correction(
+    searcher: &tantivy::Searcher,
+    field: Field,
+    analyzer: &TextAnalyzer,
+    text: &str,
+) -> Option<String> {
+    let mut corrected = false;
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        // Leave operators, phrases, exclusions, etc. alone
+        if !word.chars().all(char::is_alphanumeric) {
+            words.push(word.to_string());
+            continue;
+        }
+
+        let mut tokens = Vec::new();
+        analyzer
+            .token_stream(word)
+            .process(&mut |token| tokens.push(token.text.clone()));
+
+        // Stop words are kept as is
+        let token = match tokens.as_slice() {
+            [token] => token.clone(),
+            _ => {
+                words.push(word.to_string());
+                continue;
+            }
+        };
+
+        let term = Term::from_field_text(field, &token);
+        if searcher.doc_freq(&term).unwrap_or_default() > 0 {
+            words.push(word.to_string());
+            continue;
+        }
+
+        match closest_term(searcher, field, &token) {
+            Some(stem) => {
+                corrected = true;
+                words.push(unstem(searcher, field, analyzer, &stem).unwrap_or(stem));
+            }
+            None => words.push(word.to_string()),
+        }
+    }
+
+    if corrected {
+        Some(words.join(" "))
+    } else {
+        None
+    }
+}
+
+/// Lets a Levenshtein DFA walk the term dictionary
+struct DfaWrapper(DFA);
+
+impl Automaton for DfaWrapper {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
+
+// Building the automaton builders is expensive, they're shared across queries.
+fn automaton_builder(max_distance: u8) -> &'static LevenshteinAutomatonBuilder {
+    static BUILDERS: OnceLock<[LevenshteinAutomatonBuilder; 2]> = OnceLock::new();
+    let builders = BUILDERS.get_or_init(|| {
+        [
+            LevenshteinAutomatonBuilder::new(1, false),
+            LevenshteinAutomatonBuilder::new(2, false),
+        ]
+    });
+
+    &builders[max_distance as usize - 1]
+}
+
+// Finds the most common term in the dictionary within the allowed edit distance
+// of `token`, preferring closer matches. Only the terms accepted by a Levenshtein
+// automaton are visited.
+fn closest_term(searcher: &tantivy::Searcher, field: Field, token: &str) -> Option<String> {
+    let max_distance = match token.chars().count() {
+        0..=2 => return None,
+        3..=5 => 1,
+        _ => 2,
+    };
+
+    // term -> (distance, doc frequency)
+    let mut candidates: HashMap<String, (usize, u64)> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = match segment_reader.inverted_index(field) {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+
+        let automaton = DfaWrapper(automaton_builder(max_distance).build_dfa(token));
+        let mut terms = match inverted_index.terms().search(automaton).into_stream() {
+            Ok(terms) => terms,
+            Err(_) => continue,
+        };
+
+        while terms.advance() {
+            let candidate = match std::str::from_utf8(terms.key()) {
+                Ok(candidate) => candidate,
+                Err(_) => continue,
+            };
+
+            let entry = candidates
+                .entry(candidate.to_string())
+                .or_insert_with(|| (edit_distance(token, candidate), 0));
+            entry.1 += terms.value().doc_freq as u64;
+        }
+    }
+
+    candidates
+        .into_iter()
+        .min_by(|(a_term, (a_dist, a_freq)), (b_term, (b_dist, b_freq))| {
+            a_dist
+                .cmp(b_dist)
+                .then_with(|| b_freq.cmp(a_freq))
+                .then_with(|| a_term.cmp(b_term))
+        })
+        .map(|(term, _)| term)
+}
+
+// The word `stem` was most often stemmed from, lowercased, found by analyzing the
+// stored text of a few documents containing it.
+pub(crate) fn unstem(
+    searcher: &tantivy::Searcher,
+    field: Field,
+    analyzer: &TextAnalyzer,
+    stem: &str,
+) -> Option<String> {
+    let query = TermQuery::new(Term::from_field_text(field, stem), IndexRecordOption::Basic);
+    let addrs = searcher
+        .search(&query, &TopDocs::with_limit(UNSTEM_SAMPLE_DOCS))
+        .ok()?;
+
+    // word -> number of occurrences
+    let mut words: HashMap<String, usize> = HashMap::new();
+    for (_, addr) in addrs {
+        let Ok(doc) = searcher.doc(addr) else {
+            continue;
+        };
+
+        let mut add_word = |text: &str, token_text: &str, from: usize, to: usize| {
+            if token_text == stem {
+                if let Some(word) = text.get(from..to) {
+                    *words.entry(word.to_lowercase()).or_default() += 1;
+                }
+            }
+        };
+
+        match doc.get_first(field) {
+            // Analyzed w/ a language specific or code analyzer
+            Some(Value::PreTokStr(pre_tokenized)) => {
+                for token in &pre_tokenized.tokens {
+                    add_word(
+                        &pre_tokenized.text,
+                        &token.text,
+                        token.offset_from,
+                        token.offset_to,
+                    );
+                }
+            }
+            Some(Value::Str(text)) => {
+                analyzer.token_stream(text).process(&mut |token| {
+                    add_word(text, &token.text, token.offset_from, token.offset_to)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    words
+        .into_iter()
+        .max_by(|(a_word, a_count), (b_word, b_count)| {
+            a_count.cmp(b_count).then_with(|| b_word.cmp(a_word))
+        })
+        .map(|(word, _)| word)
+}
+
+/// Levenshtein distance between two strings, counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars = b.chars().collect::<Vec<_>>();
+    let mut prev_row = (0..=b_chars.len()).collect::<Vec<_>>();
+    let mut row = vec![0; b_chars.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        row[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let cost = if a_char == *b_char { 0 } else { 1 };
+            row[j + 1] = (prev_row[j] + cost)
+                .min(prev_row[j + 1] + 1)
+                .min(row[j] + 1);
+        }
+        std::mem::swap(&mut prev_row, &mut row);
+    }
+
+    prev_row[b_chars.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::edit_distance;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("salinas", "salinas"), 0);
+        assert_eq!(edit_distance("salinasd", "salinas"), 1);
+        assert_eq!(edit_distance("slainas", "salinas"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("häuser", "hauser"), 1);
+    }
+}
@@ -35,6 +35,29 @@ impl StopWordFilter {
 
         StopWordFilter::remove(words.iter().map(|&s| s.to_string()).collect())
     }
+
+    pub fn german() -> StopWordFilter {
+        let words: [&'static str; 52] = [
+            "aber", "alle", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "das",
+            "dass", "dem", "den", "der", "des", "die", "du", "ein", "eine", "einem", "einen",
+            "einer", "eines", "er", "es", "für", "hat", "ich", "ihr", "im", "in", "ist", "mit",
+            "nach", "nicht", "noch", "oder", "sie", "sich", "sind", "so", "und", "von", "vor",
+            "war", "wie", "wir", "zu", "zum", "zur",
+        ];
+
+        StopWordFilter::remove(words.iter().map(|&s| s.to_string()).collect())
+    }
+
+    pub fn polish() -> StopWordFilter {
+        let words: [&'static str; 47] = [
+            "a", "aby", "ale", "bo", "by", "był", "była", "było", "być", "czy", "dla", "do", "gdy",
+            "i", "ich", "jak", "jako", "jest", "jej", "jego", "już", "lub", "ma", "mnie", "na",
+            "nie", "o", "od", "po", "pod", "przez", "przy", "się", "są", "ta", "tak", "te", "tego",
+            "to", "tu", "w", "we", "z", "za", "że", "ze", "ten",
+        ];
+
+        StopWordFilter::remove(words.iter().map(|&s| s.to_string()).collect())
+    }
 }
 
 pub struct StopWordFilterStream<'a> {
@@ -8,6 +8,8 @@ use tantivy::{
 const MAX_HIGHLIGHT_SCAN: usize = 10_000;
 /// Max number of matches we need to generate a decent preview.
 const MAX_HIGHLIGHT_MATCHES: usize = 5;
+/// Max number of words shown when previewing a passage.
+const MAX_PASSAGE_WORDS: usize = 40;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct WordRange {
@@ -62,6 +64,12 @@ pub fn ff_to_string(
 /// finding matches for words and creating a window around each match, joining
 /// together overlaps & returning the final string.
 pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content: &str) -> String {
+    find_highlights(tokenizer, query, content).unwrap_or_else(|| "<span></span>".to_string())
+}
+
+/// Same as [`generate_highlight_preview`], but returns `None` when none of the
+/// query terms appear in the content.
+pub fn find_highlights(tokenizer: &TextAnalyzer, query: &str, content: &str) -> Option<String> {
     // tokenize search query
     let mut terms = HashSet::new();
     let mut tokens = tokenizer.token_stream(query);
@@ -97,6 +105,10 @@ pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content
         }
     }
 
+    if matched_indices.is_empty() {
+        return None;
+    }
+
     // Create word ranges from the indices
     let mut ranges: Vec<WordRange> = Vec::new();
     for idx in matched_indices {
@@ -134,7 +146,24 @@ pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content
         }
     }
 
-    format!("<span>{}</span>", desc.join(" "))
+    Some(format!("<span>{}</span>", desc.join(" ")))
+}
+
+/// Creates a short preview from the start of a passage, e.g. the part of a
+/// document that matched a query semantically.
+pub fn generate_passage_preview(passage: &str) -> String {
+    let words = passage.split_whitespace().collect::<Vec<_>>();
+    let mut preview = words
+        .iter()
+        .take(MAX_PASSAGE_WORDS)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if words.len() > MAX_PASSAGE_WORDS {
+        preview.push_str(" ...");
+    }
+
+    format!("<span>{preview}</span>")
 }
 
 pub fn group_urls_by_scheme(urls: Vec<&str>) -> HashMap<&str, Vec<&str>> {
@@ -155,7 +184,7 @@ pub fn group_urls_by_scheme(urls: Vec<&str>) -> HashMap<&str, Vec<&str>> {
 mod test {
     use crate::client::Searcher;
     use crate::schema::{DocFields, SearchDocument};
-    use crate::utils::generate_highlight_preview;
+    use crate::utils::{find_highlights, generate_highlight_preview, generate_passage_preview};
     use crate::IndexBackend;
 
     #[test]
@@ -172,4 +201,26 @@ mod test {
         let desc = generate_highlight_preview(&tokenizer, "rust programming", blurb);
         assert_eq!(desc, "<span><mark>Rust</mark> <mark>rust</mark> is a multi-paradigm, high-level, general-purpose <mark>programming</mark> ...</span>");
     }
+
+    #[test]
+    fn test_passage_preview() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+        let fields = DocFields::as_fields();
+        let tokenizer = searcher
+            .index
+            .tokenizer_for_field(fields.content)
+            .expect("Unable to get tokenizer for content field");
+
+        let passage = "The  borrow checker\nvalidates references. ".repeat(10);
+        assert_eq!(find_highlights(&tokenizer, "lifetimes", &passage), None);
+
+        let preview = generate_passage_preview(&passage);
+        assert!(preview.starts_with("<span>The borrow checker validates references. The"));
+        assert!(preview.ends_with(" ...</span>"));
+        assert_eq!(
+            generate_passage_preview("Short passage"),
+            "<span>Short passage</span>"
+        );
+    }
 }
@@ -3,7 +3,7 @@ use clap::{Parser, Subcommand};
 use entities::models::vec_documents::{self, DocDistance};
 use entities::models::{self, indexed_document::DocumentIdentifier, tag::check_query_for_tags};
 use libspyglass::documents::DocumentQuery;
-use libspyglass::state::AppState;
+use libspyglass::state::{embedding_model_options, AppState};
 use ron::ser::PrettyConfig;
 use shared::config::Config;
 use shared::llm::{ChatMessage, ChatRole, ChatStream, LlmSession};
@@ -140,7 +140,7 @@ async fn main() -> anyhow::Result<ExitCode> {
                     .expect("Unable to open index.");
 
                     let docs = index
-                        .search_by_query(Some(vec![doc.url.clone()]), None, &[], &[])
+                        .search_by_query(Some(vec![Boost::Url(doc.url.clone())]), None, &[], &[])
                         .await;
                     println!("### Indexed Document ###");
                     if docs.is_empty() {
@@ -167,6 +167,7 @@ async fn main() -> anyhow::Result<ExitCode> {
             tokio::spawn(async move {
                 while let Some(msg) = rx.recv().await {
                     match msg {
+                        ChatStream::DocumentContextAdded(_) => {}
                         ChatStream::LoadingPrompt => {
                             println!("loading prompt...");
                         }
@@ -205,8 +206,16 @@ async fn main() -> anyhow::Result<ExitCode> {
                 )
                 .expect("Unable to open index.");
 
-                let embedding_api = EmbeddingApi::new(config.embedding_model_dir()).unwrap();
-                if let Ok(embeddings) = embedding_api.embed(
+                let model = config.user_settings.embedding_settings.model;
+                let embedding_api = EmbeddingApi::new(
+                    config.embedding_model_path(model),
+                    embedding_model_options(model),
+                )
+                .unwrap()
+                .with_max_batch_size(
+                    config.user_settings.embedding_settings.local_batch_size as usize,
+                );
+                if let Ok(embeddings) = embedding_api.embed_blocking(
                     &question,
                     spyglass_model_interface::embedding_api::EmbeddingContentType::Query,
                 ) {
@@ -235,16 +244,19 @@ async fn main() -> anyhow::Result<ExitCode> {
                                             content: format!("Here is my question: {}", question),
                                         },
                                     ],
+                                    ..Default::default()
                                 };
 
-                            match LlmClient::new(
+                            let llm_settings = &config.user_settings.llm_settings;
+                            let model_path = llm_settings.model_path.clone().unwrap_or_else(|| {
                                 config
                                     .llm_model_dir()
                                     .join("llama3")
-                                    .join("Llama-3.2-3B-Instruct.Q5_K_M.gguf"),
-                            ) {
+                                    .join("Llama-3.2-3B-Instruct.Q5_K_M.gguf")
+                            });
+                            match LlmClient::new(model_path, llm_settings.chat_template) {
                                 Ok(mut client) => {
-                                    client.chat(&prompt, Some(tx)).await?;
+                                    client.chat(&prompt, llm_settings, Some(tx), None).await?;
                                 }
                                 Err(error) => {
                                     log::error!("Error loading model {error}");
@@ -275,9 +287,10 @@ async fn main() -> anyhow::Result<ExitCode> {
                 Searcher::with_index(&IndexBackend::LocalPath(config.index_dir()), schema, true)
                     .expect("Unable to open index.");
 
-            let docs = index
-                .search_by_query(doc_query.urls, doc_query.ids, &[], &[])
-                .await;
+            let urls = doc_query
+                .urls
+                .map(|urls| urls.into_iter().map(Boost::Url).collect());
+            let docs = index.search_by_query(urls, doc_query.ids, &[], &[]).await;
 
             if docs.is_empty() {
                 println!("No indexed document for url {:?}", id_or_url);
@@ -289,15 +302,16 @@ async fn main() -> anyhow::Result<ExitCode> {
                         .map(|x| QueryBoost::new(Boost::Tag(*x)))
                         .collect::<Vec<_>>();
 
-                    let explain = index
-                        .explain_search_with_lens(doc.doc_id, query.as_str(), &boosts)
-                        .await;
+                    let explain = index.explain(&doc.doc_id, query.as_str(), &[], &boosts);
                     match explain {
                         Some(explanation) => {
                             println!(
-                                "Query \"{:?}\" for document {:?} \n {:?}",
-                                query, id_or_url, explanation
+                                "Query \"{:?}\" for document {:?} \n score: {} (matches: {})",
+                                query, id_or_url, explanation.score, explanation.matches
                             );
+                            for component in explanation.components {
+                                println!("  {:>8.4} {:?}", component.score, component.source);
+                            }
                         }
                         None => {
                             println!("Could not get score for document");
@@ -378,29 +392,11 @@ pub async fn concat_context(distances: &[DocDistance], searcher: &Searcher) -> S
 
 #[allow(dead_code)]
 async fn pull_context(distance: &DocDistance, searcher: &Searcher) -> Option<String> {
-    if let Some(document) = searcher.get(&distance.doc_id).await {
-        if distance.segment_start == 0
-            && distance.segment_end == ((document.content.len() - 1) as i64)
-        {
-            Some(document.content)
-        } else {
-            let segment = document
-                .content
-                .trim()
-                .char_indices()
-                .filter_map(|(i, c)| {
-                    let index = i as i64;
-                    if index >= distance.segment_start && index < distance.segment_end {
-                        Some(c)
-                    } else {
-                        None
-                    }
-                })
-                .collect::<String>();
-
-            Some(segment)
-        }
-    } else {
-        None
-    }
+    let document = searcher.get(&distance.doc_id).await?;
+    let start = distance.segment_start as usize;
+    let end = (distance.segment_end as usize).min(document.content.len());
+    document
+        .content
+        .get(start..end)
+        .map(|segment| segment.to_string())
 }
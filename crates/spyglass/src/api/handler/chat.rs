@@ -0,0 +1,484 @@
+use dashmap::mapref::entry::Entry;
+use entities::models::vec_documents::DocDistance;
+use entities::models::{indexed_document, vec_documents};
+use entities::sea_orm::prelude::*;
+use jsonrpsee::core::RpcResult;
+use libspyglass::state::AppState;
+use regex::Regex;
+use shared::llm::{
+    ChatCitation, ChatMessage, ChatResponse, ChatRole, ChatStream, ChatStreamEvent, LlmSession,
+};
+use shared::response::TextSpan;
+use spyglass_llm::{LlmClient, DEFAULT_MODEL_PATH};
+use spyglass_model_interface::embedding_api::EmbeddingContentType;
+use spyglass_rpc::{server_error, RpcEvent, RpcEventType};
+use spyglass_searcher::query_parser::ParsedQuery;
+use spyglass_searcher::{Boost, QueryBoost, RetrievedDocument, SearchTrait};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::search::{search_filters, segment_span};
+
+/// Max number of document segments added to the prompt
+const MAX_CONTEXT_SEGMENTS: u32 = 6;
+/// Max number of characters of document context added to the prompt, leaves room
+/// in the context window for the conversation & the answer.
+const MAX_CONTEXT_CHARS: usize = 6_000;
+/// Characters used from the start of a document found w/ a keyword search
+const LEXICAL_SEGMENT_CHARS: usize = 1_500;
+
+/// Document segment added to the prompt
+struct ContextSegment {
+    doc_id: String,
+    title: String,
+    url: String,
+    span: TextSpan,
+    text: String,
+}
+
+/// Answers the last user message in the session using the most relevant document
+/// segments, streaming the answer as it's generated. Events are tagged w/ the chat
+/// id so concurrent chats can be told apart.
+#[instrument(skip(state))]
+pub async fn chat_completion(state: AppState, session: &LlmSession) -> RpcResult<ChatResponse> {
+    let chat_id = session
+        .chat_id
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancel = Arc::new(AtomicBool::new(false));
+    match state.chats.entry(chat_id.clone()) {
+        Entry::Occupied(_) => {
+            return Err(server_error(
+                format!("Chat {chat_id} is already in progress"),
+                None,
+            ))
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(cancel.clone());
+        }
+    }
+
+    // Unregisters the chat even if the request is dropped mid answer
+    let _guard = ChatGuard {
+        state: &state,
+        chat_id: &chat_id,
+    };
+    answer(&state, session, &chat_id, cancel).await
+}
+
+/// Removes a chat from the chats in progress when dropped
+struct ChatGuard<'a> {
+    state: &'a AppState,
+    chat_id: &'a str,
+}
+
+impl Drop for ChatGuard<'_> {
+    fn drop(&mut self) {
+        self.state.chats.remove(self.chat_id);
+    }
+}
+
+/// Stops generating the answer for a chat, the caller of `chat_completion` gets
+/// the answer so far. Chats still waiting on the model stop before starting.
+#[instrument(skip(state))]
+pub async fn cancel_chat(state: AppState, chat_id: String) -> RpcResult<()> {
+    match state.chats.get(&chat_id) {
+        Some(cancel) => cancel.store(true, Ordering::Relaxed),
+        None => log::debug!("No chat in progress w/ id {chat_id}"),
+    }
+
+    Ok(())
+}
+
+async fn answer(
+    state: &AppState,
+    session: &LlmSession,
+    chat_id: &str,
+    cancel: Arc<AtomicBool>,
+) -> RpcResult<ChatResponse> {
+    let question = session
+        .messages
+        .iter()
+        .rev()
+        .find(|msg| matches!(msg.role, ChatRole::User))
+        .map(|msg| msg.content.trim().to_string())
+        .unwrap_or_default();
+
+    let context = if question.is_empty() {
+        Vec::new()
+    } else {
+        retrieve_context(state, &question, session).await
+    };
+    let citations = citations(state, &context).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<ChatStream>(10);
+    let state_clone = state.clone();
+    let event_chat_id = chat_id.to_string();
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let is_done = msg == ChatStream::ChatDone;
+            let event = ChatStreamEvent {
+                chat_id: event_chat_id.clone(),
+                event: msg,
+            };
+            state_clone
+                .publish_event(&RpcEvent {
+                    event_type: RpcEventType::ChatStream,
+                    payload: Some(serde_json::to_value(&event).unwrap()),
+                })
+                .await;
+
+            if is_done {
+                log::info!("finished streaming chat {event_chat_id}");
+                break;
+            }
+        }
+    });
+
+    if !citations.is_empty() {
+        let _ = tx
+            .send(ChatStream::DocumentContextAdded(citations.clone()))
+            .await;
+    }
+
+    let llm_settings = state.user_settings.load().llm_settings.clone();
+    let mut llm = state.llm.lock().await;
+    let client = match llm.as_mut() {
+        Some(client) => client,
+        None => {
+            let model_path = llm_settings
+                .model_path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MODEL_PATH.into());
+            let client = LlmClient::new(model_path, llm_settings.chat_template)
+                .map_err(|e| server_error(e.to_string(), None))?;
+            *llm = Some(client);
+            llm.as_mut().unwrap()
+        }
+    };
+
+    let prompt = LlmSession {
+        messages: with_context(&session.messages, &context),
+        ..session.clone()
+    };
+    let message = client
+        .chat(&prompt, &llm_settings, Some(tx), Some(cancel.clone()))
+        .await
+        .map_err(|e| server_error(e.to_string(), None))?;
+
+    let cited = cited_sources(&message.content, citations.len());
+    let citations = if cited.is_empty() {
+        citations
+    } else {
+        citations
+            .into_iter()
+            .filter(|citation| cited.contains(&citation.index))
+            .collect()
+    };
+
+    Ok(ChatResponse {
+        chat_id: chat_id.to_string(),
+        message,
+        citations,
+        cancelled: cancel.load(Ordering::Relaxed),
+    })
+}
+
+/// Segments most relevant to the question, most relevant first. Uses the stored
+/// embeddings when an embedding model is loaded, falling back to the start of the
+/// best keyword matches when no embedded segment is close enough.
+async fn retrieve_context(
+    state: &AppState,
+    question: &str,
+    session: &LlmSession,
+) -> Vec<ContextSegment> {
+    let (mut filters, _, lens_ids) =
+        match search_filters(&state.db, &session.lenses, &ParsedQuery::default(), "").await {
+            Ok(filters) => filters,
+            Err(err) => {
+                log::error!("Unable to resolve chat filters {:?}", err);
+                return Vec::new();
+            }
+        };
+
+    let max_distance = state
+        .user_settings
+        .load()
+        .search_settings
+        .max_semantic_distance as f64;
+    let mut segments = semantic_context(state, question, session, &lens_ids)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|dist| dist.distance < max_distance)
+        .map(|dist| {
+            let span = (dist.segment_start as usize, dist.segment_end as usize);
+            (dist.doc_id, Some(span))
+        })
+        .collect::<Vec<_>>();
+
+    // Nothing embedded yet or nothing close enough, fall back to a keyword search
+    if segments.is_empty() {
+        if !session.doc_ids.is_empty() {
+            filters.push(QueryBoost::new(Boost::DocIds(session.doc_ids.clone())));
+        }
+
+        segments = state
+            .index
+            .search(question, &filters, &[], MAX_CONTEXT_SEGMENTS as usize, 0)
+            .await
+            .documents
+            .into_iter()
+            .map(|(_, doc)| (doc.doc_id, None))
+            .collect();
+    }
+
+    let mut docs: HashMap<String, Option<RetrievedDocument>> = HashMap::new();
+    let mut context = Vec::new();
+    let mut num_chars = 0;
+    for (doc_id, span) in segments.into_iter().take(MAX_CONTEXT_SEGMENTS as usize) {
+        if !docs.contains_key(&doc_id) {
+            let doc = state.index.get(&doc_id).await;
+            docs.insert(doc_id.clone(), doc);
+        }
+
+        let Some(Some(doc)) = docs.get(&doc_id) else {
+            continue;
+        };
+
+        let (start, end) = span.unwrap_or_else(|| (0, leading_chars(&doc.content)));
+        let end = end.min(doc.content.len());
+        let Some(text) = doc.content.get(start..end) else {
+            continue;
+        };
+
+        num_chars += text.chars().count();
+        if num_chars > MAX_CONTEXT_CHARS && !context.is_empty() {
+            break;
+        }
+
+        context.push(ContextSegment {
+            doc_id,
+            title: doc.title.clone(),
+            url: doc.url.clone(),
+            span: segment_span(&doc.content, start, end),
+            text: text.trim().to_string(),
+        });
+    }
+
+    context
+}
+
+/// Segments closest to the question, or None when no embedding model is loaded
+/// or the embeddings can't be read
+async fn semantic_context(
+    state: &AppState,
+    question: &str,
+    session: &LlmSession,
+    lens_ids: &[u64],
+) -> Option<Vec<DocDistance>> {
+    let embedding_api = state.embedding_api.load_full();
+    let embedding_api = embedding_api.as_ref().as_ref()?;
+
+    let embedding = match embedding_api
+        .embed(question, EmbeddingContentType::Query)
+        .await
+        .map(|embedding| embedding.first().map(|val| val.embedding.to_owned()))
+    {
+        Ok(Some(embedding)) => embedding,
+        Ok(None) => {
+            log::error!("No embedding could be generated");
+            return None;
+        }
+        Err(err) => {
+            log::error!("Error embedding question {:?}", err);
+            return None;
+        }
+    };
+
+    if session.doc_ids.is_empty() {
+        return match vec_documents::get_top_context(
+            &state.db,
+            lens_ids,
+            &embedding,
+            MAX_CONTEXT_SEGMENTS,
+        )
+        .await
+        {
+            Ok(distances) => Some(distances),
+            Err(err) => {
+                log::error!("Error accessing context {:?}", err);
+                None
+            }
+        };
+    }
+
+    let documents = indexed_document::find_by_doc_ids(&state.db, &session.doc_ids)
+        .await
+        .unwrap_or_default();
+    let mut distances = Vec::new();
+    for document in documents {
+        match vec_documents::get_context_for_doc(&state.db, document.id, &embedding).await {
+            Ok(segments) => distances.extend(segments),
+            Err(err) => log::error!("Error accessing context for doc {:?}", err),
+        }
+    }
+    distances.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    Some(distances)
+}
+
+/// Byte offset after the first few characters of the content
+fn leading_chars(content: &str) -> usize {
+    content
+        .char_indices()
+        .nth(LEXICAL_SEGMENT_CHARS)
+        .map(|(idx, _)| idx)
+        .unwrap_or(content.len())
+}
+
+/// Numbers the context segments as they're presented in the prompt
+async fn citations(state: &AppState, context: &[ContextSegment]) -> Vec<ChatCitation> {
+    let doc_ids = context
+        .iter()
+        .map(|segment| segment.doc_id.clone())
+        .collect::<HashSet<_>>();
+    let open_urls = indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.is_in(doc_ids))
+        .all(&state.db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|model| model.open_url.map(|url| (model.doc_id, url)))
+        .collect::<HashMap<_, _>>();
+
+    context
+        .iter()
+        .enumerate()
+        .map(|(idx, segment)| ChatCitation {
+            index: idx as u32 + 1,
+            doc_id: segment.doc_id.clone(),
+            title: segment.title.clone(),
+            open_url: open_urls
+                .get(&segment.doc_id)
+                .cloned()
+                .unwrap_or_else(|| segment.url.clone()),
+            segment: segment.span,
+        })
+        .collect()
+}
+
+/// Adds the context segments as numbered sources right before the question
+fn with_context(messages: &[ChatMessage], context: &[ContextSegment]) -> Vec<ChatMessage> {
+    let mut messages = messages.to_vec();
+    if context.is_empty() {
+        return messages;
+    }
+
+    let mut content = String::from(
+        "Answer the question using the numbered sources below when they're relevant & \
+        cite the sources you use by their number, e.g. [1]. If the sources don't contain \
+        the answer, say so.",
+    );
+    for (idx, segment) in context.iter().enumerate() {
+        content.push_str(&format!(
+            "\n\n[{}] {} ({})\n{}",
+            idx + 1,
+            segment.title,
+            segment.url,
+            segment.text
+        ));
+    }
+
+    let question_idx = messages
+        .iter()
+        .rposition(|msg| matches!(msg.role, ChatRole::User))
+        .unwrap_or(messages.len());
+    messages.insert(
+        question_idx,
+        ChatMessage {
+            role: ChatRole::System,
+            content,
+        },
+    );
+
+    messages
+}
+
+/// Source numbers cited in the answer, e.g. [1] or [1, 3], ignoring any that
+/// weren't part of the prompt.
+fn cited_sources(answer: &str, num_sources: usize) -> Vec<u32> {
+    let citation = Regex::new(r"\[(\d+(?:\s*,\s*\d+)*)\]").expect("Invalid citation regex");
+
+    let mut cited = Vec::new();
+    for capture in citation.captures_iter(answer) {
+        for number in capture[1].split(',') {
+            if let Ok(number) = number.trim().parse::<u32>() {
+                if number >= 1 && number as usize <= num_sources && !cited.contains(&number) {
+                    cited.push(number);
+                }
+            }
+        }
+    }
+
+    cited
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cited_sources, with_context, ContextSegment};
+    use shared::llm::{ChatMessage, ChatRole};
+    use shared::response::TextSpan;
+
+    fn message(role: ChatRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cited_sources() {
+        assert_eq!(
+            cited_sources("Rust is fast [2]. It's also safe [1, 2][7].", 3),
+            vec![2, 1]
+        );
+        assert_eq!(
+            cited_sources("Nothing relevant [0] was found.", 3),
+            Vec::<u32>::new()
+        );
+        assert_eq!(
+            cited_sources("Arrays like [a, b] aren't citations", 3),
+            Vec::<u32>::new()
+        );
+    }
+
+    #[test]
+    fn test_with_context() {
+        let messages = vec![
+            message(ChatRole::User, "hi"),
+            message(ChatRole::Assistant, "hello"),
+            message(ChatRole::User, "what is spyglass?"),
+        ];
+
+        assert_eq!(with_context(&messages, &[]).len(), 3);
+
+        let context = vec![ContextSegment {
+            doc_id: "1234".to_string(),
+            title: "Spyglass".to_string(),
+            url: "https://spyglass.fyi".to_string(),
+            span: TextSpan { start: 0, end: 27 },
+            text: "Spyglass is a search engine".to_string(),
+        }];
+        let prompt = with_context(&messages, &context);
+        assert_eq!(prompt.len(), 4);
+        assert!(matches!(prompt[2].role, ChatRole::System));
+        assert!(prompt[2]
+            .content
+            .ends_with("[1] Spyglass (https://spyglass.fyi)\nSpyglass is a search engine"));
+        assert_eq!(prompt[3].content, "what is spyglass?");
+    }
+}
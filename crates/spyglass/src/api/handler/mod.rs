@@ -5,8 +5,8 @@ use entities::models::crawl_queue::{CrawlStatus, EnqueueSettings};
 use entities::models::lens::LensType;
 use entities::models::tag::TagType;
 use entities::models::{
-    bootstrap_queue, connection::get_all_connections, crawl_queue, fetch_history, indexed_document,
-    lens,
+    bootstrap_queue, connection::get_all_connections, crawl_queue, embedding_queue,
+    embedding_rebuild, fetch_history, indexed_document, lens, sparse_backfill,
 };
 use entities::sea_orm::{prelude::*, sea_query};
 use jsonrpsee::core::RpcResult;
@@ -19,14 +19,12 @@ use libspyglass::state::AppState;
 use libspyglass::task::{AppPause, UserSettingsChange};
 use num_format::{Locale, ToFormattedString};
 use shared::config::{self, Config, UserSettings};
-use shared::llm::{ChatMessage, ChatStream, LlmSession};
 use shared::metrics::Event;
 use shared::request::{BatchDocumentRequest, RawDocType, RawDocumentRequest};
 use shared::response::{
-    AppStatus, DefaultIndices, InstallStatus, LensResult, LibraryStats, ListConnectionResult,
-    PluginResult, SupportedConnection, UserConnection,
+    AppStatus, DefaultIndices, EmbeddingRebuildStatus, InstallStatus, LensResult, LibraryStats,
+    ListConnectionResult, PluginResult, SparseBackfillStatus, SupportedConnection, UserConnection,
 };
-use spyglass_llm::LlmClient;
 use spyglass_rpc::{server_error, RpcEvent, RpcEventType};
 use spyglass_searcher::WriteTrait;
 use std::collections::HashMap;
@@ -35,6 +33,7 @@ use std::str::FromStr;
 use tracing::instrument;
 use url::Url;
 
+pub mod chat;
 pub mod search;
 
 pub async fn add_document_batch(state: &AppState, req: &BatchDocumentRequest) -> RpcResult<()> {
@@ -188,12 +187,38 @@ pub async fn authorize_connection(state: AppState, api_id: String) -> RpcResult<
 /// Fun stats about index size, etc.
 #[instrument(skip(state))]
 pub async fn app_status(state: AppState) -> RpcResult<AppStatus> {
-    // Grab details about index
-    let index = state.index;
-    let reader = index.reader.searcher();
+    let embedding_rebuild = match embedding_rebuild::in_progress(&state.db).await {
+        Ok(Some(rebuild)) => Some(EmbeddingRebuildStatus {
+            model: rebuild.model,
+            num_docs: rebuild.num_docs as u64,
+            num_remaining: embedding_queue::count_pending(&state.db)
+                .await
+                .unwrap_or_default(),
+        }),
+        Ok(None) => None,
+        Err(error) => {
+            log::error!("Unable to check embedding rebuild {:?}", error);
+            None
+        }
+    };
+
+    let sparse_backfill = match sparse_backfill::in_progress(&state.db).await {
+        Ok(Some(backfill)) => Some(SparseBackfillStatus {
+            model: backfill.model,
+            num_docs: backfill.num_docs as u64,
+            num_done: backfill.num_done as u64,
+        }),
+        Ok(None) => None,
+        Err(error) => {
+            log::error!("Unable to check sparse backfill {:?}", error);
+            None
+        }
+    };
 
     Ok(AppStatus {
-        num_docs: reader.num_docs(),
+        num_docs: state.index.num_docs().await,
+        embedding_rebuild,
+        sparse_backfill,
     })
 }
 
@@ -209,48 +234,6 @@ pub async fn delete_document(state: AppState, id: String) -> RpcResult<()> {
     Ok(())
 }
 
-#[instrument(skip(state))]
-pub async fn chat_completion(state: AppState, session: &LlmSession) -> RpcResult<ChatMessage> {
-    let mut llm = state.llm.lock().await;
-    let client = match llm.as_mut() {
-        Some(client) => client,
-        None => {
-            let client =
-                LlmClient::new("assets/models/llm/llama3/Llama-3.2-3B-Instruct.Q5_K_M.gguf".into())
-                    .map_err(|e| server_error(e.to_string(), None))?;
-            *llm = Some(client);
-            llm.as_mut().unwrap()
-        }
-    };
-
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<ChatStream>(10);
-    let state_clone = state.clone();
-    tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            state_clone
-                .publish_event(&RpcEvent {
-                    event_type: RpcEventType::ChatStream,
-                    payload: Some(serde_json::to_value(&msg).unwrap()),
-                })
-                .await;
-
-            if msg == ChatStream::ChatDone {
-                log::info!("finished streaming");
-                break;
-            }
-        }
-    });
-
-    let _ = client
-        .chat(session, Some(tx))
-        .await
-        .map_err(|e| server_error(e.to_string(), None))?;
-    Ok(ChatMessage {
-        role: shared::llm::ChatRole::Assistant,
-        content: "hello".into(),
-    })
-}
-
 /// Remove a domain from crawl queue & index
 #[instrument(skip(state))]
 pub async fn delete_domain(state: AppState, domain: String) -> RpcResult<()> {
@@ -737,6 +720,7 @@ mod test {
         assert_eq!(indexed.len(), 0);
         // Add a small delay so that the documents can be properly committed
         std::thread::sleep(std::time::Duration::from_millis(500));
-        assert_eq!(state.index.reader.searcher().num_docs(), 0);
+        let index = state.index.local().expect("Expected a local index");
+        assert_eq!(index.reader.searcher().num_docs(), 0);
     }
 }
@@ -1,23 +1,63 @@
 use entities::models::tag::{check_query_for_tags, get_favorite_tag, TagType};
 use entities::models::vec_documents::DocDistance;
-use entities::models::{indexed_document, lens, tag, vec_documents};
+use entities::models::{indexed_document, lens, search_history, tag, vec_documents};
 use entities::sea_orm::{
-    self, prelude::*, sea_query::Expr, FromQueryResult, JoinType, QueryOrder, QuerySelect,
+    self,
+    prelude::*,
+    sea_query::{Expr, Func},
+    DatabaseConnection, FromQueryResult, JoinType, QueryOrder, QuerySelect,
 };
 use jsonrpsee::core::RpcResult;
 use libspyglass::state::AppState;
 use libspyglass::task::{CleanupTask, ManagerCommand};
+use shared::config::{FusionStrategy, SearchSettings};
 use shared::metrics;
 use shared::request;
-use shared::response::{LensResult, SearchLensesResp, SearchMeta, SearchResult, SearchResults};
-use spyglass_model_interface::embedding_api::EmbeddingContentType;
-use spyglass_searcher::client::Searcher;
-use spyglass_searcher::schema::{DocFields, SearchDocument};
-use spyglass_searcher::{Boost, QueryBoost, SearchTrait};
+use shared::response::{
+    AutocompleteResults, Completion, CompletionSource, FacetCount, LensResult, RelatedResults,
+    ScoreComponent, ScoreExplanation, ScoreKind, SearchFacets, SearchLensesResp, SearchMeta,
+    SearchResult, SearchResults, SemanticScore, TextSpan,
+};
+use spyglass_model_interface::embedding_api::{EmbeddingContentType, EmbeddingProvider};
+use spyglass_model_interface::rerank_api::RerankApi;
+use spyglass_model_interface::sparse_api::SparseApi;
+use spyglass_rpc::server_error;
+use spyglass_searcher::client::IndexClient;
+use spyglass_searcher::cursor::Cursor;
+use spyglass_searcher::dedup;
+use spyglass_searcher::explain::ScoreSource;
+use spyglass_searcher::facets::FacetCounts;
+use spyglass_searcher::hybrid::{self, FusionMethod, HybridOptions, SemanticHit};
+use spyglass_searcher::language::DocLanguage;
+use spyglass_searcher::query_parser::{parse_query, ParsedQuery, QueryPart};
+use spyglass_searcher::schema::{self, DocFields, SearchDocument};
+use spyglass_searcher::{
+    Boost, QueryBoost, RetrievedDocument, SearchOptions, SearchTrait, SortOrder,
+};
 use std::collections::{HashMap, HashSet};
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::instrument;
 
+/// Fewer exact matches than this & the search is retried w/ typo tolerant matching
+const MIN_EXACT_HITS: usize = 3;
+/// Number of results returned when the request doesn't specify a limit
+const DEFAULT_SEARCH_LIMIT: u32 = 5;
+/// Max number of results returned in a single request
+const MAX_SEARCH_LIMIT: u32 = 100;
+/// Number of completions returned when the request doesn't specify a limit
+const DEFAULT_COMPLETION_LIMIT: u32 = 5;
+/// Number of related documents returned when the request doesn't specify a limit
+const DEFAULT_RELATED_LIMIT: u32 = 5;
+/// Segments of a document whose embeddings are used to find related documents
+const MAX_RELATED_SEGMENTS: u32 = 3;
+/// Max number of characters of a result read by the reranking model when no
+/// segment of it matched the query embedding
+const MAX_RERANK_PASSAGE_CHARS: usize = 2_000;
+/// Extra results fetched past the end of a page, so it can still be filled once
+/// near-duplicates are collapsed
+const DUPLICATE_SLACK: usize = 20;
+
 /// Search the user's indexed documents
 #[instrument(skip(state))]
 pub async fn search_docs(
@@ -32,101 +72,115 @@ pub async fn search_docs(
         .await;
 
     let start = SystemTime::now();
-    let index = &state.index;
-    let searcher = index.reader.searcher();
-    let query = search_req.query.clone();
+    let parsed_query = parse_query(&search_req.query)
+        .map_err(|err| server_error(format!("Invalid query: {err}"), None))?;
+    // Query text w/o any operators, used for tag matching, embeddings & highlighting
+    let query = parsed_query.text();
 
-    let lens_ids = tag::Entity::find()
-        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
-        .filter(tag::Column::Value.is_in(search_req.lenses))
-        .all(&state.db)
-        .await
-        .unwrap_or_default()
-        .iter()
-        .map(|model| model.id as u64)
-        .collect::<Vec<u64>>();
+    let (mut filters, boosts, lens_ids) =
+        search_filters(&state.db, &search_req.lenses, &parsed_query, &query).await?;
 
-    let mut boosts = Vec::new();
-    for tag in check_query_for_tags(&state.db, &query).await {
-        boosts.push(QueryBoost::new(Boost::Tag(tag)))
+    if search_req.modified_after.is_some() || search_req.modified_before.is_some() {
+        filters.push(QueryBoost::new(Boost::LastModified {
+            after: search_req.modified_after,
+            before: search_req.modified_before,
+        }));
     }
 
-    let mut filters = Vec::new();
-    for lens in &lens_ids {
-        filters.push(QueryBoost::new(Boost::Tag(*lens)));
-    }
+    let search_settings = state.user_settings.load().search_settings.clone();
+    let semantic = semantic_hits(&state, &query, &filters, &lens_ids, &search_settings).await;
 
-    if let Some(tag_id) = get_favorite_tag(&state.db).await {
-        filters.push(QueryBoost::new(Boost::Favorite {
-            id: tag_id,
-            required: false,
-        }));
-    }
+    let limit = search_req
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT) as usize;
+    let cursor = match &search_req.cursor {
+        Some(cursor) => Some(
+            cursor
+                .parse::<Cursor>()
+                .map_err(|err| server_error(format!("Invalid cursor: {err}"), None))?,
+        ),
+        None => None,
+    };
+    let offset = match &cursor {
+        Some(cursor) => cursor.offset,
+        None => search_req.offset.unwrap_or(0) as usize,
+    };
+    // W/ a cursor, grab some results around it to find where the last page ended
+    let (fetch_offset, fetch_limit) = match &cursor {
+        Some(cursor) => cursor.window(limit),
+        None => (offset, limit),
+    };
+    let order = match search_req.sort {
+        request::SearchSort::Relevance => SortOrder::Relevance,
+        request::SearchSort::Newest => SortOrder::NewestFirst,
+        request::SearchSort::Oldest => SortOrder::OldestFirst,
+    };
+    let opts = SearchOptions {
+        order,
+        min_exact_hits: if search_req.exact { 0 } else { MIN_EXACT_HITS },
+        ..Default::default()
+    };
 
-    if let Some(embedding_api) = state.embedding_api.load_full().as_ref() {
-        if !query.trim().is_empty() {
-            match embedding_api
-                .embed(&query, EmbeddingContentType::Query)
-                .map(|embedding| embedding.first().map(|val| val.to_owned()))
-            {
-                Ok(Some(embedding)) => {
-                    let mut distances = vec_documents::get_document_distance(
-                        &state.db,
-                        &lens_ids,
-                        &embedding.embedding,
-                        10,
-                    )
-                    .await;
-
-                    match distances.as_mut() {
-                        Ok(distances) => {
-                            let mut distances = distances
-                                .iter()
-                                .filter(|dist| dist.distance < 25.0)
-                                .collect::<Vec<&DocDistance>>();
-                            distances.sort_by(|a, b| a.distance.total_cmp(&b.distance));
-
-                            let min_value = distances
-                                .iter()
-                                .map(|distance| distance.distance)
-                                .reduce(f64::min);
-                            let max_value = distances
-                                .iter()
-                                .map(|distance| distance.distance)
-                                .reduce(f64::max);
-                            if let (Some(min), Some(max)) = (min_value, max_value) {
-                                for distance in distances {
-                                    let boost_normalized =
-                                        (distance.distance - min) / (max - min) * 3.0;
-                                    let boost = 3.0 - boost_normalized;
-
-                                    boosts.push(QueryBoost::with_value(
-                                        Boost::DocId(distance.doc_id.clone()),
-                                        boost as f32,
-                                    ));
-                                }
-                            }
-                        }
-                        Err(error) => {
-                            log::error!("Error accessing distances {:?}", error);
-                        }
-                    }
-                }
-                Ok(None) => {
-                    log::error!("No embedding could be generated");
-                }
-                Err(err) => {
-                    log::error!("Error embedding query {:?}", err);
-                }
-            }
+    let reranker = state.reranker.load_full();
+    let rerank_depth = match reranker.as_ref() {
+        Some(_) if search_settings.enable_reranking && order == SortOrder::Relevance => {
+            search_settings.rerank_depth as usize
         }
-    }
+        _ => 0,
+    };
+    // Near-duplicates are collapsed over every result up to the page before it's
+    // cut out, so pages aren't short & duplicates on earlier pages are skipped.
+    // Pages overlapping the reranked results are cut from the reranked list.
+    let reranked_page = fetch_offset < rerank_depth;
+    let search_limit = (fetch_offset + fetch_limit).max(rerank_depth) + DUPLICATE_SLACK;
 
-    let offset = search_req.offset.unwrap_or(0);
-    let search_result = state
+    let mut search_result = state
         .index
-        .search(&query, &filters, &boosts, 5, offset as usize)
+        .search_hybrid(
+            &search_req.query,
+            &filters,
+            &boosts,
+            &semantic,
+            search_limit,
+            0,
+            opts,
+            hybrid_options(&search_settings),
+        )
+        .await;
+    let mut documents = std::mem::take(&mut search_result.documents);
+    if reranked_page {
+        documents = rerank_results(
+            reranker,
+            &query,
+            documents,
+            &semantic,
+            rerank_depth,
+            Duration::from_millis(search_settings.rerank_budget_ms as u64),
+        )
         .await;
+    }
+
+    let num_fetched = documents.len();
+    let (documents, also_at) = collapse_duplicates(&state, documents).await;
+    search_result.total_hits = search_result
+        .total_hits
+        .saturating_sub(num_fetched - documents.len());
+    search_result.documents = documents
+        .into_iter()
+        .skip(fetch_offset)
+        .take(fetch_limit)
+        .collect();
+    if let Some(cursor) = &cursor {
+        search_result.documents = cursor.resume(search_result.documents, limit);
+    }
+
+    let end = offset + search_result.documents.len();
+    let next_cursor = search_result
+        .documents
+        .last()
+        .filter(|_| end < search_result.total_hits)
+        .map(|(score, doc)| Cursor::after(end, *score, doc).to_string());
     log::debug!(
         "query {}: {} results from {} docs in {}ms",
         query,
@@ -135,67 +189,20 @@ pub async fn search_docs(
         search_result.wall_time_ms
     );
 
-    let mut results: Vec<SearchResult> = Vec::new();
-    let mut missing: Vec<(String, String)> = Vec::new();
-    for (score, doc) in search_result.documents {
-        log::debug!("Got id with url {} {}", doc.doc_id, doc.url);
-        let indexed = indexed_document::Entity::find()
-            .filter(indexed_document::Column::DocId.eq(doc.doc_id.clone()))
-            .one(&state.db)
-            .await;
-
-        let crawl_uri = doc.url;
-        match indexed {
-            Ok(Some(indexed)) => {
-                let tags = indexed
-                    .find_related(tag::Entity)
-                    .all(&state.db)
-                    .await
-                    .unwrap_or_default()
-                    .iter()
-                    .map(|tag| (tag.label.to_string(), tag.value.clone()))
-                    .collect::<Vec<(String, String)>>();
-
-                let fields = DocFields::as_fields();
-                let tokenizer = index
-                    .index
-                    .tokenizer_for_field(fields.content)
-                    .expect("Unable to get tokenizer for content field");
-
-                let description = spyglass_searcher::utils::generate_highlight_preview(
-                    &tokenizer,
-                    &query,
-                    &doc.content,
-                );
-
-                let result = SearchResult {
-                    doc_id: doc.doc_id.clone(),
-                    domain: doc.domain,
-                    title: doc.title,
-                    crawl_uri: crawl_uri.clone(),
-                    description,
-                    url: indexed.open_url.unwrap_or(crawl_uri),
-                    tags,
-                    score,
-                };
-
-                results.push(result);
-            }
-            _ => {
-                missing.push((doc.doc_id.to_owned(), crawl_uri.to_owned()));
-            }
-        }
-    }
+    let results = build_results(&state, &query, search_result.documents, &semantic, also_at).await;
 
     let wall_time_ms = SystemTime::now()
         .duration_since(start)
         .map_or_else(|_| 0, |duration| duration.as_millis() as u64);
 
-    let num_docs = searcher.num_docs();
+    let num_docs = search_result.num_docs;
     let meta = SearchMeta {
         query: search_req.query.clone(),
         num_docs: num_docs as u32,
         wall_time_ms: wall_time_ms as u32,
+        is_fuzzy: search_result.is_fuzzy,
+        total_hits: search_result.total_hits as u32,
+        next_cursor,
     };
 
     let domains: HashSet<String> = HashSet::from_iter(results.iter().map(|r| r.domain.clone()));
@@ -210,6 +217,394 @@ pub async fn search_docs(
         })
         .await;
 
+    // Only the first page counts as a search, the rest is scrolling through results
+    if offset == 0 && !search_req.query.trim().is_empty() {
+        if let Err(err) = search_history::record(&state.db, &search_req.query).await {
+            log::warn!("Unable to record search history: {}", err);
+        }
+    }
+
+    let facets = build_facets(&state.db, &search_result.facets).await;
+    Ok(SearchResults {
+        results,
+        meta,
+        facets,
+        suggestion: search_result.suggestion,
+    })
+}
+
+/// Documents related to a document, combining documents sharing its most
+/// distinctive terms w/ the nearest neighbours of its embeddings.
+#[instrument(skip(state))]
+pub async fn related_documents(
+    state: AppState,
+    param: request::RelatedParam,
+) -> RpcResult<RelatedResults> {
+    let limit = param
+        .limit
+        .unwrap_or(DEFAULT_RELATED_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT) as usize;
+    let (filters, _, lens_ids) =
+        search_filters(&state.db, &param.lenses, &ParsedQuery::default(), "").await?;
+
+    let search_settings = state.user_settings.load().search_settings.clone();
+    let hybrid_opts = hybrid_options(&search_settings);
+    let lexical = state
+        .index
+        .more_like_this(&param.doc_id, &filters, hybrid_opts.candidates)
+        .await;
+
+    // Neighbours are only included if they pass the same filters
+    let neighbours = similar_documents(&state, &param.doc_id, &lens_ids, &search_settings).await;
+    let semantic = if neighbours.is_empty() {
+        Vec::new()
+    } else {
+        let mut semantic_filters = filters.clone();
+        semantic_filters.push(QueryBoost::new(Boost::DocIds(
+            neighbours.iter().map(|hit| hit.doc_id.clone()).collect(),
+        )));
+        let opts = SearchOptions {
+            terms_optional: true,
+            ..Default::default()
+        };
+        let mut docs = state
+            .index
+            .search_with_options("", &semantic_filters, &[], neighbours.len(), 0, opts)
+            .await
+            .documents
+            .into_iter()
+            .map(|(_, doc)| (doc.doc_id.clone(), doc))
+            .collect::<HashMap<_, _>>();
+
+        neighbours
+            .into_iter()
+            .filter_map(|hit| docs.remove(&hit.doc_id).map(|doc| (hit.distance, doc)))
+            .collect()
+    };
+
+    let related = hybrid::fuse(lexical, semantic, hybrid_opts.method);
+    let (mut related, also_at) = collapse_duplicates(&state, related).await;
+    related.truncate(limit);
+    let results = build_results(&state, "", related, &[], also_at).await;
+
+    Ok(RelatedResults {
+        doc_id: param.doc_id,
+        results,
+    })
+}
+
+/// Breaks down how a document is scored for a query, including where it lands in
+/// the keyword & similarity search results that are combined into the final ranking.
+#[instrument(skip(state))]
+pub async fn explain_result(
+    state: AppState,
+    param: request::ExplainParam,
+) -> RpcResult<ScoreExplanation> {
+    let parsed_query = parse_query(&param.query)
+        .map_err(|err| server_error(format!("Invalid query: {err}"), None))?;
+    let query = parsed_query.text();
+    let (filters, boosts, lens_ids) =
+        search_filters(&state.db, &param.lenses, &parsed_query, &query).await?;
+
+    let explanation = state
+        .index
+        .explain(&param.doc_id, &param.query, &filters, &boosts)
+        .ok_or_else(|| server_error(format!("No document w/ id {}", param.doc_id), None))?;
+
+    let search_settings = state.user_settings.load().search_settings.clone();
+    let hybrid_opts = hybrid_options(&search_settings);
+    let lexical = state
+        .index
+        .search_with_options(
+            &param.query,
+            &filters,
+            &boosts,
+            hybrid_opts.candidates,
+            0,
+            SearchOptions::default(),
+        )
+        .await;
+    let lexical_rank = lexical
+        .documents
+        .iter()
+        .position(|(_, doc)| doc.doc_id == param.doc_id);
+
+    // Similarity matches are only ranked if they pass the same filters
+    let mut semantic = semantic_hits(&state, &query, &filters, &lens_ids, &search_settings).await;
+    if !semantic.is_empty() {
+        let mut semantic_filters = filters.clone();
+        semantic_filters.push(QueryBoost::new(Boost::DocIds(
+            semantic.iter().map(|hit| hit.doc_id.clone()).collect(),
+        )));
+        let opts = SearchOptions {
+            terms_optional: true,
+            ..Default::default()
+        };
+        let allowed = state
+            .index
+            .search_with_options(
+                &param.query,
+                &semantic_filters,
+                &[],
+                semantic.len(),
+                0,
+                opts,
+            )
+            .await
+            .documents
+            .into_iter()
+            .map(|(_, doc)| doc.doc_id)
+            .collect::<HashSet<_>>();
+        semantic.retain(|hit| allowed.contains(&hit.doc_id));
+    }
+
+    let lexical_scores = hybrid::lexical_scores(
+        &lexical
+            .documents
+            .iter()
+            .map(|(score, _)| *score)
+            .collect::<Vec<_>>(),
+        hybrid_opts.method,
+    );
+    let semantic_scores = hybrid::semantic_scores(
+        &semantic.iter().map(|hit| hit.distance).collect::<Vec<_>>(),
+        hybrid_opts.method,
+    );
+    let semantic_score = semantic
+        .iter()
+        .position(|hit| hit.doc_id == param.doc_id)
+        .map(|rank| SemanticScore {
+            distance: semantic[rank].distance,
+            rank: rank as u32 + 1,
+            score: semantic_scores[rank],
+        });
+
+    // W/o any similarity matches, results are ranked by the keyword score as is
+    let score = if semantic.is_empty() {
+        explanation.score
+    } else {
+        lexical_rank
+            .map(|rank| lexical_scores[rank])
+            .unwrap_or_default()
+            + semantic_score
+                .as_ref()
+                .map(|sem| sem.score)
+                .unwrap_or_default()
+    };
+
+    let tag_ids = explanation
+        .components
+        .iter()
+        .filter_map(|component| match component.source {
+            ScoreSource::Tag(id) | ScoreSource::Favorite(id) => Some(id as i64),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let tags = tag::Entity::find()
+        .filter(tag::Column::Id.is_in(tag_ids))
+        .all(&state.db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tag| (tag.id as u64, tag.tag_pair()))
+        .collect::<HashMap<_, _>>();
+
+    let components = explanation
+        .components
+        .into_iter()
+        .map(|component| {
+            let (kind, field, label) = match component.source {
+                ScoreSource::Term { field, term } => (ScoreKind::Term, Some(field), term),
+                ScoreSource::FuzzyTerm { field, term } => (ScoreKind::FuzzyTerm, Some(field), term),
+                ScoreSource::Phrase { field } => (ScoreKind::Phrase, Some(field), query.clone()),
+                ScoreSource::Tag(id) => match tags.get(&id) {
+                    Some((TagType::Lens, value)) => (ScoreKind::Lens, None, value.clone()),
+                    Some((label, value)) => (ScoreKind::Tag, None, format!("{label}:{value}")),
+                    None => (ScoreKind::Tag, None, id.to_string()),
+                },
+                ScoreSource::Favorite(_) => (ScoreKind::Favorite, None, "favorited".into()),
+                ScoreSource::DocId(doc_id) => (ScoreKind::Document, None, doc_id),
+                ScoreSource::Url(url) => (ScoreKind::Url, None, url),
+                ScoreSource::CustomField { field, value } => {
+                    (ScoreKind::CustomField, Some(field), value.to_string())
+                }
+                ScoreSource::Operator(operator) => (ScoreKind::Operator, None, operator),
+            };
+
+            ScoreComponent {
+                kind,
+                field,
+                label,
+                score: component.score,
+            }
+        })
+        .collect();
+
+    Ok(ScoreExplanation {
+        query: param.query,
+        doc_id: param.doc_id,
+        matches: explanation.matches,
+        lexical_score: explanation.score,
+        lexical_rank: lexical_rank.map(|rank| rank as u32 + 1),
+        components,
+        semantic: semantic_score,
+        score,
+    })
+}
+
+/// Collapses mirrors & other near-duplicates into the highest ranked result of
+/// their group, using the fingerprints of the indexed documents. Returns the
+/// remaining results in order, along w/ the URLs of the duplicates collapsed into
+/// each of them by doc id.
+async fn collapse_duplicates(
+    state: &AppState,
+    documents: Vec<(f32, RetrievedDocument)>,
+) -> (Vec<(f32, RetrievedDocument)>, HashMap<String, Vec<String>>) {
+    let doc_ids = documents
+        .iter()
+        .map(|(_, doc)| doc.doc_id.clone())
+        .collect::<Vec<_>>();
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.is_in(doc_ids))
+        .all(&state.db)
+        .await
+        .unwrap_or_else(|err| {
+            log::warn!("Unable to look up document fingerprints: {err}");
+            Vec::new()
+        })
+        .into_iter()
+        .map(|indexed| (indexed.doc_id.clone(), indexed))
+        .collect::<HashMap<_, _>>();
+
+    let collapsed = dedup::collapse(documents, |(_, doc)| {
+        indexed
+            .get(&doc.doc_id)
+            .and_then(|indexed| indexed.fingerprint)
+            .map(|print| print as u64)
+    });
+
+    let mut also_at = HashMap::new();
+    let mut results = Vec::new();
+    for ((score, doc), duplicates) in collapsed {
+        if !duplicates.is_empty() {
+            let urls = duplicates
+                .into_iter()
+                .map(|(_, dupe)| {
+                    indexed
+                        .get(&dupe.doc_id)
+                        .and_then(|indexed| indexed.open_url.clone())
+                        .unwrap_or(dupe.url)
+                })
+                .collect();
+            also_at.insert(doc.doc_id.clone(), urls);
+        }
+        results.push((score, doc));
+    }
+
+    (results, also_at)
+}
+
+/// Looks up the indexed documents for the search results & generates a preview
+/// highlighting the `query`. Documents found through similarity search that don't
+/// contain any of the query terms are previewed w/ the segment that matched
+/// instead. `also_at` holds the URLs of the near-duplicates collapsed into each
+/// result. Documents that can no longer be found are cleaned up.
+async fn build_results(
+    state: &AppState,
+    query: &str,
+    documents: Vec<(f32, RetrievedDocument)>,
+    semantic: &[SemanticHit],
+    mut also_at: HashMap<String, Vec<String>>,
+) -> Vec<SearchResult> {
+    let segments = semantic
+        .iter()
+        .filter_map(|hit| hit.segment.map(|segment| (hit.doc_id.as_str(), segment)))
+        .collect::<HashMap<_, _>>();
+
+    let mut found = Vec::new();
+    let mut missing: Vec<(String, String)> = Vec::new();
+    for (score, doc) in documents {
+        log::debug!("Got id with url {} {}", doc.doc_id, doc.url);
+        let indexed = indexed_document::Entity::find()
+            .filter(indexed_document::Column::DocId.eq(doc.doc_id.clone()))
+            .one(&state.db)
+            .await;
+
+        match indexed {
+            Ok(Some(indexed)) => found.push((score, doc, indexed)),
+            _ => {
+                missing.push((doc.doc_id.to_owned(), doc.url.to_owned()));
+            }
+        }
+    }
+
+    let mut results: Vec<SearchResult> = Vec::new();
+    for (score, doc, indexed) in found {
+        let tags = indexed
+            .find_related(tag::Entity)
+            .all(&state.db)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| (tag.label.to_string(), tag.value.clone()))
+            .collect::<Vec<(String, String)>>();
+
+        // Highlight w/ the analyzer the content was indexed w/, so stemmed query
+        // terms line up w/ the words in the document.
+        let language = tags
+            .iter()
+            .find(|(label, _)| *label == TagType::Language.to_string())
+            .and_then(|(_, code)| DocLanguage::from_code(code))
+            .or_else(|| DocLanguage::detect(&doc.content));
+        let tokenizer = schema::content_analyzer(&doc.url, language);
+
+        let passage = segments
+            .get(doc.doc_id.as_str())
+            .map(|(start, end)| segment_span(&doc.content, *start, *end));
+        let highlights = spyglass_searcher::utils::find_highlights(&tokenizer, query, &doc.content);
+        let (description, semantic_match) = match (highlights, &passage) {
+            (Some(highlights), _) => (highlights, false),
+            (None, Some(span)) => {
+                let text = doc
+                    .content
+                    .chars()
+                    .skip(span.start as usize)
+                    .take((span.end - span.start) as usize)
+                    .collect::<String>();
+                (
+                    spyglass_searcher::utils::generate_passage_preview(&text),
+                    true,
+                )
+            }
+            (None, None) => (
+                spyglass_searcher::utils::generate_highlight_preview(
+                    &tokenizer,
+                    query,
+                    &doc.content,
+                ),
+                false,
+            ),
+        };
+
+        let also_at = also_at.remove(&doc.doc_id).unwrap_or_default();
+        let crawl_uri = doc.url;
+        let result = SearchResult {
+            doc_id: doc.doc_id.clone(),
+            domain: doc.domain,
+            title: doc.title,
+            crawl_uri: crawl_uri.clone(),
+            description,
+            url: indexed.open_url.unwrap_or(crawl_uri),
+            tags,
+            score,
+            also_at,
+            semantic_match,
+            passage,
+        };
+
+        results.push(result);
+    }
+
     // Send cleanup task for any missing docs
     if !missing.is_empty() {
         let mut cmd_tx = state.manager_cmd_tx.lock().await;
@@ -220,7 +615,467 @@ pub async fn search_docs(
         }
     }
 
-    Ok(SearchResults { results, meta })
+    results
+}
+
+/// Reorders the top `depth` results by how relevant the cross-encoder finds them
+/// to the query. Results are scored in rank order until `budget` runs out, only
+/// the ones scored by then are reordered.
+async fn rerank_results(
+    reranker: Arc<Option<RerankApi>>,
+    query: &str,
+    documents: Vec<(f32, RetrievedDocument)>,
+    semantic: &[SemanticHit],
+    depth: usize,
+    budget: Duration,
+) -> Vec<(f32, RetrievedDocument)> {
+    if query.trim().is_empty() || documents.len() < 2 {
+        return documents;
+    }
+
+    let segments = semantic
+        .iter()
+        .filter_map(|hit| hit.segment.map(|segment| (hit.doc_id.as_str(), segment)))
+        .collect::<HashMap<_, _>>();
+    let passages = documents
+        .iter()
+        .take(depth)
+        .map(|(_, doc)| {
+            rerank_passage(
+                doc,
+                segments
+                    .get(doc.doc_id.as_str())
+                    .and_then(|(start, end)| doc.content.get(*start..*end)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let query = query.to_string();
+    let deadline = Instant::now() + budget;
+    let scores = tokio::task::spawn_blocking(move || {
+        let Some(reranker) = reranker.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let passages = passages.iter().map(String::as_str).collect::<Vec<_>>();
+        reranker.score(&query, &passages, Some(deadline))
+    })
+    .await;
+
+    match scores {
+        Ok(Ok(scores)) => {
+            log::debug!("Reranked {} of {} results", scores.len(), depth);
+            apply_rerank(documents, &scores)
+        }
+        Ok(Err(error)) => {
+            log::error!("Unable to rerank results {:?}", error);
+            documents
+        }
+        Err(error) => {
+            log::error!("Reranking task failed {:?}", error);
+            documents
+        }
+    }
+}
+
+/// Text of a result the cross-encoder reads: the title, followed by the segment
+/// that matched the query embedding or else the start of the content.
+fn rerank_passage(doc: &RetrievedDocument, segment: Option<&str>) -> String {
+    let text = segment.unwrap_or_else(|| {
+        let end = doc
+            .content
+            .char_indices()
+            .nth(MAX_RERANK_PASSAGE_CHARS)
+            .map_or(doc.content.len(), |(idx, _)| idx);
+        &doc.content[..end]
+    });
+
+    format!("{}\n{}", doc.title.trim(), text.trim())
+}
+
+/// Sorts the first `scores.len()` results by their reranking score. Results keep
+/// the scores of the positions they move to, so scores still decrease down the
+/// list & results that weren't reranked stay in place.
+fn apply_rerank<T>(mut documents: Vec<(f32, T)>, scores: &[f32]) -> Vec<(f32, T)> {
+    let rest = documents.split_off(scores.len().min(documents.len()));
+    let positions = documents
+        .iter()
+        .map(|(score, _)| *score)
+        .collect::<Vec<_>>();
+
+    let mut reranked = documents
+        .into_iter()
+        .zip(scores)
+        .map(|((_, doc), score)| (*score, doc))
+        .collect::<Vec<_>>();
+    // Stable, ties keep their original order
+    reranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    reranked
+        .into_iter()
+        .zip(positions)
+        .map(|((_, doc), score)| (score, doc))
+        .chain(rest)
+        .collect()
+}
+
+/// Character offsets of an embedded segment in the document content. Segments are
+/// stored as byte offsets, w/ an exclusive end.
+pub(super) fn segment_span(content: &str, start: usize, end: usize) -> TextSpan {
+    let char_offset = |byte: usize| {
+        content
+            .char_indices()
+            .take_while(|(idx, _)| *idx < byte)
+            .count()
+    };
+    let start = char_offset(start);
+    let end = char_offset(end).max(start);
+
+    TextSpan {
+        start: start as u32,
+        end: end as u32,
+    }
+}
+
+/// Fusion parameters for merging keyword & similarity search results
+fn hybrid_options(settings: &SearchSettings) -> HybridOptions {
+    let method = match settings.fusion_strategy {
+        FusionStrategy::ReciprocalRank => FusionMethod::ReciprocalRank {
+            k: settings.rrf_k as f32,
+        },
+        FusionStrategy::Weighted => FusionMethod::Weighted {
+            semantic_weight: settings.semantic_weight.min(100) as f32 / 100.0,
+        },
+    };
+
+    HybridOptions {
+        method,
+        candidates: settings.num_candidates as usize,
+    }
+}
+
+/// Lens, favorite & operator filters along w/ tag boosts for a query. Also returns
+/// the ids of the lenses being searched.
+pub(super) async fn search_filters(
+    db: &DatabaseConnection,
+    lenses: &[String],
+    parsed_query: &ParsedQuery,
+    query: &str,
+) -> RpcResult<(Vec<QueryBoost>, Vec<QueryBoost>, Vec<u64>)> {
+    let mut lens_ids = tag::Entity::find()
+        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
+        .filter(tag::Column::Value.is_in(lenses.to_vec()))
+        .all(db)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(|model| model.id as u64)
+        .collect::<Vec<u64>>();
+
+    let mut boosts = Vec::new();
+    for tag in check_query_for_tags(db, query).await {
+        boosts.push(QueryBoost::new(Boost::Tag(tag)))
+    }
+
+    let (mut filters, query_lenses) = resolve_tag_filters(db, parsed_query).await?;
+    lens_ids.extend(query_lenses);
+    for lens in &lens_ids {
+        filters.push(QueryBoost::new(Boost::Tag(*lens)));
+    }
+
+    if let Some(tag_id) = get_favorite_tag(db).await {
+        filters.push(QueryBoost::new(Boost::Favorite {
+            id: tag_id,
+            required: false,
+        }));
+    }
+
+    Ok((filters, boosts, lens_ids))
+}
+
+/// Documents close to the query according to the embedding model, closest first.
+/// W/ sparse retrieval enabled, the SPLADE model is used instead. Empty if neither
+/// model is loaded.
+async fn semantic_hits(
+    state: &AppState,
+    query: &str,
+    filters: &[QueryBoost],
+    lens_ids: &[u64],
+    settings: &SearchSettings,
+) -> Vec<SemanticHit> {
+    if query.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let sparse_api = state.sparse_api.load_full();
+    if settings.enable_sparse_retrieval && sparse_api.is_some() {
+        return sparse_hits(state, sparse_api, query, filters, settings).await;
+    }
+
+    let embedding_api = state.embedding_api.load_full();
+    let Some(embedding_api) = embedding_api.as_ref() else {
+        return Vec::new();
+    };
+
+    let embedding = match embedding_api
+        .embed(query, EmbeddingContentType::Query)
+        .await
+        .map(|embedding| embedding.first().map(|val| val.to_owned()))
+    {
+        Ok(Some(embedding)) => embedding,
+        Ok(None) => {
+            log::error!("No embedding could be generated");
+            return Vec::new();
+        }
+        Err(err) => {
+            log::error!("Error embedding query {:?}", err);
+            return Vec::new();
+        }
+    };
+
+    match vec_documents::get_document_distance(
+        &state.db,
+        lens_ids,
+        &embedding.embedding,
+        settings.num_candidates,
+    )
+    .await
+    {
+        Ok(distances) => {
+            let max_distance = settings.max_semantic_distance as f64;
+            distances
+                .into_iter()
+                .filter(|dist| dist.distance < max_distance)
+                .map(|dist| SemanticHit {
+                    doc_id: dist.doc_id,
+                    distance: dist.distance as f32,
+                    segment: Some((dist.segment_start as usize, dist.segment_end as usize)),
+                })
+                .collect()
+        }
+        Err(error) => {
+            log::error!("Error accessing distances {:?}", error);
+            Vec::new()
+        }
+    }
+}
+
+/// Documents whose SPLADE expansion best matches the query's, best first. Scores
+/// are negated, so the hits sort like distances.
+async fn sparse_hits(
+    state: &AppState,
+    sparse_api: Arc<Option<SparseApi>>,
+    query: &str,
+    filters: &[QueryBoost],
+    settings: &SearchSettings,
+) -> Vec<SemanticHit> {
+    let query_text = query.to_string();
+    let expanded = tokio::task::spawn_blocking(move || {
+        sparse_api
+            .as_ref()
+            .as_ref()
+            .map(|sparse_api| sparse_api.encode(&query_text, EmbeddingContentType::Query))
+    })
+    .await;
+
+    let terms = match expanded {
+        Ok(Some(Ok(terms))) => terms,
+        Ok(None) => return Vec::new(),
+        Ok(Some(Err(err))) => {
+            log::error!("Error expanding query {:?}", err);
+            return Vec::new();
+        }
+        Err(err) => {
+            log::error!("Query expansion task failed {:?}", err);
+            return Vec::new();
+        }
+    };
+
+    state
+        .index
+        .search_sparse(&terms, filters, settings.num_candidates as usize)
+        .await
+        .into_iter()
+        .map(|(score, doc_id)| SemanticHit {
+            doc_id,
+            distance: -score,
+            segment: None,
+        })
+        .collect()
+}
+
+/// Documents closest to any of the first few segments of a document according to
+/// their stored embeddings, closest first. The document itself is excluded.
+async fn similar_documents(
+    state: &AppState,
+    doc_id: &str,
+    lens_ids: &[u64],
+    settings: &SearchSettings,
+) -> Vec<SemanticHit> {
+    let doc = match indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.eq(doc_id))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(doc)) => doc,
+        Ok(None) => return Vec::new(),
+        Err(err) => {
+            log::error!("Unable to look up document {doc_id}: {err}");
+            return Vec::new();
+        }
+    };
+
+    let embeddings = match vec_documents::get_embeddings_for_doc(
+        &state.db,
+        doc.id,
+        MAX_RELATED_SEGMENTS,
+    )
+    .await
+    {
+        Ok(embeddings) => embeddings,
+        Err(err) => {
+            log::error!("Unable to load embeddings for {doc_id}: {err}");
+            return Vec::new();
+        }
+    };
+
+    let max_distance = settings.max_semantic_distance as f64;
+    let mut closest: HashMap<String, f64> = HashMap::new();
+    for embedding in embeddings {
+        match vec_documents::get_document_distance(
+            &state.db,
+            lens_ids,
+            &embedding,
+            settings.num_candidates,
+        )
+        .await
+        {
+            Ok(distances) => {
+                for dist in distances {
+                    if dist.doc_id == doc_id || dist.distance >= max_distance {
+                        continue;
+                    }
+
+                    let entry = closest.entry(dist.doc_id).or_insert(dist.distance);
+                    *entry = entry.min(dist.distance);
+                }
+            }
+            Err(error) => log::error!("Error accessing distances {:?}", error),
+        }
+    }
+
+    let mut hits = closest
+        .into_iter()
+        .map(|(doc_id, distance)| SemanticHit {
+            doc_id,
+            distance: distance as f32,
+            segment: None,
+        })
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| {
+        a.distance
+            .total_cmp(&b.distance)
+            .then_with(|| a.doc_id.cmp(&b.doc_id))
+    });
+    hits.truncate(settings.num_candidates as usize);
+    hits
+}
+
+/// Resolves the tag ids in the facet counts from the index into the lens, source,
+/// file type & mimetype facets shown to the user.
+async fn build_facets(db: &DatabaseConnection, counts: &FacetCounts) -> SearchFacets {
+    let mut facets = SearchFacets::default();
+
+    let tag_ids = counts.tags.keys().map(|id| *id as i64).collect::<Vec<_>>();
+    let tags = if tag_ids.is_empty() {
+        Vec::new()
+    } else {
+        tag::Entity::find()
+            .filter(tag::Column::Id.is_in(tag_ids))
+            .all(db)
+            .await
+            .unwrap_or_else(|err| {
+                log::warn!("Unable to load facet tags: {err}");
+                Vec::new()
+            })
+    };
+
+    for tag in tags {
+        let (label, value) = tag.tag_pair();
+        let bucket = match label {
+            TagType::Lens => &mut facets.lenses,
+            TagType::Source => &mut facets.sources,
+            TagType::FileExt => &mut facets.file_types,
+            TagType::MimeType => &mut facets.mime_types,
+            _ => continue,
+        };
+
+        if let Some(count) = counts.tags.get(&(tag.id as u64)) {
+            bucket.push(FacetCount {
+                value,
+                count: *count as u32,
+            });
+        }
+    }
+
+    facets.domains = counts
+        .domains
+        .iter()
+        .map(|(domain, count)| FacetCount {
+            value: domain.clone(),
+            count: *count as u32,
+        })
+        .collect();
+
+    for bucket in [
+        &mut facets.lenses,
+        &mut facets.sources,
+        &mut facets.file_types,
+        &mut facets.mime_types,
+        &mut facets.domains,
+    ] {
+        bucket.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    }
+
+    facets
+}
+
+/// Resolves the lens, tag & file type operators in the query into tag filters. Lenses
+/// are returned separately so they can be used to scope the embedding search as well.
+async fn resolve_tag_filters(
+    db: &DatabaseConnection,
+    query: &ParsedQuery,
+) -> RpcResult<(Vec<QueryBoost>, Vec<u64>)> {
+    let mut filters = Vec::new();
+    let mut lens_ids = Vec::new();
+    for clause in query.tag_clauses() {
+        let (label, value) = match &clause.part {
+            QueryPart::Lens(lens) => (TagType::Lens, lens.as_str()),
+            QueryPart::Tag { label, value } => (TagType::string_to_tag_type(label), value.as_str()),
+            QueryPart::FileType(ext) => (TagType::FileExt, ext.as_str()),
+            _ => continue,
+        };
+
+        let found = tag::Entity::find()
+            .filter(tag::Column::Label.eq(label.to_string()))
+            .filter(Expr::expr(Func::lower(Expr::col(tag::Column::Value))).eq(value.to_lowercase()))
+            .one(db)
+            .await
+            .map_err(|err| server_error(format!("Unable to query db: {err}"), None))?;
+
+        match found {
+            Some(tag) if clause.negated => {
+                filters.push(QueryBoost::new(Boost::ExcludeTag(tag.id as u64)));
+            }
+            Some(tag) if label == TagType::Lens => lens_ids.push(tag.id as u64),
+            Some(tag) => filters.push(QueryBoost::new(Boost::Tag(tag.id as u64))),
+            // Nothing to exclude
+            None if clause.negated => {}
+            // No documents are tagged w/ it, so nothing can match
+            None => filters.push(QueryBoost::new(Boost::DocIds(Vec::new()))),
+        }
+    }
+
+    Ok((filters, lens_ids))
 }
 
 #[derive(FromQueryResult)]
@@ -272,8 +1127,65 @@ pub async fn search_lenses(
     Ok(SearchLensesResp { results })
 }
 
+/// Completions for a partially typed query. Recent queries come first, followed
+/// by matching document titles & the query completed w/ terms from the index. An
+/// empty query only returns recent queries.
+#[instrument(skip(state))]
+pub async fn autocomplete(
+    state: AppState,
+    param: request::AutocompleteParam,
+) -> RpcResult<AutocompleteResults> {
+    let limit = param
+        .limit
+        .unwrap_or(DEFAULT_COMPLETION_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT) as usize;
+    let typed = param.query.trim().to_lowercase();
+
+    let mut candidates = Vec::new();
+    match search_history::recent_with_prefix(&state.db, &param.query, limit as u64).await {
+        Ok(recent) => candidates.extend(
+            recent
+                .into_iter()
+                .map(|model| (model.query, CompletionSource::RecentQuery)),
+        ),
+        Err(err) => log::warn!("Unable to load search history: {}", err),
+    }
+
+    if !typed.is_empty() {
+        let index_completions = state.index.autocomplete(&param.query, limit);
+        candidates.extend(
+            index_completions
+                .titles
+                .into_iter()
+                .map(|title| (title, CompletionSource::Title)),
+        );
+        candidates.extend(
+            index_completions
+                .terms
+                .into_iter()
+                .map(|term| (term, CompletionSource::Term)),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    let completions = candidates
+        .into_iter()
+        .filter(|(text, _)| {
+            let normalized = text.trim().to_lowercase();
+            normalized != typed && seen.insert(normalized)
+        })
+        .take(limit)
+        .map(|(text, source)| Completion { text, source })
+        .collect();
+
+    Ok(AutocompleteResults {
+        query: param.query,
+        completions,
+    })
+}
+
 #[allow(dead_code)]
-pub async fn concat_context(distances: &[DocDistance], searcher: &Searcher) -> String {
+pub async fn concat_context(distances: &[DocDistance], searcher: &IndexClient) -> String {
     let mut map = HashMap::<String, usize>::new();
     let mut sorted: Vec<Vec<&DocDistance>> = Vec::new();
     // documents are already ordered now we just want to group documents by
@@ -319,42 +1231,26 @@ pub async fn concat_context(distances: &[DocDistance], searcher: &Searcher) -> S
 }
 
 #[allow(dead_code)]
-async fn pull_context(distance: &DocDistance, searcher: &Searcher) -> Option<String> {
-    if let Some(document) = searcher.get(&distance.doc_id).await {
-        if distance.segment_start == 0
-            && distance.segment_end == ((document.content.len() - 1) as i64)
-        {
-            Some(document.content)
-        } else {
-            let segment = document
-                .content
-                .trim()
-                .char_indices()
-                .filter_map(|(i, c)| {
-                    let index = i as i64;
-                    if index >= distance.segment_start && index < distance.segment_end {
-                        Some(c)
-                    } else {
-                        None
-                    }
-                })
-                .collect::<String>();
-
-            Some(segment)
-        }
-    } else {
-        None
-    }
+async fn pull_context(distance: &DocDistance, searcher: &IndexClient) -> Option<String> {
+    let document = searcher.get(&distance.doc_id).await?;
+    let start = distance.segment_start as usize;
+    let end = (distance.segment_end as usize).min(document.content.len());
+    document
+        .content
+        .get(start..end)
+        .map(|segment| segment.to_string())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::api::handler::search::concat_context;
+    use crate::api::handler::search::{apply_rerank, concat_context, rerank_passage, segment_span};
     use entities::models::vec_documents::DocDistance;
     use entities::test::setup_test_db;
     use libspyglass::state::AppState;
+    use shared::response::TextSpan;
     use spyglass_searcher::schema::DocFields;
     use spyglass_searcher::schema::SearchDocument;
+    use spyglass_searcher::RetrievedDocument;
     use tantivy::Document;
 
     #[tokio::test]
@@ -399,7 +1295,8 @@ mod test {
         let db = setup_test_db().await;
         let state = AppState::builder().with_db(db).build();
 
-        if let Ok(mut writer) = state.index.lock_writer() {
+        let index = state.index.local().expect("Expected a local index");
+        if let Ok(mut writer) = index.lock_writer() {
             if let Err(error) = writer.add_document(create_document(
                 "1234",
                 "What is this that we are doing. Well not sure yet maybe something",
@@ -426,7 +1323,7 @@ mod test {
             }
         }
 
-        if let Err(error) = state.index.reader.reload() {
+        if let Err(error) = index.reader.reload() {
             println!("Error reloading {:?}", error);
         }
 
@@ -435,6 +1332,51 @@ mod test {
         assert_eq!(expected_txt, context);
     }
 
+    #[test]
+    fn test_segment_span() {
+        let content = "\n  Über die Brücke. Second segment";
+        // Segments are stored as byte offsets
+        assert_eq!(segment_span(content, 3, 21), TextSpan { start: 3, end: 19 });
+        assert_eq!(
+            segment_span(content, 22, 36),
+            TextSpan { start: 20, end: 34 }
+        );
+        assert_eq!(
+            segment_span(content, 100, 200),
+            TextSpan { start: 34, end: 34 }
+        );
+    }
+
+    #[test]
+    fn test_apply_rerank() {
+        let documents = vec![(4.0, "a"), (3.0, "b"), (2.0, "c"), (1.0, "d")];
+        // Only the first 3 were scored before the time ran out
+        let reranked = apply_rerank(documents, &[0.1, 5.0, 0.1]);
+        assert_eq!(
+            reranked,
+            vec![(4.0, "b"), (3.0, "a"), (2.0, "c"), (1.0, "d")]
+        );
+
+        let reranked = apply_rerank(vec![(1.0, "a")], &[0.5, 2.0]);
+        assert_eq!(reranked, vec![(1.0, "a")]);
+    }
+
+    #[test]
+    fn test_rerank_passage() {
+        let doc = RetrievedDocument {
+            title: " Rivers ".into(),
+            content: "The river runs deep. ".repeat(200),
+            ..Default::default()
+        };
+        assert_eq!(
+            rerank_passage(&doc, Some("runs deep.")),
+            "Rivers\nruns deep."
+        );
+
+        let passage = rerank_passage(&doc, None);
+        assert_eq!(passage.chars().count(), "Rivers\n".len() + 2_000);
+    }
+
     fn create_document(uuid: &str, content: &str) -> Document {
         let schema = DocFields::as_schema();
         let mut new_doc = Document::default();
@@ -9,8 +9,11 @@ use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
 use libspyglass::state::AppState;
 use libspyglass::task::{CollectTask, ManagerCommand};
 use shared::config::{Config, UserSettings};
-use shared::llm::{ChatMessage, LlmSession};
-use shared::request::{BatchDocumentRequest, RawDocumentRequest, SearchLensesParam, SearchParam};
+use shared::llm::{ChatResponse, LlmSession};
+use shared::request::{
+    AutocompleteParam, BatchDocumentRequest, ExplainParam, RawDocumentRequest, RelatedParam,
+    SearchLensesParam, SearchParam,
+};
 use shared::response::{self as resp, DefaultIndices, LibraryStats};
 use spyglass_rpc::{server_error, RpcEventType, RpcServer};
 use spyglass_searcher::WriteTrait;
@@ -47,12 +50,28 @@ impl RpcServer for SpyglassRpc {
         handler::authorize_connection(self.state.clone(), id).await
     }
 
+    async fn autocomplete(&self, query: AutocompleteParam) -> RpcResult<resp::AutocompleteResults> {
+        handler::search::autocomplete(self.state.clone(), query).await
+    }
+
+    async fn related_documents(&self, param: RelatedParam) -> RpcResult<resp::RelatedResults> {
+        handler::search::related_documents(self.state.clone(), param).await
+    }
+
+    async fn explain_result(&self, param: ExplainParam) -> RpcResult<resp::ScoreExplanation> {
+        handler::search::explain_result(self.state.clone(), param).await
+    }
+
     async fn app_status(&self) -> RpcResult<resp::AppStatus> {
         handler::app_status(self.state.clone()).await
     }
 
-    async fn chat_completion(&self, session: LlmSession) -> RpcResult<ChatMessage> {
-        handler::chat_completion(self.state.clone(), &session).await
+    async fn chat_completion(&self, session: LlmSession) -> RpcResult<ChatResponse> {
+        handler::chat::chat_completion(self.state.clone(), &session).await
+    }
+
+    async fn cancel_chat(&self, chat_id: String) -> RpcResult<()> {
+        handler::chat::cancel_chat(self.state.clone(), chat_id).await
     }
 
     /// Default folders used in the local file indexer
@@ -172,10 +172,14 @@ fn event_to_crawl(api_url: &Url, event: &CalendarEvent) -> Option<CrawlResult> {
     }
 
     // Skip recurring events that aren't coming up after today.
+    let event_start = if event.is_recurring() {
+        event.next_recurrence().map(|x| x.with_timezone(&Utc))
+    } else {
+        event.start.date_time.map(|x| x.with_timezone(&Utc))
+    };
+
     let date = if event.is_recurring() {
-        event
-            .next_recurrence()
-            .map(|x| x.with_timezone(&chrono::Local).format("%F %r").to_string())
+        event_start.map(|x| x.with_timezone(&chrono::Local).format("%F %r").to_string())
     } else {
         Some(event.start.date_time.map_or(event.start.date.clone(), |d| {
             d.with_timezone(&chrono::Local).format("%F %r").to_string()
@@ -193,6 +197,7 @@ fn event_to_crawl(api_url: &Url, event: &CalendarEvent) -> Option<CrawlResult> {
             None,
         );
         crawl_result.tags = tags;
+        crawl_result.published_at = event_start;
         Some(crawl_result)
     } else {
         None
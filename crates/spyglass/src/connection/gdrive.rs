@@ -224,6 +224,7 @@ fn file_to_crawl(
         &file.name.clone(),
         Some(file.description.clone()),
     );
+    result.last_modified = Some(file.modified_time.with_timezone(&Utc));
 
     for owner in &file.owners {
         let name = owner
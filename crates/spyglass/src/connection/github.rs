@@ -318,6 +318,7 @@ fn issue_to_crawl(api_url: &Url, issue: &Issue) -> CrawlResult {
         &issue.title,
         None,
     );
+    result.last_modified = Some(issue.updated_at.with_timezone(&Utc));
 
     result.tags.push((TagType::Owner, issue.user.login.clone()));
     result
@@ -210,6 +210,8 @@ fn post_to_crawl(api_url: &Url, post: &Post) -> CrawlResult {
             CrawlResult::new(api_url, Some(open_url), &content, title, None)
         };
 
+    // Listings only tell when a post or comment was created
+    result.last_modified = DateTime::from_timestamp(post.created_utc as i64, 0);
     result.tags.extend(tags);
     result
 }
@@ -97,6 +97,10 @@ pub struct CrawlResult {
     pub links: HashSet<String>,
     /// Tags to apply to this document
     pub tags: Vec<TagPair>,
+    /// When the document was originally published/created, if known
+    pub published_at: Option<DateTime<Utc>>,
+    /// When the document was last modified, if known
+    pub last_modified: Option<DateTime<Utc>>,
 }
 
 impl CrawlResult {
@@ -303,6 +307,12 @@ impl Crawler {
         let extracted = parse_result.canonical_url.and_then(|s| Url::parse(&s).ok());
         let canonical_url = determine_canonical(url, extracted);
 
+        let last_modified = headers
+            .iter()
+            .find(|(header, _value)| header.eq_ignore_ascii_case("last-modified"))
+            .and_then(|(_header, value)| DateTime::parse_from_rfc2822(value).ok())
+            .map(|date| date.with_timezone(&Utc));
+
         Some(CrawlResult {
             content_hash: Some(parse_result.content_hash),
             content: Some(parse_result.content),
@@ -311,6 +321,7 @@ impl Crawler {
             url: canonical_url.clone(),
             open_url: Some(canonical_url),
             links: parse_result.links,
+            last_modified,
             ..Default::default()
         })
     }
@@ -515,6 +526,8 @@ async fn _process_file(
     let mut content = None;
     let mut title = Some(file_name.clone());
     let mut tags = Vec::new();
+    let mut published_at = None;
+    let last_modified = Some(filesystem::utils::last_modified_time_for_path(path));
 
     if let Some(ext) = ext {
         let extension = SupportedExt::from_ext(&ext.to_string_lossy());
@@ -600,10 +613,12 @@ async fn _process_file(
                     if let Some(parsed_title) = parsed.title {
                         title = Some(parsed_title);
                     }
+                    published_at = parsed.created;
                 }
                 Err(err) => log::warn!("Unable to parse `{}`: {}", path.display(), err),
             },
-            // todo: also parse symbols from code files.
+            // Symbols are extracted from code files when they are indexed, based on the
+            // file extension.
             SupportedExt::Code(_) | SupportedExt::Text(_) => match std::fs::read_to_string(path) {
                 Ok(x) => {
                     content = Some(x);
@@ -647,6 +662,8 @@ async fn _process_file(
         open_url: Some(url.to_string()),
         links: Default::default(),
         tags,
+        published_at,
+        last_modified,
     })
 }
 
@@ -1,121 +1,204 @@
 use entities::{
     models::{embedding_queue, vec_documents, vec_to_indexed},
-    sea_orm::EntityTrait,
+    sea_orm::{ColumnTrait, DbErr, EntityTrait, QueryFilter, TransactionTrait},
+};
+use shared::config::VectorQuantization;
+use spyglass_model_interface::embedding_api::{
+    EmbeddingContentType, EmbeddingProvider, SegmentEmbedding,
 };
-use spyglass_model_interface::embedding_api::EmbeddingContentType;
 
 use crate::state::AppState;
 
-pub async fn trigger_processing_embedding(state: &AppState, job_id: i64) {
-    let _ = tokio::spawn(processing_embedding(state.clone(), job_id)).await;
+pub async fn trigger_processing_embedding(state: &AppState, job_ids: Vec<i64>) {
+    let _ = tokio::spawn(processing_embedding(state.clone(), job_ids)).await;
 }
 
-pub async fn processing_embedding(state: AppState, job_id: i64) {
-    match embedding_queue::Entity::find_by_id(job_id)
-        .one(&state.db)
+/// Generates & stores the embeddings for a group of queued documents. The
+/// documents are embedded together, so their segments can be batched.
+pub async fn processing_embedding(state: AppState, job_ids: Vec<i64>) {
+    let jobs = match embedding_queue::Entity::find()
+        .filter(embedding_queue::Column::Id.is_in(job_ids.clone()))
+        .all(&state.db)
         .await
     {
-        Ok(Some(job)) => match job.content {
-            Some(content) => {
-                let embeddings = if let Some(api) = state.embedding_api.load_full().as_ref() {
-                    api.embed(&content, EmbeddingContentType::Document)
-                } else {
-                    Err(anyhow::format_err!(
-                        "Embedding Model is not properly configured"
-                    ))
-                };
-                match embeddings {
-                    Ok(embeddings) => {
-                        if let Err(error) = vec_to_indexed::delete_all_for_document(
-                            &state.db,
-                            job.indexed_document_id,
-                        )
-                        .await
-                        {
-                            log::error!("Error deleting document vectors {:?}", error);
-                        }
-
-                        for embedding in embeddings {
-                            match vec_to_indexed::insert_embedding_mapping(
-                                &state.db,
-                                job.indexed_document_id,
-                                embedding.start,
-                                embedding.end,
-                            )
-                            .await
-                            {
-                                Ok(insert_result) => {
-                                    let id: i64 = insert_result.last_insert_id;
-                                    match vec_documents::insert_embedding(
-                                        &state.db,
-                                        id,
-                                        &embedding.embedding,
-                                    )
-                                    .await
-                                    {
-                                        Ok(_) => {
-                                            let _ =
-                                                embedding_queue::mark_done(&state.db, job_id).await;
-                                        }
-                                        Err(error) => {
-                                            let _ = embedding_queue::mark_failed(
-                                                &state.db,
-                                                job_id,
-                                                Some(format!(
-                                                    "Error storing embedding for {}. Error {:?}",
-                                                    job.document_id, error
-                                                )),
-                                            )
-                                            .await;
-                                        }
-                                    }
-                                }
-                                Err(error) => {
-                                    log::error!("Error inserting mapping {:?}", error);
-                                }
-                            }
-                        }
-                    }
-                    Err(error) => {
-                        let _ = embedding_queue::mark_failed(
-                            &state.db,
-                            job_id,
-                            Some(format!(
-                                "Error generating embedding for {}. Error {:?}",
-                                job.document_id, error
-                            )),
-                        )
-                        .await;
-                    }
-                }
-            }
-            None => {
+        Ok(jobs) => jobs,
+        Err(error) => {
+            for job_id in job_ids {
                 let _ = embedding_queue::mark_failed(
                     &state.db,
                     job_id,
-                    Some(format!("No content found for document {}", job.document_id)),
+                    Some(format!(
+                        "Unable to access job {}. Error {:?}",
+                        job_id, error
+                    )),
                 )
                 .await;
             }
-        },
-        Ok(None) => {
+            return;
+        }
+    };
+
+    for job_id in &job_ids {
+        if !jobs.iter().any(|job| job.id == *job_id) {
             let _ = embedding_queue::mark_failed(
                 &state.db,
-                job_id,
+                *job_id,
                 Some(format!("Job {} not found", job_id)),
             )
             .await;
         }
+    }
+
+    let mut to_embed = Vec::new();
+    for job in jobs {
+        match &job.content {
+            Some(_) => to_embed.push(job),
+            None => {
+                let _ = embedding_queue::mark_failed(
+                    &state.db,
+                    job.id,
+                    Some(format!("No content found for document {}", job.document_id)),
+                )
+                .await;
+            }
+        }
+    }
+
+    if to_embed.is_empty() {
+        return;
+    }
+
+    let contents = to_embed
+        .iter()
+        .map(|job| job.content.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let embedding_api = state.embedding_api.load_full();
+    let results: Vec<anyhow::Result<Vec<SegmentEmbedding>>> = match embedding_api.as_ref() {
+        Some(api) => match api
+            .embed_many(&contents, EmbeddingContentType::Document)
+            .await
+        {
+            Ok(embedded) => embedded.into_iter().map(Ok).collect(),
+            // Retry one at a time so a single bad document doesn't fail the rest
+            Err(error) if contents.len() > 1 => {
+                log::warn!("Error embedding batch, retrying individually {:?}", error);
+                let mut results = Vec::with_capacity(contents.len());
+                for content in &contents {
+                    results.push(api.embed(content, EmbeddingContentType::Document).await);
+                }
+                results
+            }
+            Err(error) => vec![Err(error)],
+        },
+        None => contents
+            .iter()
+            .map(|_| {
+                Err(anyhow::format_err!(
+                    "Embedding Model is not properly configured"
+                ))
+            })
+            .collect(),
+    };
+
+    for (job, result) in to_embed.iter().zip(results) {
+        match result {
+            Ok(embeddings) => store_embeddings(&state, job, embeddings).await,
+            Err(error) => {
+                let _ = embedding_queue::mark_failed(
+                    &state.db,
+                    job.id,
+                    Some(format!(
+                        "Error generating embedding for {}. Error {:?}",
+                        job.document_id, error
+                    )),
+                )
+                .await;
+            }
+        }
+    }
+}
+
+/// Drops every stored embedding & clears the embedding queue so all documents
+/// can be embedded again w/ a model that generates `dimensions` sized embeddings,
+/// stored w/ `quantization`.
+pub async fn reset_embeddings(
+    state: &AppState,
+    dimensions: usize,
+    quantization: VectorQuantization,
+) -> Result<(), DbErr> {
+    let tx = state.db.begin().await?;
+    vec_to_indexed::Entity::delete_many().exec(&tx).await?;
+    vec_documents::recreate_table(&tx, dimensions, quantization).await?;
+    embedding_queue::Entity::delete_many().exec(&tx).await?;
+    tx.commit().await
+}
+
+/// Replaces the stored embeddings for a document & marks its job as done
+async fn store_embeddings(
+    state: &AppState,
+    job: &embedding_queue::Model,
+    embeddings: Vec<SegmentEmbedding>,
+) {
+    // Embeddings were reset while this job was running, likely generated w/ the
+    // previous model.
+    if let Ok(None) = embedding_queue::Entity::find_by_id(job.id)
+        .one(&state.db)
+        .await
+    {
+        log::debug!("Skipping embeddings for removed job {}", job.id);
+        return;
+    }
+
+    let quantization = match vec_documents::stored_format(&state.db).await {
+        Ok(Some(format)) => format.quantization,
+        Ok(None) => {
+            log::error!("Missing vector table, unable to store embeddings");
+            return;
+        }
         Err(error) => {
-            let _ = embedding_queue::mark_failed(
-                &state.db,
-                job_id,
-                Some(format!(
-                    "Unable to access job {}. Error {:?}",
-                    job_id, error
-                )),
-            )
-            .await;
+            log::error!("Error reading vector table format {:?}", error);
+            return;
         }
+    };
+
+    if let Err(error) =
+        vec_to_indexed::delete_all_for_document(&state.db, job.indexed_document_id).await
+    {
+        log::error!("Error deleting document vectors {:?}", error);
     }
+
+    for embedding in embeddings {
+        let quantized = vec_documents::quantize(&embedding.embedding, quantization);
+        match vec_to_indexed::insert_embedding_mapping(
+            &state.db,
+            job.indexed_document_id,
+            embedding.start,
+            embedding.end,
+            quantized.scale,
+        )
+        .await
+        {
+            Ok(insert_result) => {
+                let id: i64 = insert_result.last_insert_id;
+                if let Err(error) = vec_documents::insert_embedding(&state.db, id, &quantized).await
+                {
+                    let _ = embedding_queue::mark_failed(
+                        &state.db,
+                        job.id,
+                        Some(format!(
+                            "Error storing embedding for {}. Error {:?}",
+                            job.document_id, error
+                        )),
+                    )
+                    .await;
+                    return;
+                }
+            }
+            Err(error) => {
+                log::error!("Error inserting mapping {:?}", error);
+            }
+        }
+    }
+
+    let _ = embedding_queue::mark_done(&state.db, job.id).await;
 }
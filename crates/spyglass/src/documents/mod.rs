@@ -19,9 +19,12 @@ use url::Url;
 use crate::{crawler::CrawlResult, state::AppState};
 use entities::models::tag::TagType;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
+use spyglass_model_interface::{embedding_api::EmbeddingContentType, sparse_api::SparseTerms};
 use spyglass_searcher::{
+    dedup,
+    language::DocLanguage,
     schema::{DocumentUpdate, ToDocument},
-    RetrievedDocument, WriteTrait,
+    sparse, RetrievedDocument, WriteTrait,
 };
 
 pub mod embeddings;
@@ -165,10 +168,35 @@ pub async fn process_crawl_results(
     let mut updates = Vec::new();
     let mut added_docs = Vec::new();
 
+    // Expanded before the transaction is opened, the model can take a while.
+    let expansions = sparse_terms(
+        state,
+        results
+            .iter()
+            .map(|result| {
+                expansion_text(
+                    result.title.as_deref().unwrap_or_default(),
+                    result.content.as_deref().unwrap_or_default(),
+                )
+            })
+            .collect(),
+    )
+    .await;
+
     let tx = state.db.begin().await?;
-    for crawl_result in results {
+    for (crawl_result, terms) in results.iter().zip(expansions) {
+        // Tag the document w/ the language it's written in, if we can tell.
+        let mut crawl_tags = crawl_result.tags.clone();
+        let language = crawl_result
+            .content
+            .as_deref()
+            .and_then(DocLanguage::detect);
+        if let Some(lang) = language {
+            crawl_tags.push((TagType::Language, lang.code().to_string()));
+        }
+
         // Fetch the tag ids to apply to this crawl.
-        let mut tags_for_crawl = _get_tag_ids(&state.db, &crawl_result.tags, &mut tag_cache).await;
+        let mut tags_for_crawl = _get_tag_ids(&state.db, &crawl_tags, &mut tag_cache).await;
         tags_for_crawl.extend(global_tids.clone());
         tag_map.insert(crawl_result.url.clone(), tags_for_crawl.clone());
 
@@ -177,22 +205,26 @@ pub async fn process_crawl_results(
         let url_host = url.host_str().unwrap_or("");
 
         // Add document to index
-        let doc_id = state
-            .index
-            .upsert(
-                &DocumentUpdate {
-                    doc_id: id_map.get(&crawl_result.url).cloned(),
-                    title: &crawl_result.title.clone().unwrap_or_default(),
-                    domain: url_host,
-                    url: url.as_str(),
-                    content: &crawl_result.content.clone().unwrap_or_default(),
-                    tags: &tags_for_crawl.clone(),
-                    published_at: None,
-                    last_modified: None,
-                }
-                .to_document(),
-            )
-            .await?;
+        let mut doc = DocumentUpdate {
+            doc_id: id_map.get(&crawl_result.url).cloned(),
+            title: &crawl_result.title.clone().unwrap_or_default(),
+            domain: url_host,
+            url: url.as_str(),
+            content: &crawl_result.content.clone().unwrap_or_default(),
+            tags: &tags_for_crawl.clone(),
+            published_at: crawl_result.published_at,
+            last_modified: crawl_result.last_modified,
+        }
+        .to_document_with_language(language);
+        sparse::add_terms(&mut doc, &terms);
+        let doc_id = state.index.upsert(&doc).await?;
+
+        // Used to collapse mirrors & other near-duplicates in search results
+        let fingerprint = crawl_result
+            .content
+            .as_deref()
+            .and_then(dedup::fingerprint)
+            .map(|print| print as i64);
 
         if crawl_result.content.is_some() && state.embedding_api.load().as_ref().is_some() {
             embedding_map.insert(doc_id.clone(), crawl_result.content.clone().unwrap());
@@ -205,12 +237,14 @@ pub async fn process_crawl_results(
                 url: Set(url.to_string()),
                 open_url: Set(crawl_result.open_url.clone()),
                 doc_id: Set(doc_id),
+                fingerprint: Set(fingerprint),
                 updated_at: Set(Utc::now()),
                 ..Default::default()
             });
         } else if let Some(model) = model_map.get(&doc_id) {
             // Touch the existing model so we know it's been checked recently.
             let mut update: indexed_document::ActiveModel = model.to_owned().into();
+            update.fingerprint = Set(fingerprint);
             update.updated_at = Set(Utc::now());
             updates.push(update);
         }
@@ -340,33 +374,40 @@ pub async fn process_records(
         .map(|x| x.id)
         .collect::<Vec<_>>();
 
+    let expansions = sparse_terms(
+        state,
+        results
+            .iter()
+            .map(|result| {
+                expansion_text(result.title.as_deref().unwrap_or_default(), &result.content)
+            })
+            .collect(),
+    )
+    .await;
+
     let transaction = state.db.begin().await?;
     let mut updates = Vec::new();
     let mut added_docs = Vec::new();
-    for crawl_result in results {
+    for (crawl_result, terms) in results.iter().zip(expansions) {
         if let Some(canonical_url_str) = &crawl_result.canonical_url {
             match Url::parse(canonical_url_str) {
                 Ok(url) => {
                     let url_host = url.host_str().unwrap_or("");
                     // Add document to index
+                    let mut doc = DocumentUpdate {
+                        doc_id: id_map.get(&canonical_url_str.clone()).cloned(),
+                        title: &crawl_result.title.clone().unwrap_or_default(),
+                        domain: url_host,
+                        url: url.as_str(),
+                        content: &crawl_result.content,
+                        tags: &tag_list,
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document();
+                    sparse::add_terms(&mut doc, &terms);
                     let doc_id: Option<String> = {
-                        match state
-                            .index
-                            .upsert(
-                                &DocumentUpdate {
-                                    doc_id: id_map.get(&canonical_url_str.clone()).cloned(),
-                                    title: &crawl_result.title.clone().unwrap_or_default(),
-                                    domain: url_host,
-                                    url: url.as_str(),
-                                    content: &crawl_result.content,
-                                    tags: &tag_list,
-                                    published_at: None,
-                                    last_modified: None,
-                                }
-                                .to_document(),
-                            )
-                            .await
-                        {
+                        match state.index.upsert(&doc).await {
                             Ok(new_doc_id) => Some(new_doc_id),
                             _ => None,
                         }
@@ -380,6 +421,8 @@ pub async fn process_records(
                                 url: Set(url.to_string()),
                                 open_url: Set(Some(url.to_string())),
                                 doc_id: Set(new_id),
+                                fingerprint: Set(dedup::fingerprint(&crawl_result.content)
+                                    .map(|print| print as i64)),
                                 ..Default::default()
                             };
 
@@ -401,9 +444,7 @@ pub async fn process_records(
     // Save the data
     indexed_document::insert_many(&transaction, &updates).await?;
     transaction.commit().await?;
-    if let Ok(mut writer) = state.index.lock_writer() {
-        let _ = writer.commit();
-    }
+    let _ = state.index.save().await;
 
     let added_entries: Vec<indexed_document::Model> = indexed_document::Entity::find()
         .filter(indexed_document::Column::Url.is_in(added_docs))
@@ -510,28 +551,114 @@ pub async fn update_tags(
 
         log::debug!("Tag map generated {}", tag_map.len());
         for (_, (doc, ids)) in tag_map.iter() {
-            let _doc_id = state
-                .index
-                .upsert(
-                    &DocumentUpdate {
-                        doc_id: Some(doc.doc_id.clone()),
-                        title: &doc.title,
-                        domain: &doc.domain,
-                        url: &doc.url,
-                        content: &doc.content,
-                        tags: ids,
-                        published_at: None,
-                        last_modified: None,
-                    }
-                    .to_document(),
-                )
-                .await?;
+            let mut update = DocumentUpdate {
+                doc_id: Some(doc.doc_id.clone()),
+                title: &doc.title,
+                domain: &doc.domain,
+                url: &doc.url,
+                content: &doc.content,
+                tags: ids,
+                published_at: doc.published,
+                last_modified: doc.last_modified,
+            }
+            .to_document();
+            // Expanding the document again would be slow, keep what it had.
+            sparse::add_terms(&mut update, &doc.sparse_terms);
+            let _doc_id = state.index.upsert(&update).await?;
         }
     }
 
     Ok(())
 }
 
+/// Text a document is expanded from w/ the SPLADE model
+fn expansion_text(title: &str, content: &str) -> String {
+    match (title.trim(), content.trim()) {
+        ("", content) => content.to_string(),
+        (title, "") => title.to_string(),
+        (title, content) => format!("{title}\n{content}"),
+    }
+}
+
+/// SPLADE expansion terms of each of `contents`, in the same order. Empty when
+/// sparse retrieval is disabled or the contents couldn't be expanded.
+async fn sparse_terms(state: &AppState, contents: Vec<String>) -> Vec<SparseTerms> {
+    let num_contents = contents.len();
+    let sparse_api = state.sparse_api.load_full();
+    if sparse_api.is_none() {
+        return vec![Vec::new(); num_contents];
+    }
+
+    let expanded = tokio::task::spawn_blocking(move || {
+        let Some(sparse_api) = sparse_api.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let contents = contents.iter().map(String::as_str).collect::<Vec<_>>();
+        sparse_api.encode_many(&contents, EmbeddingContentType::Document)
+    })
+    .await;
+
+    match expanded {
+        Ok(Ok(expanded)) if expanded.len() == num_contents => expanded,
+        Ok(Ok(_)) => vec![Vec::new(); num_contents],
+        Ok(Err(err)) => {
+            log::warn!("Unable to expand documents. {:?}", err);
+            vec![Vec::new(); num_contents]
+        }
+        Err(err) => {
+            log::warn!("Document expansion task failed. {:?}", err);
+            vec![Vec::new(); num_contents]
+        }
+    }
+}
+
+/// Re-adds documents to the index w/ expansion terms from the loaded SPLADE model,
+/// e.g. documents indexed before sparse retrieval was turned on. Returns the
+/// number of documents updated.
+pub async fn add_sparse_terms(state: &AppState, doc_ids: &[String]) -> anyhow::Result<usize> {
+    let docs = state
+        .index
+        .search_by_query(None, Some(doc_ids.to_vec()), &[], &[])
+        .await
+        .into_iter()
+        .map(|(_, doc)| doc)
+        .collect::<Vec<_>>();
+    if docs.is_empty() {
+        return Ok(0);
+    }
+
+    let expansions = sparse_terms(
+        state,
+        docs.iter()
+            .map(|doc| expansion_text(&doc.title, &doc.content))
+            .collect(),
+    )
+    .await;
+
+    let mut updates = Vec::new();
+    for (doc, terms) in docs.iter().zip(expansions) {
+        let tags = doc.tags.iter().map(|tag| *tag as i64).collect::<Vec<_>>();
+        let mut update = DocumentUpdate {
+            doc_id: Some(doc.doc_id.clone()),
+            title: &doc.title,
+            domain: &doc.domain,
+            url: &doc.url,
+            content: &doc.content,
+            tags: &tags,
+            published_at: doc.published,
+            last_modified: doc.last_modified,
+        }
+        .to_document();
+        sparse::add_terms(&mut update, &terms);
+        updates.push(update);
+    }
+
+    state.index.delete_many_by_id(doc_ids).await?;
+    state.index.upsert_many(&updates).await?;
+    state.index.save().await?;
+    Ok(updates.len())
+}
+
 /// Helper method used to get the tag ids for a specific crawl result. The tag map and the tag cache
 /// will be modified as results are processed. The tag map contains the url to tag it mapping used
 /// for insertion to the database. The tag_cache is used to avoid additional loops for common tags
@@ -918,6 +918,8 @@ fn _path_to_result(url: &Url, path: &Path) -> Option<CrawlResult> {
             open_url: Some(url.to_string()),
             links: Default::default(),
             tags,
+            last_modified: Some(utils::last_modified_time_for_path(path)),
+            ..Default::default()
         })
     } else {
         None
@@ -218,6 +218,8 @@ async fn start_backend(state: AppState, config: Config) {
 
     let embedding_handler = tokio::spawn(task::embedding_task(state.clone(), worker_cmd_tx));
 
+    let sparse_backfill_handle = tokio::spawn(task::sparse_backfill_task(state.clone()));
+
     // Crawlers
     let worker_handle = tokio::spawn(task::worker_task(
         state.clone(),
@@ -282,5 +284,6 @@ async fn start_backend(state: AppState, config: Config) {
         lens_watcher_handle,
         config_handle,
         embedding_handler,
+        sparse_backfill_handle,
     );
 }
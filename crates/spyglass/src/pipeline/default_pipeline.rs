@@ -142,8 +142,8 @@ async fn start_crawl(
                                         url: url.as_str(),
                                         content: &content,
                                         tags: &[],
-                                        published_at: None,
-                                        last_modified: None,
+                                        published_at: crawl_result.published_at,
+                                        last_modified: crawl_result.last_modified,
                                     }
                                     .to_document(),
                                 )
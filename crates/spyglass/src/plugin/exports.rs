@@ -10,7 +10,7 @@ use entities::sea_orm::ModelTrait;
 use entities::sea_orm::QueryFilter;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
-use spyglass_searcher::{RetrievedDocument, WriteTrait};
+use spyglass_searcher::{Boost, RetrievedDocument, WriteTrait};
 use std::path::Path;
 use std::str::FromStr;
 use tokio::sync::mpsc::Sender;
@@ -165,7 +165,10 @@ async fn handle_plugin_cmd_request(
                 .app_state
                 .index
                 .search_by_query(
-                    documents.urls.clone(),
+                    documents
+                        .urls
+                        .clone()
+                        .map(|urls| urls.into_iter().map(Boost::Url).collect()),
                     documents.ids.clone(),
                     &tag_ids,
                     &exclude_tags,
@@ -323,7 +326,10 @@ async fn query_documents_and_send(env: &PluginEnv, query: &DocumentQuery, send_e
         .app_state
         .index
         .search_by_query(
-            query.urls.clone(),
+            query
+                .urls
+                .clone()
+                .map(|urls| urls.into_iter().map(Boost::Url).collect()),
             query.ids.clone(),
             &tag_ids,
             &exclude_tags,
@@ -3,10 +3,18 @@ use dashmap::DashMap;
 use entities::models::create_connection;
 use entities::sea_orm::DatabaseConnection;
 use spyglass_llm::LlmClient;
-use spyglass_model_interface::embedding_api::EmbeddingApi;
+use spyglass_model_interface::chunker::ChunkOptions;
+use spyglass_model_interface::embedding_api::{
+    EmbeddingApi, EmbeddingModelOptions, EmbeddingProvider,
+};
+use spyglass_model_interface::remote_embedding_api::RemoteEmbeddingApi;
+use spyglass_model_interface::rerank_api::RerankApi;
+use spyglass_model_interface::sparse_api::SparseApi;
+use spyglass_model_interface::{load_tokenizer, Pool};
 use spyglass_rpc::RpcEvent;
 use spyglass_searcher::schema::DocFields;
 use spyglass_searcher::schema::SearchDocument;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tantivy::schema::Schema;
 use tokio::sync::mpsc::error::SendError;
@@ -14,14 +22,18 @@ use tokio::sync::Mutex;
 use tokio::sync::{broadcast, mpsc};
 
 use crate::filesystem::SpyglassFileWatcher;
-use crate::task::{AppShutdown, UserSettingsChange};
+use crate::task::{AppShutdown, UserSettingsChange, RERANK_MODEL_FILES, SPARSE_MODEL_FILES};
 use crate::{
     pipeline::PipelineCommand,
     task::{AppPause, ManagerCommand},
 };
-use shared::config::{Config, LensConfig, PipelineConfiguration, UserSettings};
+use shared::config::{
+    Config, EmbeddingBackend, EmbeddingModel, EmbeddingPooling, LensConfig, PipelineConfiguration,
+    UserSettings,
+};
 use shared::metrics::Metrics;
-use spyglass_searcher::{client::Searcher, IndexBackend};
+use spyglass_searcher::{client::IndexClient, IndexBackend};
+use url::Url;
 
 /// Used to track inflight requests and limit things
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -58,14 +70,20 @@ impl FetchLimitType {
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
-    pub embedding_api: Arc<ArcSwap<Option<EmbeddingApi>>>,
+    pub embedding_api: Arc<ArcSwap<Option<Box<dyn EmbeddingProvider>>>>,
+    // Cross-encoder used to rerank the top search results, if enabled
+    pub reranker: Arc<ArcSwap<Option<RerankApi>>>,
+    // SPLADE model used to expand documents & queries, if sparse retrieval is enabled
+    pub sparse_api: Arc<ArcSwap<Option<SparseApi>>>,
     pub app_state: Arc<DashMap<String, String>>,
     pub lenses: Arc<DashMap<String, LensConfig>>,
     pub pipelines: Arc<DashMap<String, PipelineConfiguration>>,
     pub user_settings: Arc<ArcSwap<UserSettings>>,
-    pub index: Searcher,
+    pub index: IndexClient,
     // Language model client. lazy loaded.
     pub llm: Arc<Mutex<Option<LlmClient>>>,
+    // Cancel flags for the chats in progress, keyed by chat id
+    pub chats: Arc<DashMap<String, Arc<AtomicBool>>>,
     pub metrics: Metrics,
     pub config: Config,
     // Task scheduler command/control
@@ -96,7 +114,7 @@ impl AppState {
         AppStateBuilder::new()
             .with_db(db)
             .with_index(
-                &IndexBackend::LocalPath(config.index_dir()),
+                &index_backend(config),
                 DocFields::as_schema(),
                 readonly_mode,
             )
@@ -128,6 +146,16 @@ impl AppState {
         self.embedding_api.store(Arc::new(embedding_api));
     }
 
+    pub fn reload_reranker(&mut self) {
+        let reranker = load_reranker(self.user_settings.load_full().as_ref());
+        self.reranker.store(Arc::new(reranker));
+    }
+
+    pub fn reload_sparse_model(&mut self) {
+        let sparse_api = load_sparse_model(self.user_settings.load_full().as_ref());
+        self.sparse_api.store(Arc::new(sparse_api));
+    }
+
     pub fn builder() -> AppStateBuilder {
         AppStateBuilder::new()
     }
@@ -156,7 +184,7 @@ impl AppState {
 #[derive(Default)]
 pub struct AppStateBuilder {
     db: Option<DatabaseConnection>,
-    index: Option<Searcher>,
+    index: Option<IndexClient>,
     lenses: Option<Vec<LensConfig>>,
     pipelines: Option<Vec<PipelineConfiguration>>,
     user_settings: Option<UserSettings>,
@@ -182,7 +210,7 @@ impl AppStateBuilder {
         let index = if let Some(index) = &self.index {
             index.to_owned()
         } else {
-            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            IndexClient::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
                 .expect("Unable to open search index")
         };
 
@@ -193,6 +221,8 @@ impl AppStateBuilder {
         };
 
         let embedding_api = load_model(&user_settings);
+        let reranker = load_reranker(&user_settings);
+        let sparse_api = load_sparse_model(&user_settings);
 
         let (shutdown_tx, _) = broadcast::channel::<AppShutdown>(16);
         let (config_tx, _) = broadcast::channel::<UserSettingsChange>(16);
@@ -204,6 +234,7 @@ impl AppStateBuilder {
             index,
             lenses: Arc::new(lenses),
             llm: Arc::new(Mutex::new(None)),
+            chats: Arc::new(DashMap::new()),
             manager_cmd_tx: Arc::new(Mutex::new(None)),
             metrics: Metrics::new(
                 &Config::machine_identifier(),
@@ -221,6 +252,8 @@ impl AppStateBuilder {
             fetch_limits: Arc::new(DashMap::new()),
             readonly_mode: self.readonly_mode.unwrap_or_default(),
             embedding_api: Arc::new(ArcSwap::from_pointee(embedding_api)),
+            reranker: Arc::new(ArcSwap::from_pointee(reranker)),
+            sparse_api: Arc::new(ArcSwap::from_pointee(sparse_api)),
         }
     }
 
@@ -260,7 +293,7 @@ impl AppStateBuilder {
             }
         }
 
-        let searcher = Searcher::with_index(index, schema, readonly);
+        let searcher = IndexClient::with_index(index, schema, readonly);
         if let Err(error) = &searcher {
             log::error!("Error connecting to index {index:?}. Error: {error:?}");
         }
@@ -270,33 +303,167 @@ impl AppStateBuilder {
     }
 }
 
-fn load_model(user_settings: &UserSettings) -> Option<EmbeddingApi> {
+/// Index server shared w/ other daemons if one is configured, otherwise the local
+/// index in the data directory.
+fn index_backend(config: &Config) -> IndexBackend {
+    let remote_url = config.user_settings.remote_index_url.trim();
+    if remote_url.is_empty() {
+        return IndexBackend::LocalPath(config.index_dir());
+    }
+
+    match Url::parse(remote_url) {
+        Ok(url) => IndexBackend::Http(url),
+        Err(error) => {
+            log::error!("Invalid remote index URL {remote_url}, using the local index. {error}");
+            IndexBackend::LocalPath(config.index_dir())
+        }
+    }
+}
+
+fn load_model(user_settings: &UserSettings) -> Option<Box<dyn EmbeddingProvider>> {
     if user_settings.embedding_settings.enable_embeddings {
-        let mut model_root = user_settings.data_directory.clone();
-        model_root.push("models");
-        model_root.push("embeddings");
+        let settings = &user_settings.embedding_settings;
+        let mut embeddings_dir = user_settings.data_directory.clone();
+        embeddings_dir.push("models");
+        embeddings_dir.push("embeddings");
+        let model_root = settings.model.model_dir(&embeddings_dir);
 
         let mut tokenizer_file = model_root.clone();
         tokenizer_file.push("tokenizer.json");
         let mut model = model_root.clone();
         model.push("model.safetensors");
 
-        if tokenizer_file.exists() && model.exists() {
-            match EmbeddingApi::new(model_root.clone()) {
-                Ok(embedding_api) => {
-                    log::info!("Embedding Model Loaded");
-                    Some(embedding_api)
+        let chunk_opts = ChunkOptions {
+            window: settings.chunk_size as usize,
+            overlap: settings.chunk_overlap as usize,
+        };
+
+        match settings.backend {
+            EmbeddingBackend::Local if tokenizer_file.exists() && model.exists() => {
+                match EmbeddingApi::new(model_root.clone(), embedding_model_options(settings.model))
+                {
+                    Ok(embedding_api) => {
+                        log::info!("Embedding Model {} Loaded", settings.model);
+                        Some(Box::new(
+                            embedding_api
+                                .with_chunk_options(chunk_opts)
+                                .with_max_batch_size(settings.local_batch_size as usize),
+                        ))
+                    }
+                    Err(error) => {
+                        log::error!("Error Loading Embedding Model {:?}", error);
+                        None
+                    }
                 }
-                Err(error) => {
-                    log::error!("Error Loading Embedding Model {:?}", error);
-                    None
+            }
+            // Only the tokenizer is needed to split content into chunks
+            EmbeddingBackend::Remote if tokenizer_file.exists() => {
+                if settings.remote_url.trim().is_empty() {
+                    log::warn!("No remote embedding URL configured");
+                    return None;
+                }
+
+                match load_tokenizer(&model_root) {
+                    Ok(tokenizer) => {
+                        log::info!("Using Remote Embeddings @ {}", settings.remote_url);
+                        let api = RemoteEmbeddingApi::new(
+                            &settings.remote_url,
+                            &settings.remote_model_name(),
+                            tokenizer,
+                            embedding_model_options(settings.model),
+                        )
+                        .with_chunk_options(chunk_opts)
+                        .with_batch_size(settings.remote_batch_size as usize)
+                        .with_dimensions(settings.model.dimensions());
+                        Some(Box::new(api))
+                    }
+                    Err(error) => {
+                        log::error!("Error Loading Embedding Tokenizer {:?}", error);
+                        None
+                    }
                 }
             }
-        } else {
-            log::warn!("Model does not exist");
-            None
+            _ => {
+                log::warn!("Model does not exist");
+                None
+            }
         }
     } else {
         None
     }
 }
+
+fn load_reranker(user_settings: &UserSettings) -> Option<RerankApi> {
+    let settings = &user_settings.search_settings;
+    if !settings.enable_reranking {
+        return None;
+    }
+
+    let mut rerank_dir = user_settings.data_directory.clone();
+    rerank_dir.push("models");
+    rerank_dir.push("rerank");
+    let model_root = settings.rerank_model.model_dir(&rerank_dir);
+
+    let missing = RERANK_MODEL_FILES
+        .iter()
+        .any(|(file, _)| !model_root.join(file).exists());
+    if missing {
+        log::warn!("Reranking model does not exist");
+        return None;
+    }
+
+    match RerankApi::new(model_root) {
+        Ok(reranker) => {
+            log::info!("Reranking Model {} Loaded", settings.rerank_model);
+            Some(reranker)
+        }
+        Err(error) => {
+            log::error!("Error Loading Reranking Model {:?}", error);
+            None
+        }
+    }
+}
+
+fn load_sparse_model(user_settings: &UserSettings) -> Option<SparseApi> {
+    let settings = &user_settings.search_settings;
+    if !settings.enable_sparse_retrieval {
+        return None;
+    }
+
+    let mut sparse_dir = user_settings.data_directory.clone();
+    sparse_dir.push("models");
+    sparse_dir.push("sparse");
+    let model_root = settings.sparse_model.model_dir(&sparse_dir);
+
+    let missing = SPARSE_MODEL_FILES
+        .iter()
+        .any(|(file, _)| !model_root.join(file).exists());
+    if missing {
+        log::warn!("Sparse retrieval model does not exist");
+        return None;
+    }
+
+    match SparseApi::new(model_root) {
+        Ok(sparse_api) => {
+            log::info!("Sparse Retrieval Model {} Loaded", settings.sparse_model);
+            Some(sparse_api)
+        }
+        Err(error) => {
+            log::error!("Error Loading Sparse Retrieval Model {:?}", error);
+            None
+        }
+    }
+}
+
+/// Prefixes, pooling & token limit used w/ an embedding model
+pub fn embedding_model_options(model: EmbeddingModel) -> EmbeddingModelOptions {
+    EmbeddingModelOptions {
+        pool: match model.pooling() {
+            EmbeddingPooling::Cls => Pool::Cls,
+            EmbeddingPooling::Mean => Pool::Mean,
+        },
+        document_prefix: model.document_prefix().to_string(),
+        query_prefix: model.query_prefix().to_string(),
+        max_tokens: model.max_tokens(),
+    }
+}
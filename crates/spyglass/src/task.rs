@@ -1,14 +1,17 @@
 use anyhow::anyhow;
 use entities::models::crawl_queue::CrawlStatus;
 use entities::models::{
-    bootstrap_queue, connection, crawl_queue, embedding_queue, indexed_document,
+    bootstrap_queue, connection, crawl_queue, embedding_queue, embedding_rebuild, indexed_document,
+    sparse_backfill, vec_documents,
 };
 use entities::sea_orm::Set;
 use entities::sea_orm::{sea_query::Expr, ColumnTrait, Condition, EntityTrait, QueryFilter};
 use futures::StreamExt;
 use notify::event::ModifyKind;
 use notify::{EventKind, RecursiveMode, Watcher};
-use shared::config::{Config, LensConfig, UserSettings, UserSettingsDiff};
+use shared::config::{
+    Config, EmbeddingBackend, EmbeddingModel, LensConfig, UserSettings, UserSettingsDiff,
+};
 use spyglass_rpc::{ModelDownloadStatusPayload, RpcEvent, RpcEventType};
 use std::collections::HashMap;
 use std::fs::File;
@@ -22,7 +25,7 @@ use tokio::sync::{broadcast, mpsc};
 
 use crate::connection::{api_id_to_label, load_connection};
 use crate::crawler::bootstrap;
-use crate::documents::embeddings;
+use crate::documents::{self, embeddings};
 use crate::filesystem;
 use crate::state::AppState;
 use crate::task::worker::FetchResult;
@@ -35,6 +38,30 @@ mod manager;
 pub mod worker;
 use lens::{load_lenses, read_lenses};
 
+/// Max number of documents expanded together when adding sparse terms to the
+/// documents already in the index
+const SPARSE_BACKFILL_BATCH_SIZE: u64 = 16;
+/// Files downloaded for an embedding model & the name shown while downloading
+const EMBEDDING_MODEL_FILES: [(&str, &str); 3] = [
+    ("model.safetensors", "Embedding Model"),
+    ("config.json", "Embedding Model Config"),
+    ("tokenizer.json", "Embedding Model Tokenizer"),
+];
+
+/// Files needed to run a reranking model
+pub const RERANK_MODEL_FILES: [(&str, &str); 3] = [
+    ("model.safetensors", "Reranking Model"),
+    ("config.json", "Reranking Model Config"),
+    ("tokenizer.json", "Reranking Model Tokenizer"),
+];
+
+/// Files needed to run a SPLADE model, only published as PyTorch weights
+pub const SPARSE_MODEL_FILES: [(&str, &str); 3] = [
+    ("pytorch_model.bin", "Sparse Retrieval Model"),
+    ("config.json", "Sparse Retrieval Model Config"),
+    ("tokenizer.json", "Sparse Retrieval Model Tokenizer"),
+];
+
 #[derive(Debug, Clone)]
 pub struct CrawlTask {
     pub id: i64,
@@ -100,9 +127,9 @@ pub enum WorkerCommand {
     /// Updates the document store for indexed document database table to
     /// cleanup inconsistencies
     CleanupDatabase(CleanupTask),
-    // Generates an embedding for a document
+    // Generates the embeddings for a group of documents
     Embedding {
-        id: i64,
+        ids: Vec<i64>,
     },
 }
 
@@ -194,16 +221,20 @@ pub async fn embedding_task(state: AppState, queue: mpsc::Sender<WorkerCommand>)
     let mut queue_check_interval = tokio::time::interval(Duration::from_millis(500));
     let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
 
+    // Picks up model changes made while the app wasn't running
+    sync_embeddings(&state).await;
+
     // first is always instant
     queue_check_interval.tick().await;
     loop {
         tokio::select! {
             // Listen for manager level commands. This can be sent internally (i.e. CheckForJobs) or
             // externally (e.g. Collect)
-            job = embedding_queue::check_for_embedding_jobs(&state.db) => {
-                match job {
-                    Ok(Some(job)) => {
-                        let _ = queue.send(WorkerCommand::Embedding{id: job.id}).await;
+            jobs = embedding_queue::check_for_embedding_jobs(&state.db, embedding_batch_size(&state)) => {
+                match jobs {
+                    Ok(jobs) if !jobs.is_empty() => {
+                        let ids = jobs.into_iter().map(|job| job.id).collect();
+                        let _ = queue.send(WorkerCommand::Embedding { ids }).await;
                         queue_check_interval.tick().await;
                     }
                     Err(error) => {
@@ -211,6 +242,11 @@ pub async fn embedding_task(state: AppState, queue: mpsc::Sender<WorkerCommand>)
                         queue_check_interval.tick().await;
                     }
                     _ => {
+                        match embedding_rebuild::complete_if_done(&state.db).await {
+                            Ok(true) => log::info!("Finished rebuilding embeddings"),
+                            Ok(false) => {}
+                            Err(error) => log::error!("Error updating embedding rebuild {:?}", error),
+                        }
                         queue_check_interval.tick().await;
                     }
                 }
@@ -223,6 +259,31 @@ pub async fn embedding_task(state: AppState, queue: mpsc::Sender<WorkerCommand>)
     }
 }
 
+/// Adds SPLADE expansion terms to the documents already in the index, a batch at
+/// a time, once sparse retrieval is turned on or switched to another model.
+#[tracing::instrument(skip_all)]
+pub async fn sparse_backfill_task(state: AppState) {
+    log::info!("Sparse Backfill Task Started");
+
+    let mut check_interval = tokio::time::interval(Duration::from_secs(1));
+    let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
+
+    // Picks up a model that changed while the app wasn't running
+    sync_sparse_terms(&state).await;
+
+    loop {
+        tokio::select! {
+            _ = check_interval.tick() => {
+                backfill_sparse_terms(&state).await;
+            }
+            _ = shutdown_rx.recv() => {
+                log::info!("🛑 Shutting down sparse backfill");
+                return;
+            }
+        };
+    }
+}
+
 /// Manages changes to the user's settings
 #[tracing::instrument(skip_all)]
 pub async fn config_task(mut state: AppState) {
@@ -290,39 +351,114 @@ pub async fn config_task(mut state: AppState) {
                         }
 
                         if new_settings.embedding_settings.enable_embeddings {
-                            let model_dir = state.config.embedding_model_dir();
-                            let model_path = state.config.embedding_model_dir().join("model.safetensors");
-                            let tokenizer_path = state.config.embedding_model_dir().join("tokenizer.json");
-                            let model_config_path = state.config.embedding_model_dir().join("config.json");
-                            if !model_path.exists() || !tokenizer_path.exists() || !model_config_path.exists() {
+                            let model = new_settings.embedding_settings.model;
+                            let model_dir = state.config.embedding_model_path(model);
+                            // Remote embeddings only need the tokenizer to split content into chunks
+                            let backend = new_settings.embedding_settings.backend;
+                            let missing_files = EMBEDDING_MODEL_FILES
+                                .iter()
+                                .filter(|(file, _)| backend == EmbeddingBackend::Local || *file == "tokenizer.json")
+                                .filter(|(file, _)| !model_dir.join(file).exists())
+                                .copied()
+                                .collect::<Vec<_>>();
+                            if !missing_files.is_empty() {
                                 log::debug!("Loading Embedding Models...");
                                 let mut state_clone = state.clone();
 
                                 if !model_dir.exists() {
-                                    let _ = std::fs::create_dir_all(model_dir);
+                                    let _ = std::fs::create_dir_all(&model_dir);
                                 }
 
                                 tokio::spawn(async move {
-
-                                    if let Err(error) = download_model(&state_clone, "Embedding Model", model_path, shared::constants::EMBEDDING_MODEL).await {
-                                        log::error!("Error downloading Embedding model {:?}", error);
-                                    }
-                                    if let Err(error) = download_model(&state_clone, "Embedding Model Config", model_config_path, shared::constants::EMBEDDING_MODEL_CONFIG).await {
-                                        log::error!("Error downloading Embedding model config {:?}", error);
-                                    }
-                                    if let Err(error) = download_model(&state_clone, "Embedding Model Tokenizer", tokenizer_path, shared::constants::EMBEDDING_MODEL_TOKENIZER).await {
-                                        log::error!("Error downloading Embedding model tokenizer config {:?}", error);
+                                    for (file, label) in missing_files {
+                                        if let Err(error) = download_model(&state_clone, label, model_dir.join(file), &model.file_url(file)).await {
+                                            log::error!("Error downloading {} {:?}", label, error);
+                                        }
                                     }
 
                                     state_clone.reload_model();
 
-                                    add_missing_embeddings(&state_clone).await;
+                                    sync_embeddings(&state_clone).await;
                                 });
                             } else {
                                 state.reload_model();
-                                add_missing_embeddings(&state).await;
+                                sync_embeddings(&state).await;
                             }
                         }
+
+                        // Reranking model changed or toggled?
+                        let rerank = &new_settings.search_settings;
+                        let rerank_changed = rerank.enable_reranking != old_config.search_settings.enable_reranking
+                            || rerank.rerank_model != old_config.search_settings.rerank_model;
+                        if rerank_changed && rerank.enable_reranking {
+                            let model = rerank.rerank_model;
+                            let model_dir = state.config.rerank_model_path(model);
+                            let missing_files = RERANK_MODEL_FILES
+                                .iter()
+                                .filter(|(file, _)| !model_dir.join(file).exists())
+                                .copied()
+                                .collect::<Vec<_>>();
+                            if !missing_files.is_empty() {
+                                log::debug!("Loading Reranking Model...");
+                                let mut state_clone = state.clone();
+                                let _ = std::fs::create_dir_all(&model_dir);
+                                tokio::spawn(async move {
+                                    for (file, label) in missing_files {
+                                        if let Err(error) = download_model(&state_clone, label, model_dir.join(file), &model.file_url(file)).await {
+                                            log::error!("Error downloading {} {:?}", label, error);
+                                        }
+                                    }
+
+                                    state_clone.reload_reranker();
+                                });
+                            } else {
+                                state.reload_reranker();
+                            }
+                        } else if rerank_changed {
+                            state.reload_reranker();
+                        }
+
+                        // Sparse retrieval model changed or toggled?
+                        let sparse = &new_settings.search_settings;
+                        let sparse_changed = sparse.enable_sparse_retrieval != old_config.search_settings.enable_sparse_retrieval
+                            || sparse.sparse_model != old_config.search_settings.sparse_model;
+                        if sparse_changed && sparse.enable_sparse_retrieval {
+                            let model = sparse.sparse_model;
+                            let model_dir = state.config.sparse_model_path(model);
+                            let missing_files = SPARSE_MODEL_FILES
+                                .iter()
+                                .filter(|(file, _)| !model_dir.join(file).exists())
+                                .copied()
+                                .collect::<Vec<_>>();
+                            if !missing_files.is_empty() {
+                                log::debug!("Loading Sparse Retrieval Model...");
+                                let mut state_clone = state.clone();
+                                let _ = std::fs::create_dir_all(&model_dir);
+                                tokio::spawn(async move {
+                                    for (file, label) in missing_files {
+                                        if let Err(error) = download_model(&state_clone, label, model_dir.join(file), &model.file_url(file)).await {
+                                            log::error!("Error downloading {} {:?}", label, error);
+                                        }
+                                    }
+
+                                    state_clone.reload_sparse_model();
+                                    start_sparse_backfill(&state_clone).await;
+                                });
+                            } else {
+                                state.reload_sparse_model();
+                                start_sparse_backfill(&state).await;
+                            }
+                        } else if sparse_changed {
+                            state.reload_sparse_model();
+                        }
+
+                        // Chat model or prompt format changed? It's loaded again on the next chat.
+                        let llm = &new_settings.llm_settings;
+                        if llm.model_path != old_config.llm_settings.model_path
+                            || llm.chat_template != old_config.llm_settings.chat_template
+                        {
+                            *state.llm.lock().await = None;
+                        }
                     }
                 }
             }
@@ -334,6 +470,152 @@ pub async fn config_task(mut state: AppState) {
     }
 }
 
+/// Max number of documents embedded together
+fn embedding_batch_size(state: &AppState) -> u64 {
+    let settings = state.user_settings.load_full();
+    settings.embedding_settings.document_batch_size.max(1) as u64
+}
+
+/// Queues any documents missing embeddings. If the stored embeddings were generated
+/// w/ a different model than the one picked in the settings, including a different
+/// model on the remote embedding service, or are stored in a different format or
+/// size, they're dropped first & the rebuild is tracked until every document is
+/// embedded again.
+async fn sync_embeddings(state: &AppState) {
+    let settings = state.user_settings.load_full();
+    if !settings.embedding_settings.enable_embeddings {
+        return;
+    }
+
+    let model = settings.embedding_settings.model;
+    let model_name = settings.embedding_settings.effective_model_name();
+    let quantization = settings.embedding_settings.quantization;
+    let current = embedding_rebuild::current_model(&state.db).await;
+    let format = vec_documents::stored_format(&state.db).await;
+    let rebuild = match (current, format) {
+        (Ok(current), Ok(format)) => {
+            // Embeddings that were never rebuilt are from the default model
+            let current = current.unwrap_or_else(|| EmbeddingModel::default().to_string());
+            let changed = current != model_name
+                || format.map(|format| (format.quantization, format.dimensions))
+                    != Some((quantization, model.dimensions()));
+            // Only drop the old embeddings once the new model can replace them
+            changed && state.embedding_api.load().is_some()
+        }
+        (Err(error), _) | (_, Err(error)) => {
+            log::error!("Unable to check embedding model {:?}", error);
+            false
+        }
+    };
+
+    if rebuild {
+        log::info!("Rebuilding embeddings w/ {} ({})", model_name, quantization);
+        if let Err(error) =
+            embeddings::reset_embeddings(state, model.dimensions(), quantization).await
+        {
+            log::error!("Error resetting embeddings {:?}", error);
+            return;
+        }
+    }
+
+    add_missing_embeddings(state).await;
+
+    if rebuild {
+        let num_docs = embedding_queue::count_pending(&state.db)
+            .await
+            .unwrap_or_default();
+        if let Err(error) =
+            embedding_rebuild::start(&state.db, &model_name, model.dimensions(), num_docs).await
+        {
+            log::error!("Error tracking embedding rebuild {:?}", error);
+        }
+    }
+}
+
+/// Starts a backfill if sparse retrieval is on & the documents in the index were
+/// never expanded w/ the configured model.
+async fn sync_sparse_terms(state: &AppState) {
+    let settings = state.user_settings.load_full();
+    if !settings.search_settings.enable_sparse_retrieval {
+        return;
+    }
+
+    let model = settings.search_settings.sparse_model.to_string();
+    match sparse_backfill::current_model(&state.db).await {
+        Ok(Some(current)) if current == model => {}
+        Ok(_) => start_sparse_backfill(state).await,
+        Err(error) => log::error!("Unable to check sparse backfill {:?}", error),
+    }
+}
+
+/// Expands every document in the index again w/ the configured sparse model,
+/// replacing any backfill that is still running.
+async fn start_sparse_backfill(state: &AppState) {
+    let model = state.user_settings.load_full().search_settings.sparse_model;
+    match sparse_backfill::start(&state.db, &model.to_string()).await {
+        Ok(backfill) => log::info!(
+            "Adding sparse terms w/ {} to {} documents",
+            model,
+            backfill.num_docs
+        ),
+        Err(error) => log::error!("Error starting sparse backfill {:?}", error),
+    }
+}
+
+/// Expands the next batch of documents for the running backfill. Waits while the
+/// sparse model is still loading.
+async fn backfill_sparse_terms(state: &AppState) {
+    let settings = state.user_settings.load_full();
+    if !settings.search_settings.enable_sparse_retrieval || state.sparse_api.load().is_none() {
+        return;
+    }
+
+    let backfill = match sparse_backfill::in_progress(&state.db).await {
+        Ok(Some(backfill)) => backfill,
+        Ok(None) => return,
+        Err(error) => {
+            log::error!("Unable to check sparse backfill {:?}", error);
+            return;
+        }
+    };
+
+    let docs =
+        match sparse_backfill::next_documents(&state.db, &backfill, SPARSE_BACKFILL_BATCH_SIZE)
+            .await
+        {
+            Ok(docs) => docs,
+            Err(error) => {
+                log::error!("Unable to read documents to expand {:?}", error);
+                return;
+            }
+        };
+
+    let Some(last_document_id) = docs.last().map(|doc| doc.id) else {
+        let model = backfill.model.clone();
+        match sparse_backfill::complete(&state.db, backfill).await {
+            Ok(_) => log::info!("Finished adding sparse terms w/ {}", model),
+            Err(error) => log::error!("Error completing sparse backfill {:?}", error),
+        }
+        return;
+    };
+
+    let doc_ids = docs
+        .iter()
+        .map(|doc| doc.doc_id.clone())
+        .collect::<Vec<_>>();
+    if let Err(error) = documents::add_sparse_terms(state, &doc_ids).await {
+        // Retried on the next check
+        log::error!("Error adding sparse terms {:?}", error);
+        return;
+    }
+
+    if let Err(error) =
+        sparse_backfill::record_progress(&state.db, backfill, last_document_id, docs.len()).await
+    {
+        log::error!("Error updating sparse backfill {:?}", error);
+    }
+}
+
 async fn add_missing_embeddings(state: &AppState) {
     match indexed_document::get_documents_missing_embeddings(&state.db).await {
         Ok(missing_embeddings) => {
@@ -611,8 +893,8 @@ pub async fn worker_task(
                             });
                         }
                         WorkerCommand::Tag => {},
-                        WorkerCommand::Embedding { id } => {
-                            embeddings::trigger_processing_embedding(&state, id).await;
+                        WorkerCommand::Embedding { ids } => {
+                            embeddings::trigger_processing_embedding(&state, ids).await;
                         },
 
                     }